@@ -0,0 +1,677 @@
+use std::mem;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::tts::AudioFormat;
+use audiopus::coder::{Decoder, Encoder};
+use audiopus::{Application, Channels, SampleRate};
+
+/// The sample rates supported by the Opus encoder (in Hz).
+pub const SUPPORTED_SAMPLE_RATES: [u32; 5] = [8_000, 12_000, 16_000, 24_000, 48_000];
+/// The sample rate (in Hz) used unless configured otherwise or preferred by the TTS provider.
+pub const DEFAULT_SAMPLE_RATE: u32 = 16_000;
+/// The number of samples of a 20ms frame at the default sample rate (16kHz, mono).
+pub const FRAME_SIZE: usize = DEFAULT_SAMPLE_RATE as usize * 20 / 1000;
+/// The playtime of a single frame, which is the frame duration SRS expects.
+pub const FRAME_DURATION: Duration = Duration::from_millis(20);
+
+/// Validates that the given sample rate (in Hz) is supported by the Opus encoder.
+pub fn opus_sample_rate(sample_rate: u32) -> Result<SampleRate, anyhow::Error> {
+    match sample_rate {
+        8_000 => Ok(SampleRate::Hz8000),
+        12_000 => Ok(SampleRate::Hz12000),
+        16_000 => Ok(SampleRate::Hz16000),
+        24_000 => Ok(SampleRate::Hz24000),
+        48_000 => Ok(SampleRate::Hz48000),
+        _ => Err(anyhow!(
+            "unsupported sample rate {}Hz, supported are: {}",
+            sample_rate,
+            SUPPORTED_SAMPLE_RATES
+                .iter()
+                .map(|rate| format!("{}Hz", rate))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Returns the number of samples of a 20ms frame (mono) at the given sample rate.
+pub fn frame_size(sample_rate: u32) -> usize {
+    sample_rate as usize * 20 / 1000
+}
+
+/// Returns the number of samples of the given duration (mono) at the given sample rate.
+fn samples(duration: Duration, sample_rate: u32) -> usize {
+    (duration.as_millis() as usize) * sample_rate as usize / 1000
+}
+
+/// Converts the samples from one sample rate to another using linear interpolation. Only used
+/// for TTS providers that cannot synthesize the configured sample rate themselves.
+pub fn resample(pcm: &[i16], from: u32, to: u32) -> Vec<i16> {
+    if from == to || pcm.is_empty() {
+        return pcm.to_vec();
+    }
+
+    let len = (pcm.len() as u64 * u64::from(to) / u64::from(from)) as usize;
+    let step = f64::from(from) / f64::from(to);
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let ix = pos as usize;
+            let a = f64::from(pcm[ix]);
+            let b = f64::from(*pcm.get(ix + 1).unwrap_or(&pcm[ix]));
+            (a + (b - a) * pos.fract()).round() as i16
+        })
+        .collect()
+}
+
+/// The lowest supported station gain (in dB).
+pub const MIN_GAIN: f32 = -20.0;
+/// The highest supported station gain (in dB).
+pub const MAX_GAIN: f32 = 20.0;
+
+/// Parses a gain in dB, e.g. `-3dB`, `+6 dB` or `2`, and validates that it is within the
+/// supported range.
+pub fn parse_gain(s: &str) -> Result<f32, anyhow::Error> {
+    let s = s.trim();
+    let value = if s.len() >= 2 && s[s.len() - 2..].eq_ignore_ascii_case("dB") {
+        s[..s.len() - 2].trim_end()
+    } else {
+        s
+    };
+    let gain = f32::from_str(value).map_err(|_| anyhow!("invalid gain `{}`", s))?;
+    if !gain.is_finite() || gain < MIN_GAIN || gain > MAX_GAIN {
+        return Err(anyhow!(
+            "gain {}dB is out of the supported range of {}dB to {}dB",
+            gain,
+            MIN_GAIN,
+            MAX_GAIN
+        ));
+    }
+    Ok(gain)
+}
+
+/// Converts a gain in dB into the factor the samples are scaled by.
+pub fn gain_factor(gain: f32) -> f32 {
+    10f32.powf(gain / 20.0)
+}
+
+/// Scales the samples by the given gain (in dB). Samples are clamped to the range of an `i16`
+/// instead of letting them overflow.
+pub fn apply_gain(pcm: &mut [i16], gain: f32) {
+    if gain == 0.0 {
+        return;
+    }
+
+    let factor = gain_factor(gain);
+    for sample in pcm {
+        let scaled = (f32::from(*sample) * factor).round();
+        *sample = scaled.max(f32::from(i16::MIN)).min(f32::from(i16::MAX)) as i16;
+    }
+}
+
+/// The amplitude of the background noise at full radio effect intensity.
+const NOISE_AMPLITUDE: f32 = 1200.0;
+/// The amplitude of the squelch clicks at full radio effect intensity.
+const SQUELCH_AMPLITUDE: f32 = 6000.0;
+/// The length of the squelch click at the start of a transmission.
+const SQUELCH_OPEN: Duration = Duration::from_millis(40);
+/// The length of the squelch tail at the end of a transmission.
+const SQUELCH_TAIL: Duration = Duration::from_millis(160);
+
+/// Makes the audio sound like a radio transmission by mixing in background noise and adding
+/// squelch clicks to the start and end of the transmission. The `intensity` ranges from `0.0`
+/// (effects disabled, the audio is left untouched) to `1.0`.
+pub fn apply_radio_effects(pcm: &mut Vec<i16>, intensity: f32, sample_rate: u32) {
+    if intensity <= 0.0 {
+        return;
+    }
+    let intensity = intensity.min(1.0);
+    let squelch_open_samples = samples(SQUELCH_OPEN, sample_rate);
+    let squelch_tail_samples = samples(SQUELCH_TAIL, sample_rate);
+    let mut noise = Noise::new(pcm.len() as u32);
+
+    for sample in pcm.iter_mut() {
+        *sample = mix(*sample, noise.next() * NOISE_AMPLITUDE * intensity);
+    }
+
+    let open = (0..squelch_open_samples)
+        .map(|_| mix(0, noise.next() * SQUELCH_AMPLITUDE * intensity))
+        .collect::<Vec<_>>();
+    pcm.splice(0..0, open);
+
+    // the squelch tail fades out
+    for i in 0..squelch_tail_samples {
+        let fade = 1.0 - i as f32 / squelch_tail_samples as f32;
+        pcm.push(mix(0, noise.next() * SQUELCH_AMPLITUDE * intensity * fade));
+    }
+}
+
+/// The amplitude of the lead-in tone.
+const TONE_AMPLITUDE: f32 = 8000.0;
+/// The length of the fade in and out of the lead-in tone, which avoids clicks.
+const TONE_FADE: Duration = Duration::from_millis(10);
+/// The lowest supported lead-in tone frequency (in Hz).
+pub const MIN_TONE_FREQUENCY: f32 = 100.0;
+/// The highest supported lead-in tone frequency (in Hz), which is still below the Nyquist
+/// frequency of the lowest supported sample rate.
+pub const MAX_TONE_FREQUENCY: f32 = 3_500.0;
+/// The longest supported lead-in tone.
+pub const MAX_TONE_DURATION: Duration = Duration::from_secs(2);
+
+/// A short attention tone that is played before each report.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LeadInTone {
+    /// in Hz
+    pub frequency: f32,
+    pub duration: Duration,
+}
+
+impl LeadInTone {
+    /// Validates that the frequency and duration of the tone are within the supported ranges.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if !self.frequency.is_finite()
+            || self.frequency < MIN_TONE_FREQUENCY
+            || self.frequency > MAX_TONE_FREQUENCY
+        {
+            return Err(anyhow!(
+                "lead-in tone frequency {}Hz is out of the supported range of {}Hz to {}Hz",
+                self.frequency,
+                MIN_TONE_FREQUENCY,
+                MAX_TONE_FREQUENCY
+            ));
+        }
+        if self.duration == Duration::from_secs(0) || self.duration > MAX_TONE_DURATION {
+            return Err(anyhow!(
+                "lead-in tone duration {}ms is out of the supported range of 1ms to {}ms",
+                self.duration.as_millis(),
+                MAX_TONE_DURATION.as_millis()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Parses a lead-in tone given as `{frequency in Hz}/{duration in ms}`, e.g. `1000/300`.
+pub fn parse_lead_in_tone(s: &str) -> Result<LeadInTone, anyhow::Error> {
+    let invalid = || anyhow!("invalid lead-in tone `{}`, expected e.g. `1000/300`", s);
+    let mut parts = s.trim().splitn(2, '/');
+    let frequency = parts.next().unwrap_or("").trim();
+    let duration = parts.next().ok_or_else(invalid)?.trim();
+    let tone = LeadInTone {
+        frequency: f32::from_str(frequency).map_err(|_| invalid())?,
+        duration: Duration::from_millis(u64::from_str(duration).map_err(|_| invalid())?),
+    };
+    tone.validate()?;
+    Ok(tone)
+}
+
+/// Prepends the given tone to the audio. The tone fades in and out to avoid clicks.
+pub fn prepend_tone(pcm: &mut Vec<i16>, tone: LeadInTone, sample_rate: u32) {
+    let len = samples(tone.duration, sample_rate);
+    let fade = samples(TONE_FADE, sample_rate).min(len / 2).max(1);
+    let step = 2.0 * std::f32::consts::PI * tone.frequency / sample_rate as f32;
+
+    let tone = (0..len)
+        .map(|i| {
+            let envelope = (i.min(len - 1 - i) as f32 / fade as f32).min(1.0);
+            mix(0, (i as f32 * step).sin() * TONE_AMPLITUDE * envelope)
+        })
+        .collect::<Vec<_>>();
+    pcm.splice(0..0, tone);
+}
+
+fn mix(sample: i16, add: f32) -> i16 {
+    (f32::from(sample) + add)
+        .round()
+        .max(f32::from(i16::MIN))
+        .min(f32::from(i16::MAX)) as i16
+}
+
+/// A simple (xorshift) pseudo random noise generator. The same seed always generates the same
+/// noise, which is fine as the noise only has to sound random.
+struct Noise(u32);
+
+impl Noise {
+    fn new(seed: u32) -> Self {
+        Noise(seed | 1)
+    }
+
+    /// Returns the next noise value in the range of `-1.0..=1.0`.
+    fn next(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+/// Opus encoded audio, ready to be sent to SRS.
+pub struct OpusAudio {
+    pub frames: Vec<Vec<u8>>,
+    /// The playtime of all frames.
+    pub duration: Duration,
+}
+
+/// Returns the playtime of the given number of frames.
+pub fn frames_duration(frames: usize) -> Duration {
+    FRAME_DURATION * frames as u32
+}
+
+/// Encodes mono PCM of the given sample rate into 20ms Opus frames. The last frame is padded
+/// with silence.
+pub fn encode_opus(pcm: &[i16], sample_rate: u32) -> Result<OpusAudio, anyhow::Error> {
+    let enc = Encoder::new(
+        opus_sample_rate(sample_rate)?,
+        Channels::Mono,
+        Application::Voip,
+    )?;
+    let frame_size = frame_size(sample_rate);
+    let mut output = [0; 256];
+    let mut frames = Vec::with_capacity(pcm.len() / frame_size + 1);
+
+    for chunk in pcm.chunks(frame_size) {
+        let len = if chunk.len() < frame_size {
+            let mut padded = vec![0; frame_size];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            enc.encode(&padded, &mut output)?
+        } else {
+            enc.encode(chunk, &mut output)?
+        };
+        frames.push(output[..len].to_vec());
+    }
+
+    Ok(OpusAudio {
+        duration: frames_duration(frames.len()),
+        frames,
+    })
+}
+
+/// Reads 16bit little-endian samples. If the data is a WAV file, only the samples of its `data`
+/// chunk are returned.
+pub fn pcm_from_bytes(data: &[u8]) -> Vec<i16> {
+    let data = wav_data_chunk(data).unwrap_or(data);
+    data.chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect()
+}
+
+fn wav_data_chunk(data: &[u8]) -> Option<&[u8]> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]])
+            as usize;
+        let start = pos + 8;
+        if id == b"data" {
+            return Some(&data[start..data.len().min(start + size)]);
+        }
+        // chunks are padded to an even size
+        pos = start + size + size % 2;
+    }
+
+    None
+}
+
+/// The maximum Opus frame duration is 120ms, which are 5760 samples per channel at 48kHz.
+const MAX_OPUS_FRAME_SIZE: usize = 5760;
+
+/// Decodes the audio synthesized by a TTS provider into mono PCM of the given sample rate.
+pub fn decode(
+    data: &[u8],
+    format: AudioFormat,
+    sample_rate: u32,
+) -> Result<Vec<i16>, anyhow::Error> {
+    match format {
+        AudioFormat::Pcm => Ok(pcm_from_bytes(data)),
+        AudioFormat::OggOpus => decode_ogg_opus(data, sample_rate),
+        AudioFormat::Mp3 => decode_mp3(data, sample_rate),
+    }
+}
+
+fn decode_ogg_opus(data: &[u8], sample_rate: u32) -> Result<Vec<i16>, anyhow::Error> {
+    let packets = ogg_packets(data)?;
+    // the first packet is the ID header and the second one contains the comments
+    let head = match packets.first() {
+        Some(head) if head.len() >= 19 && head.starts_with(b"OpusHead") => head,
+        _ => return Err(anyhow!("Ogg stream does not contain Opus audio")),
+    };
+    let channels = match head[9] {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        n => return Err(anyhow!("unsupported number of Opus channels: {}", n)),
+    };
+    // the number of samples (at 48kHz) to discard from the start of the decoded audio
+    let pre_skip = u16::from_le_bytes([head[10], head[11]]) as usize;
+
+    let mut decoder = Decoder::new(opus_sample_rate(sample_rate)?, channels)?;
+    let mut output = vec![0; MAX_OPUS_FRAME_SIZE * channels as usize];
+    let mut pcm = Vec::new();
+    for packet in packets.iter().skip(2) {
+        let len = decoder.decode(Some(&packet[..]), &mut output, false)?;
+        pcm.extend_from_slice(&output[..len * channels as usize]);
+    }
+
+    let pcm = match channels {
+        Channels::Stereo => downmix(&pcm),
+        _ => pcm,
+    };
+    let skip = pre_skip * sample_rate as usize / 48_000;
+    Ok(pcm.get(skip..).unwrap_or_default().to_vec())
+}
+
+/// Extracts the packets of an Ogg stream. Only streams with a single logical bitstream (as
+/// returned by the TTS providers) are supported.
+fn ogg_packets(data: &[u8]) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+    let mut packets = Vec::new();
+    let mut packet = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        if data.len() < pos + 27 || &data[pos..pos + 4] != b"OggS" {
+            return Err(anyhow!("invalid Ogg page at byte {}", pos));
+        }
+        let segment_count = data[pos + 26] as usize;
+        let lacing = data
+            .get(pos + 27..pos + 27 + segment_count)
+            .ok_or_else(|| anyhow!("truncated Ogg page at byte {}", pos))?;
+        pos += 27 + segment_count;
+
+        for len in lacing.iter().map(|len| *len as usize) {
+            let segment = data
+                .get(pos..pos + len)
+                .ok_or_else(|| anyhow!("truncated Ogg page at byte {}", pos))?;
+            packet.extend_from_slice(segment);
+            pos += len;
+            // a segment shorter than 255 bytes completes the packet, longer packets continue in
+            // the next segment (or page)
+            if len < 255 {
+                packets.push(mem::take(&mut packet));
+            }
+        }
+    }
+
+    Ok(packets)
+}
+
+fn decode_mp3(data: &[u8], sample_rate: u32) -> Result<Vec<i16>, anyhow::Error> {
+    let mut decoder = minimp3::Decoder::new(data);
+    let mut pcm = Vec::new();
+    let mut mp3_sample_rate = sample_rate;
+    loop {
+        match decoder.next_frame() {
+            Ok(frame) => {
+                mp3_sample_rate = frame.sample_rate as u32;
+                match frame.channels {
+                    1 => pcm.extend_from_slice(&frame.data),
+                    2 => pcm.extend(downmix(&frame.data)),
+                    n => return Err(anyhow!("unsupported number of MP3 channels: {}", n)),
+                }
+            }
+            Err(minimp3::Error::Eof) => break,
+            Err(err) => return Err(anyhow!("failed to decode MP3 audio: {}", err)),
+        }
+    }
+
+    Ok(resample(&pcm, mp3_sample_rate, sample_rate))
+}
+
+/// Mixes interleaved stereo samples down to mono.
+fn downmix(pcm: &[i16]) -> Vec<i16> {
+    pcm.chunks_exact(2)
+        .map(|s| ((s[0] as i32 + s[1] as i32) / 2) as i16)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_gain() {
+        assert_eq!(parse_gain("-3dB").unwrap(), -3.0);
+        assert_eq!(parse_gain("+6 dB").unwrap(), 6.0);
+        assert_eq!(parse_gain("1.5").unwrap(), 1.5);
+        assert_eq!(parse_gain("-3db").unwrap(), -3.0);
+        assert!(parse_gain("loud").is_err());
+        assert!(parse_gain("-21dB").is_err());
+        assert!(parse_gain("30dB").is_err());
+    }
+
+    #[test]
+    fn test_apply_gain() {
+        let mut pcm = vec![1000, -1000, 0, 20_000];
+        apply_gain(&mut pcm, -6.0);
+        assert_eq!(pcm, vec![501, -501, 0, 10_024]);
+
+        let mut pcm = vec![1000, -1000, 0, 20_000];
+        apply_gain(&mut pcm, 6.0);
+        assert_eq!(pcm, vec![1995, -1995, 0, 32_767]);
+
+        let mut pcm = vec![1000, -32_768];
+        apply_gain(&mut pcm, 0.0);
+        assert_eq!(pcm, vec![1000, -32_768]);
+    }
+
+    #[test]
+    fn test_radio_effects() {
+        let original = vec![1000i16; FRAME_SIZE * 10];
+
+        let mut pcm = original.clone();
+        apply_radio_effects(&mut pcm, 0.0, DEFAULT_SAMPLE_RATE);
+        assert_eq!(pcm, original);
+
+        let mut pcm = original.clone();
+        apply_radio_effects(&mut pcm, 0.5, DEFAULT_SAMPLE_RATE);
+        // 40ms squelch click and 160ms squelch tail
+        assert_eq!(pcm.len(), original.len() + 640 + 2560);
+        let transmission = &pcm[640..640 + original.len()];
+        assert_ne!(transmission, &original[..]);
+        assert!(transmission
+            .iter()
+            .all(|s| (*s - 1000).abs() <= (NOISE_AMPLITUDE * 0.5) as i16 + 1));
+    }
+
+    #[test]
+    fn test_lead_in_tone() {
+        let original = vec![1000i16; FRAME_SIZE * 10];
+        let tone = LeadInTone {
+            frequency: 1000.0,
+            duration: Duration::from_millis(300),
+        };
+
+        let mut pcm = original.clone();
+        prepend_tone(&mut pcm, tone, DEFAULT_SAMPLE_RATE);
+        // 300ms at 16kHz
+        assert_eq!(pcm.len(), original.len() + 4800);
+        assert_eq!(&pcm[4800..], &original[..]);
+        assert!(pcm[..4800].iter().any(|s| *s != 0));
+        assert!(pcm[..4800]
+            .iter()
+            .all(|s| f32::from(*s).abs() <= TONE_AMPLITUDE));
+
+        let audio = encode_opus(&original, DEFAULT_SAMPLE_RATE).unwrap();
+        let with_tone = encode_opus(&pcm, DEFAULT_SAMPLE_RATE).unwrap();
+        assert_eq!(
+            with_tone.duration,
+            audio.duration + Duration::from_millis(300)
+        );
+    }
+
+    #[test]
+    fn test_parse_lead_in_tone() {
+        assert_eq!(
+            parse_lead_in_tone("1000/300").unwrap(),
+            LeadInTone {
+                frequency: 1000.0,
+                duration: Duration::from_millis(300),
+            }
+        );
+        assert_eq!(
+            parse_lead_in_tone(" 850.5 / 150 ").unwrap(),
+            LeadInTone {
+                frequency: 850.5,
+                duration: Duration::from_millis(150),
+            }
+        );
+        assert!(parse_lead_in_tone("1000").is_err());
+        assert!(parse_lead_in_tone("beep/300").is_err());
+        assert!(parse_lead_in_tone("1000/0").is_err());
+        assert!(parse_lead_in_tone("1000/5000").is_err());
+        assert!(parse_lead_in_tone("50/300").is_err());
+        assert!(parse_lead_in_tone("8000/300").is_err());
+    }
+
+    #[test]
+    fn test_frames_duration() {
+        assert_eq!(frames_duration(0), Duration::from_secs(0));
+        assert_eq!(frames_duration(50), Duration::from_secs(1));
+        assert_eq!(frames_duration(51), Duration::from_millis(1020));
+    }
+
+    #[test]
+    fn test_encoded_duration() {
+        // one second of audio and a few samples more, which result in an additional frame
+        let pcm = vec![0i16; 16_000 + 10];
+        let audio = encode_opus(&pcm, DEFAULT_SAMPLE_RATE).unwrap();
+        assert_eq!(audio.frames.len(), 51);
+        assert_eq!(audio.duration, Duration::from_millis(1020));
+    }
+
+    #[test]
+    fn test_configured_sample_rate() {
+        assert_eq!(opus_sample_rate(24_000).unwrap(), SampleRate::Hz24000);
+        assert_eq!(frame_size(24_000), 480);
+        assert_eq!(frame_size(48_000), 960);
+
+        // one second of 48kHz audio is encoded into 50 frames of 960 samples each
+        let pcm = vec![0i16; 48_000 + 10];
+        let audio = encode_opus(&pcm, 48_000).unwrap();
+        assert_eq!(audio.frames.len(), 51);
+        assert_eq!(audio.duration, Duration::from_millis(1020));
+    }
+
+    #[test]
+    fn test_unsupported_sample_rate() {
+        assert!(opus_sample_rate(44_100).is_err());
+        assert!(opus_sample_rate(0).is_err());
+        assert!(encode_opus(&[0; 882], 44_100).is_err());
+    }
+
+    #[test]
+    fn test_resample() {
+        let pcm = vec![0, 100, 200, 300];
+        assert_eq!(resample(&pcm, 16_000, 16_000), pcm);
+        assert_eq!(
+            resample(&pcm, 16_000, 32_000),
+            vec![0, 50, 100, 150, 200, 250, 300, 300]
+        );
+        assert_eq!(resample(&pcm, 16_000, 8_000), vec![0, 200]);
+    }
+
+    #[test]
+    fn test_pcm_from_wav() {
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&40u32.to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&[0; 16]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&4u32.to_le_bytes());
+        wav.extend_from_slice(&[1, 0, 0xff, 0xff]);
+
+        assert_eq!(pcm_from_bytes(&wav), vec![1, -1]);
+        assert_eq!(pcm_from_bytes(&[2, 0, 0xfe, 0xff]), vec![2, -2]);
+    }
+
+    /// Pretends to be a TTS provider that synthesizes a second of audio in the requested format.
+    /// There is no MP3 encoder, so MP3 audio is read from a fixture instead.
+    fn mock_synthesize(format: AudioFormat, sample_rate: u32) -> Option<Vec<u8>> {
+        let pcm = (0..sample_rate as usize)
+            .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+            .collect::<Vec<_>>();
+        match format {
+            AudioFormat::Pcm => Some(pcm.iter().flat_map(|s| s.to_le_bytes().to_vec()).collect()),
+            AudioFormat::OggOpus => {
+                let mut head = b"OpusHead".to_vec();
+                head.extend_from_slice(&[1, 1]); // version, channels
+                head.extend_from_slice(&312u16.to_le_bytes()); // pre-skip
+                head.extend_from_slice(&sample_rate.to_le_bytes());
+                head.extend_from_slice(&[0, 0, 0]); // gain, channel mapping family
+                let mut packets = vec![head, b"OpusTags".to_vec()];
+                packets.extend(encode_opus(&pcm, sample_rate).unwrap().frames);
+                Some(ogg_page(&packets))
+            }
+            AudioFormat::Mp3 => None,
+        }
+    }
+
+    /// Wraps the packets into a single Ogg page (ignoring the granule position and checksum).
+    fn ogg_page(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat(255).take(packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+        let mut page = b"OggS".to_vec();
+        page.extend_from_slice(&[0; 22]);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+        page
+    }
+
+    #[test]
+    fn test_decode_pcm() {
+        let data = mock_synthesize(AudioFormat::Pcm, 16_000).unwrap();
+        let pcm = decode(&data, AudioFormat::Pcm, 16_000).unwrap();
+        assert_eq!(pcm.len(), 16_000);
+
+        // the decoded PCM is accepted by the Opus encoder
+        let audio = encode_opus(&pcm, 16_000).unwrap();
+        assert_eq!(audio.duration, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_decode_ogg_opus() {
+        let data = mock_synthesize(AudioFormat::OggOpus, 24_000).unwrap();
+        let pcm = decode(&data, AudioFormat::OggOpus, 24_000).unwrap();
+        // one second of audio, minus the pre-skip (312 samples at 48kHz)
+        assert_eq!(pcm.len(), 24_000 - 156);
+        assert!(encode_opus(&pcm, 24_000).is_ok());
+
+        assert!(decode(b"OggS", AudioFormat::OggOpus, 24_000).is_err());
+        assert!(decode(
+            &ogg_page(&[b"OpusTags".to_vec()]),
+            AudioFormat::OggOpus,
+            24_000
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_decode_mp3() {
+        assert_eq!(mock_synthesize(AudioFormat::Mp3, 24_000), None);
+
+        // 12 frames of mono MPEG-2 Layer III silence (16kHz, 576 samples per frame)
+        let data = include_bytes!("silence.mp3");
+        let pcm = decode(data, AudioFormat::Mp3, 16_000).unwrap();
+        assert_eq!(pcm.len(), 12 * 576);
+        assert!(pcm.iter().all(|s| *s == 0));
+
+        // resampled to the requested rate
+        let pcm = decode(data, AudioFormat::Mp3, 24_000).unwrap();
+        assert_eq!(pcm.len(), 12 * 576 * 3 / 2);
+        assert!(encode_opus(&pcm, 24_000).is_ok());
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+
+/// How many report changes are buffered for each subscriber. Subscribers that fall further behind
+/// miss the oldest changes.
+const CAPACITY: usize = 64;
+
+/// The new report of a station, published whenever the station's report is regenerated and its
+/// content differs from its previous one (see `Report::diff`); a new information letter alone
+/// isn't a change.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ReportChange {
+    pub station: String,
+    pub freq: u64,
+    /// The textual (not the spoken) report.
+    pub report: String,
+    /// The information letter of the report, for stations that have one (airfields and weather
+    /// stations).
+    pub info_letter: Option<String>,
+}
+
+/// Publishes the changes of the stations' reports to any number of subscribers (e.g. a chat bot
+/// that posts the current ATIS), independent of the broadcast over SRS.
+#[derive(Clone)]
+pub struct ReportEvents(Arc<ReportEventsInner>);
+
+struct ReportEventsInner {
+    tx: broadcast::Sender<ReportChange>,
+}
+
+impl ReportEvents {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CAPACITY);
+        ReportEvents(Arc::new(ReportEventsInner { tx }))
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ReportChange> {
+        self.0.tx.subscribe()
+    }
+
+    /// Publishes the change to all current subscribers. Whether a report changed is decided by
+    /// the station, which diffs it against its previous report.
+    pub fn publish(&self, change: ReportChange) {
+        // sending only fails if there are no subscribers
+        let _ = self.0.tx.send(change);
+    }
+}
+
+impl Default for ReportEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ReportChange, ReportEvents};
+
+    fn change(station: &str, report: &str) -> ReportChange {
+        ReportChange {
+            station: station.to_string(),
+            freq: 251_000_000,
+            report: report.to_string(),
+            info_letter: Some("Alpha".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_publish() {
+        let events = ReportEvents::new();
+        let mut rx = events.subscribe();
+
+        events.publish(change("Kutaisi", "Runway in use is 04."));
+        events.publish(change("Batumi", "Runway in use is 04."));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            change("Kutaisi", "Runway in use is 04.")
+        );
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            change("Batumi", "Runway in use is 04.")
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_publish_without_subscribers() {
+        let events = ReportEvents::new();
+        events.publish(change("Kutaisi", "Runway in use is 04."));
+
+        // subscribers only receive changes published after they subscribed
+        let mut rx = events.subscribe();
+        assert!(rx.try_recv().is_err());
+        events.publish(change("Kutaisi", "Runway in use is 22."));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            change("Kutaisi", "Runway in use is 22.")
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use std::str::FromStr;
+
+use crate::rpc::{Clouds, WeatherInfo};
+use crate::station::Position;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+/// The URL METARs are fetched from by default. `{station}` is replaced with the ICAO code of the
+/// station.
+pub const DEFAULT_METAR_URL: &str =
+    "https://tgftp.nws.noaa.gov/data/observations/metar/stations/{station}.TXT";
+
+/// A real-world METAR used as the weather source of a station instead of the mission's weather.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MetarSource {
+    /// The ICAO code of the real-world airport, e.g. `UGKO`.
+    pub station: String,
+    /// The URL the METAR is fetched from, with `{station}` as the placeholder for the ICAO code.
+    /// The response is either the raw METAR (optionally preceded by other lines, like the NOAA
+    /// text files) or a JSON object with the METAR in its `raw` property (like AVWX).
+    pub url: String,
+}
+
+impl MetarSource {
+    pub fn new<S: Into<String>>(station: S) -> Self {
+        MetarSource {
+            station: station.into(),
+            url: DEFAULT_METAR_URL.to_string(),
+        }
+    }
+
+    /// Fetches the current METAR and converts it into the weather at the given position (the
+    /// position's altitude is used to calculate the QFE).
+    pub async fn get_weather_at(&self, pos: &Position) -> Result<WeatherInfo, anyhow::Error> {
+        let raw = self.fetch().await?;
+        debug!("Fetched METAR for {}: {}", self.station, raw);
+        parse_metar(&raw, pos)
+    }
+
+    async fn fetch(&self) -> Result<String, anyhow::Error> {
+        let url = self.url.replace("{station}", &self.station);
+        let res = reqwest::get(&url).await?;
+        if res.status() != StatusCode::OK {
+            return Err(anyhow!(
+                "failed to fetch METAR for {}: {}",
+                self.station,
+                res.status()
+            ));
+        }
+
+        let body = res.text().await?;
+        extract_raw_metar(&body, &self.station)
+            .ok_or_else(|| anyhow!("response did not contain a METAR for {}", self.station))
+    }
+}
+
+fn extract_raw_metar(body: &str, station: &str) -> Option<String> {
+    let body = body.trim();
+    if body.starts_with('{') {
+        let json: Value = serde_json::from_str(body).ok()?;
+        return json
+            .get("raw")
+            .and_then(|raw| raw.as_str())
+            .map(String::from);
+    }
+
+    body.lines()
+        .map(|line| line.trim())
+        .find(|line| line.split_whitespace().any(|token| token == station))
+        .or_else(|| body.lines().map(|line| line.trim()).last())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+}
+
+/// Parses a METAR into the weather fields used for reports. The wind, the temperature and the
+/// altimeter setting are mandatory. Trend forecasts and remarks are ignored.
+pub fn parse_metar(metar: &str, pos: &Position) -> Result<WeatherInfo, anyhow::Error> {
+    let mut wind = None;
+    let mut visibility = None;
+    let mut clouds: Option<Clouds> = None;
+    let mut iprecptns = 0;
+    let mut temperature = None;
+    let mut pressure_qnh = None;
+
+    for token in metar.split_whitespace() {
+        match token {
+            "RMK" | "NOSIG" | "BECMG" | "TEMPO" => break,
+            "CAVOK" => {
+                visibility = None;
+                clouds = None;
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some(w) = parse_wind(token) {
+            wind = Some(w);
+        } else if let Some(v) = parse_visibility(token) {
+            visibility = Some(v);
+        } else if let Some(layer) = parse_cloud_layer(token) {
+            // report the ceiling, or the lowest layer if there is no ceiling
+            let is_more_significant = match &clouds {
+                Some(current) => current.density < 8 && layer.density > current.density,
+                None => true,
+            };
+            if is_more_significant {
+                clouds = Some(layer);
+            }
+        } else if let Some(t) = parse_temperature(token) {
+            temperature = Some(t);
+        } else if let Some(p) = parse_altimeter(token) {
+            pressure_qnh = Some(p);
+        } else if let Some(p) = parse_precipitation(token) {
+            iprecptns = iprecptns.max(p);
+        }
+    }
+
+    let (wind_dir, wind_speed) = wind.ok_or_else(|| anyhow!("METAR is missing the wind"))?;
+    let temperature = temperature.ok_or_else(|| anyhow!("METAR is missing the temperature"))?;
+    let pressure_qnh =
+        pressure_qnh.ok_or_else(|| anyhow!("METAR is missing the altimeter setting"))?;
+
+    if let Some(clouds) = clouds.as_mut() {
+        clouds.iprecptns = iprecptns;
+        // METARs report the cloud base above the airport, but like the mission's clouds, the
+        // base is expected to be above MSL
+        clouds.base += pos.alt.max(0.0).round() as u32;
+    }
+
+    Ok(WeatherInfo {
+        clouds,
+        visibility,
+        wind_speed,
+        wind_dir,
+        temperature,
+        pressure_qnh,
+        pressure_qfe: qnh_to_qfe(pressure_qnh, pos.alt),
+        position: pos.clone(),
+    })
+}
+
+/// Parses wind groups like `24012G20KT`, `VRB03KT` or `18005MPS` into their direction (in degrees)
+/// and speed (in m/s).
+pub(crate) fn parse_wind(token: &str) -> Option<(f64, f64)> {
+    let (value, to_mps) = if token.ends_with("KT") {
+        (&token[..token.len() - 2], 0.514_444)
+    } else if token.ends_with("MPS") {
+        (&token[..token.len() - 3], 1.0)
+    } else if token.ends_with("KMH") {
+        (&token[..token.len() - 3], 1.0 / 3.6)
+    } else {
+        return None;
+    };
+
+    if value.len() < 5 || !value.is_char_boundary(3) {
+        return None;
+    }
+    let dir = match &value[..3] {
+        "VRB" => 0.0,
+        dir => f64::from_str(dir).ok()?,
+    };
+    // ignore gusts
+    let speed = value[3..].split('G').next()?;
+    let speed = u32::from_str(speed).ok()?;
+
+    Some((dir, f64::from(speed) * to_mps))
+}
+
+/// Parses visibilities in meters (e.g. `4000`) or statute miles (e.g. `3SM`, `1/2SM`) into meters.
+/// Returns `None` for unlimited visibilities (`9999`, `P6SM`).
+pub(crate) fn parse_visibility(token: &str) -> Option<u32> {
+    if token.len() == 4 && token.chars().all(|c| c.is_ascii_digit()) {
+        let m = u32::from_str(token).ok()?;
+        return if m >= 9999 { None } else { Some(m) };
+    }
+
+    if token.ends_with("SM") {
+        let value = token[..token.len() - 2].trim_start_matches('M');
+        if value.starts_with('P') {
+            return None;
+        }
+        let sm = match value.find('/') {
+            Some(ix) => f64::from_str(&value[..ix]).ok()? / f64::from_str(&value[ix + 1..]).ok()?,
+            None => f64::from_str(value).ok()?,
+        };
+        return Some((sm * 1_609.344).round() as u32);
+    }
+
+    None
+}
+
+/// Parses cloud layers like `BKN015` or `OVC008CB`.
+fn parse_cloud_layer(token: &str) -> Option<Clouds> {
+    let (density, base) = if token.starts_with("VV") {
+        // vertical visibility (sky obscured)
+        (10, token.get(2..5)?)
+    } else {
+        let density = match token.get(..3)? {
+            "FEW" => 3,
+            "SCT" => 6,
+            "BKN" => 8,
+            "OVC" => 10,
+            _ => return None,
+        };
+        (density, token.get(3..6)?)
+    };
+    let base = u32::from_str(base).ok()?;
+
+    Some(Clouds {
+        base: ft_to_m(base * 100),
+        density,
+        thickness: 0,
+        iprecptns: 0,
+    })
+}
+
+/// Parses temperature/dew point groups like `12/08` or `M02/M05` into the temperature (in °C).
+fn parse_temperature(token: &str) -> Option<f64> {
+    let mut parts = token.splitn(2, '/');
+    let temp = parts.next()?;
+    parts.next()?;
+    if temp.len() < 2 || temp.len() > 3 {
+        return None;
+    }
+
+    let (sign, temp) = if temp.starts_with('M') {
+        (-1.0, &temp[1..])
+    } else {
+        (1.0, temp)
+    };
+    if temp.len() != 2 {
+        return None;
+    }
+    let temp = f64::from(u32::from_str(temp).ok()?);
+
+    Some(sign * temp)
+}
+
+/// Parses altimeter settings in hPa (e.g. `Q1013`) or inHg (e.g. `A2992`) into N/m2.
+fn parse_altimeter(token: &str) -> Option<f64> {
+    if token.len() != 5 {
+        return None;
+    }
+    let value = f64::from(u32::from_str(&token[1..]).ok()?);
+    match &token[..1] {
+        "Q" => Some(value * 100.0),
+        "A" => Some(value / 100.0 * 3_386.389),
+        _ => None,
+    }
+}
+
+/// The descriptor and phenomenon codes of present weather groups.
+static WEATHER_CODES: &[&str] = &[
+    "MI", "BC", "PR", "DR", "BL", "SH", "TS", "FZ", "DZ", "RA", "SN", "SG", "IC", "PL", "GR", "GS",
+    "UP", "BR", "FG", "FU", "VA", "DU", "SA", "HZ", "PY", "PO", "SQ", "FC", "SS", "DS",
+];
+
+/// Parses present weather groups (e.g. `-RA`, `+TSRA`, `VCSH`) into the precipitation kind used
+/// by DCS (`1` for rain, `2` for rain and thunderstorm).
+fn parse_precipitation(token: &str) -> Option<u32> {
+    let codes = token.trim_start_matches(|c| c == '-' || c == '+');
+    let codes = codes.trim_start_matches("VC");
+    if codes.is_empty() || codes.len() % 2 != 0 || !codes.is_ascii() {
+        return None;
+    }
+
+    let codes = (0..codes.len())
+        .step_by(2)
+        .map(|i| &codes[i..i + 2])
+        .collect::<Vec<_>>();
+    if !codes.iter().all(|c| WEATHER_CODES.contains(c)) {
+        return None;
+    }
+
+    if codes.contains(&"TS") {
+        Some(2)
+    } else if codes.iter().any(|c| *c == "RA" || *c == "DZ" || *c == "SH") {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Converts ft to m, rounded up so that converting it back to ft doesn't end up slightly below the
+/// reported height.
+fn ft_to_m(ft: u32) -> u32 {
+    (f64::from(ft) * 0.3048).ceil() as u32
+}
+
+/// Calculates the QFE (in N/m2) from the QNH (in N/m2) for the given elevation (in m), using the
+/// international standard atmosphere.
+fn qnh_to_qfe(qnh: f64, elevation: f64) -> f64 {
+    qnh * (1.0 - 0.0065 * elevation / 288.15).powf(5.255)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_metar() {
+        let pos = Position {
+            x: 0.0,
+            y: 0.0,
+            alt: 0.0,
+        };
+        let weather = parse_metar(
+            "UGKO 201200Z 24012G20KT 4000 -RA BKN015 OVC030 12/08 Q1013 NOSIG",
+            &pos,
+        )
+        .unwrap();
+
+        assert_eq!(weather.wind_dir, 240.0);
+        assert_eq!((weather.wind_speed * 1.94384).round(), 12.0);
+        assert_eq!(weather.visibility, Some(4000));
+        assert_eq!(
+            weather.clouds,
+            Some(Clouds {
+                base: 458,
+                density: 8,
+                thickness: 0,
+                iprecptns: 1,
+            })
+        );
+        assert_eq!(weather.temperature, 12.0);
+        assert_eq!(weather.pressure_qnh, 101_300.0);
+        assert_eq!(weather.pressure_qfe, 101_300.0);
+    }
+
+    #[test]
+    fn test_parse_us_metar() {
+        let pos = Position {
+            x: 0.0,
+            y: 0.0,
+            alt: 570.0,
+        };
+        let weather = parse_metar(
+            "METAR KLSV 201155Z VRB03KT 1/2SM +TSRA FEW008 SCT020CB M02/M05 A2992 RMK AO2",
+            &pos,
+        )
+        .unwrap();
+
+        assert_eq!(weather.wind_dir, 0.0);
+        assert_eq!(weather.visibility, Some(805));
+        assert_eq!(
+            weather.clouds,
+            Some(Clouds {
+                base: 1_180,
+                density: 6,
+                thickness: 0,
+                iprecptns: 2,
+            })
+        );
+        assert_eq!(weather.temperature, -2.0);
+        assert_eq!((weather.pressure_qnh / 100.0).round(), 1013.0);
+        assert_eq!((weather.pressure_qfe / 100.0).round(), 947.0);
+    }
+
+    #[test]
+    fn test_parse_cavok_metar() {
+        let weather = parse_metar(
+            "OMDB 201200Z 33008KT CAVOK 38/12 Q1008",
+            &Position::default(),
+        )
+        .unwrap();
+        assert_eq!(weather.visibility, None);
+        assert_eq!(weather.clouds, None);
+    }
+
+    #[test]
+    fn test_parse_precipitation() {
+        assert_eq!(parse_precipitation("-RA"), Some(1));
+        assert_eq!(parse_precipitation("VCSH"), Some(1));
+        assert_eq!(parse_precipitation("+TSRA"), Some(2));
+        assert_eq!(parse_precipitation("BR"), None);
+        // ICAO codes must not be mistaken for weather groups
+        assert_eq!(parse_precipitation("OTRA"), None);
+    }
+
+    #[test]
+    fn test_incomplete_metar() {
+        assert!(parse_metar("UGKO 201200Z 9999 12/08 Q1013", &Position::default()).is_err());
+        assert!(parse_metar("UGKO 201200Z 24012KT 12/08", &Position::default()).is_err());
+    }
+
+    #[test]
+    fn test_extract_raw_metar() {
+        assert_eq!(
+            extract_raw_metar(
+                "2020/06/20 12:00\nUGKO 201200Z 24012KT 9999 12/08 Q1013\n",
+                "UGKO"
+            ),
+            Some("UGKO 201200Z 24012KT 9999 12/08 Q1013".to_string())
+        );
+        assert_eq!(
+            extract_raw_metar(
+                r#"{"raw": "UGKO 201200Z 24012KT 9999 12/08 Q1013", "station": "UGKO"}"#,
+                "UGKO"
+            ),
+            Some("UGKO 201200Z 24012KT 9999 12/08 Q1013".to_string())
+        );
+        assert_eq!(extract_raw_metar("", "UGKO"), None);
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Health indicators of the running stations, e.g. to show them on a status page or to alert on
+/// them from a monitoring system.
+#[derive(Clone, Default)]
+pub struct StationMetrics(Arc<Mutex<StationMetricsInner>>);
+
+#[derive(Default)]
+struct StationMetricsInner {
+    /// Since when the stations transmit stale audio, because the synthesis of their newer reports
+    /// fails.
+    stale_since: HashMap<String, Instant>,
+}
+
+impl StationMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records since when the station's audio is stale, or that it is up to date (`None`).
+    pub(crate) fn set_stale_since(&self, station: &str, since: Option<Instant>) {
+        let mut inner = self.0.lock().unwrap();
+        match since {
+            Some(since) => {
+                inner.stale_since.insert(station.to_string(), since);
+            }
+            None => {
+                inner.stale_since.remove(station);
+            }
+        }
+    }
+
+    /// Returns the stations that currently transmit the audio of an older report, because the
+    /// synthesis of their current report fails, together with how long their audio is stale
+    /// already. Sorted by the station name.
+    pub fn stale_stations(&self) -> Vec<(String, Duration)> {
+        let inner = self.0.lock().unwrap();
+        let mut stale = inner
+            .stale_since
+            .iter()
+            .map(|(station, since)| (station.clone(), since.elapsed()))
+            .collect::<Vec<_>>();
+        stale.sort_by(|a, b| a.0.cmp(&b.0));
+        stale
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::StationMetrics;
+
+    #[test]
+    fn test_stale_stations() {
+        let metrics = StationMetrics::new();
+        assert!(metrics.stale_stations().is_empty());
+
+        let since = Instant::now() - Duration::from_secs(90);
+        metrics.set_stale_since("Kutaisi", Some(since));
+        metrics.set_stale_since("Batumi", Some(Instant::now()));
+        let stale = metrics.stale_stations();
+        assert_eq!(
+            stale
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Batumi", "Kutaisi"]
+        );
+        assert!(stale[1].1 >= Duration::from_secs(90));
+
+        // recovered stations aren't stale anymore
+        metrics.set_stale_since("Kutaisi", None);
+        assert_eq!(
+            metrics
+                .stale_stations()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            vec!["Batumi".to_string()]
+        );
+    }
+}
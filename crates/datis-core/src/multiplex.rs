@@ -0,0 +1,291 @@
+//! Multiplexes multiple airfield stations through a single SRS client, e.g. for SRS servers that
+//! limit the number of connected clients. The stations take turns: for each slot, the client is
+//! moved to the station's position and retuned to its frequency, before the station's report is
+//! transmitted.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::station::{LatLngPosition, Station};
+use crate::{
+    configure_client, report_interval, transmit, AudioConfig, ReportSinks, ReportState, SrsConfig,
+    StationVoice, Watchdog,
+};
+use futures::future::{self, FutureExt};
+use futures::select;
+use futures::stream::{SplitSink, StreamExt};
+use log::Level;
+use srs::{Client, FrequencyControl, TransmissionControl, VoiceStream};
+use tokio::sync::{oneshot, watch};
+use tokio::time::delay_for;
+
+/// The name of the shared SRS client.
+const CLIENT_NAME: &str = "ATIS Multiplex";
+/// How long to wait after retuning before transmitting, so that the server has processed the
+/// radio update before the first voice packet on the new frequency arrives.
+const RETUNE_SETTLE_DELAY: Duration = Duration::from_millis(500);
+/// The pause after each slot, before the next station transmits.
+const SLOT_GAP: Duration = Duration::from_secs(3);
+
+/// A station that shares its SRS client with other stations.
+pub(crate) struct MultiplexedStation {
+    pub(crate) station: Station,
+    pub(crate) voice: StationVoice,
+    pub(crate) audio_config: AudioConfig,
+}
+
+/// Cycles through the frequencies of the multiplexed stations, giving each station one slot per
+/// rotation, in the order the stations have been added.
+struct FrequencyScheduler {
+    freqs: Vec<u64>,
+    next: usize,
+}
+
+impl FrequencyScheduler {
+    fn new(freqs: Vec<u64>) -> Result<Self, anyhow::Error> {
+        if freqs.is_empty() {
+            return Err(anyhow!("cannot multiplex zero stations"));
+        }
+        Ok(FrequencyScheduler { freqs, next: 0 })
+    }
+
+    /// Returns the index of the station the next slot belongs to, and the frequency to retune to.
+    fn next_slot(&mut self) -> (usize, u64) {
+        let ix = self.next;
+        self.next = (self.next + 1) % self.freqs.len();
+        (ix, self.freqs[ix])
+    }
+}
+
+/// Runs the multiplexed stations until the shutdown signal is received. The shared client is
+/// restarted if it fails, just like the client of a single station.
+pub(crate) async fn spawn(
+    stations: Vec<MultiplexedStation>,
+    addr: SocketAddr,
+    srs_config: SrsConfig,
+    reports: ReportSinks,
+    watchdog_timeout: Option<Duration>,
+    shutdown_signal: oneshot::Receiver<()>,
+) {
+    let names = stations
+        .iter()
+        .map(|s| s.station.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    debug!("Connecting multiplexed ATIS {} to {}", names, addr);
+
+    let mut shutdown_signal = shutdown_signal.fuse();
+    loop {
+        let (tx, rx) = oneshot::channel();
+        let mut r = Box::pin(run(
+            &stations,
+            addr,
+            &srs_config,
+            &reports,
+            watchdog_timeout,
+            rx,
+        ))
+        .fuse();
+
+        select! {
+            result = r => {
+                if let Err(err) = result {
+                    error!("Multiplexed ATIS {} failed: {:?}", names, err);
+                }
+
+                info!("Restarting multiplexed ATIS {} in 60 seconds ...", names);
+                delay_for(Duration::from_secs(60)).await;
+            }
+            _ = shutdown_signal => {
+                let _ = tx.send(());
+                let _ = r.await; // run until stopped
+                break;
+            }
+        }
+    }
+}
+
+async fn run(
+    stations: &[MultiplexedStation],
+    addr: SocketAddr,
+    srs_config: &SrsConfig,
+    reports: &ReportSinks,
+    watchdog_timeout: Option<Duration>,
+    shutdown_signal: oneshot::Receiver<()>,
+) -> Result<(), anyhow::Error> {
+    let first = stations
+        .first()
+        .ok_or_else(|| anyhow!("cannot multiplex zero stations"))?;
+    let mut client = Client::new(
+        CLIENT_NAME,
+        first.station.freq,
+        first.station.modulation.as_str(),
+    );
+    configure_client(&mut client, srs_config)?;
+    let pos = client.position_handle();
+
+    let (tx, rx) = oneshot::channel();
+
+    let voice_stream = client.start(addr, None, rx).await?;
+    let transmission = voice_stream.transmission_control();
+    let frequency = voice_stream.frequency_control();
+    let (sink, stream) = voice_stream.split();
+
+    // airfield stations are never paused
+    let (_active_tx, active_rx) = watch::channel(true);
+
+    let watchdog = Watchdog::new();
+    let mut watchdog_check = match watchdog_timeout {
+        Some(timeout) => watchdog.stalled(timeout, active_rx).boxed(),
+        None => future::pending().boxed(),
+    }
+    .fuse();
+
+    let mut stream = stream.fuse();
+    let mut shutdown_signal = shutdown_signal.fuse();
+    let mut broadcast = Box::pin(multiplexed_broadcast(
+        sink,
+        transmission,
+        frequency,
+        pos,
+        stations,
+        reports,
+        &watchdog,
+    ))
+    .fuse();
+
+    loop {
+        select! {
+            packet = stream.next() => {
+                if let Some(packet) = packet {
+                    packet?;
+                    // we are currently not interested in the received voice packets, so simply discard them
+                }
+            }
+
+            result = broadcast => {
+                return result;
+            }
+
+            stalled = watchdog_check => {
+                return Err(anyhow!(
+                    "watchdog: no transmission for {}s, restarting the multiplexed stations",
+                    stalled.as_secs()
+                ));
+            }
+
+            _ = shutdown_signal => {
+                // shutdown socket
+                let _ = tx.send(());
+
+                break;
+            }
+        }
+    }
+
+    debug!("Multiplexed ATIS stations successfully shut down");
+
+    Ok(())
+}
+
+/// Transmits the reports of the stations in turns. Each station's report is regenerated (and
+/// synthesized if it has changed) in the station's slot once its report interval has elapsed, so
+/// a slow TTS request delays all stations.
+async fn multiplexed_broadcast(
+    mut sink: SplitSink<VoiceStream, Vec<u8>>,
+    transmission: TransmissionControl,
+    frequency: FrequencyControl,
+    position: Arc<RwLock<LatLngPosition>>,
+    stations: &[MultiplexedStation],
+    reports: &ReportSinks,
+    watchdog: &Watchdog,
+) -> Result<(), anyhow::Error> {
+    let mut scheduler = FrequencyScheduler::new(stations.iter().map(|s| s.station.freq).collect())?;
+    let mut states = stations
+        .iter()
+        .map(|_| ReportState::default())
+        .collect::<Vec<_>>();
+    let mut next_refresh = vec![Instant::now(); stations.len()];
+
+    loop {
+        let (ix, freq) = scheduler.next_slot();
+        let MultiplexedStation {
+            station,
+            voice,
+            audio_config,
+        } = &stations[ix];
+
+        if Instant::now() >= next_refresh[ix] {
+            if states[ix]
+                .refresh(station, voice, *audio_config, reports)
+                .await?
+            {
+                next_refresh[ix] = Instant::now() + report_interval(station);
+            } else {
+                station_log!(
+                    station,
+                    Level::Debug,
+                    "No report available for station {}. Trying again in 30 seconds ...",
+                    station.name
+                );
+                watchdog.feed();
+                next_refresh[ix] = Instant::now() + Duration::from_secs(30);
+            }
+        }
+
+        if states[ix].cached.segments.is_empty() {
+            // skip the slot of stations without a report, but don't spin while none of the
+            // stations has one
+            if states.iter().all(|s| s.cached.segments.is_empty()) {
+                delay_for(SLOT_GAP).await;
+            }
+            continue;
+        }
+
+        // move the client before retuning, so that the radio update announcing the new frequency
+        // already contains the station's position
+        {
+            let mut pos = position.write().unwrap();
+            *pos = states[ix].position.clone();
+        }
+        frequency.retune(freq);
+        delay_for(RETUNE_SETTLE_DELAY).await;
+
+        transmit(
+            &mut sink,
+            &transmission,
+            &states[ix].cached.segments,
+            watchdog,
+        )
+        .await?;
+
+        delay_for(SLOT_GAP).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FrequencyScheduler;
+
+    #[test]
+    fn test_scheduler_rotation() {
+        let mut scheduler =
+            FrequencyScheduler::new(vec![251_000_000, 131_500_000, 124_000_000]).unwrap();
+        let slots = (0..7).map(|_| scheduler.next_slot()).collect::<Vec<_>>();
+        assert_eq!(
+            slots,
+            vec![
+                (0, 251_000_000),
+                (1, 131_500_000),
+                (2, 124_000_000),
+                (0, 251_000_000),
+                (1, 131_500_000),
+                (2, 124_000_000),
+                (0, 251_000_000),
+            ]
+        );
+
+        assert!(FrequencyScheduler::new(Vec::new()).is_err());
+    }
+}
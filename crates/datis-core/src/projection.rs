@@ -0,0 +1,196 @@
+use std::str::FromStr;
+
+use crate::station::{LatLngPosition, Position};
+
+/// The DCS maps (theatres) positions can be converted to latitude/longitude for.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Theatre {
+    Caucasus,
+    Nevada,
+    PersianGulf,
+    Syria,
+}
+
+/// Parameters of the transverse mercator projection (on the WGS84 ellipsoid) DCS uses for its
+/// maps.
+struct TransverseMercator {
+    central_meridian: f64,
+    false_easting: f64,
+    false_northing: f64,
+    scale_factor: f64,
+}
+
+// WGS84 ellipsoid
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+const FLATTENING: f64 = 1.0 / 298.257_223_563;
+
+impl Theatre {
+    /// The approximate magnetic declination (in degrees, positive to the east) of the map, i.e.
+    /// the difference between true and magnetic north, as of 2020 (from the World Magnetic Model
+    /// at the center of the map).
+    pub fn magnetic_declination(self) -> f64 {
+        match self {
+            Theatre::Caucasus => 6.0,
+            Theatre::Nevada => 11.5,
+            Theatre::PersianGulf => 2.0,
+            Theatre::Syria => 5.0,
+        }
+    }
+
+    fn projection(self) -> TransverseMercator {
+        match self {
+            Theatre::Caucasus => TransverseMercator {
+                central_meridian: 33.0,
+                false_easting: -99_517.0,
+                false_northing: -4_998_115.0,
+                scale_factor: 0.9996,
+            },
+            Theatre::Nevada => TransverseMercator {
+                central_meridian: -117.0,
+                false_easting: -193_996.81,
+                false_northing: -4_410_028.064,
+                scale_factor: 0.9996,
+            },
+            Theatre::PersianGulf => TransverseMercator {
+                central_meridian: 57.0,
+                false_easting: 75_756.0,
+                false_northing: -2_894_933.0,
+                scale_factor: 0.9996,
+            },
+            Theatre::Syria => TransverseMercator {
+                central_meridian: 39.0,
+                false_easting: 282_801.0,
+                false_northing: -3_879_866.0,
+                scale_factor: 0.9996,
+            },
+        }
+    }
+
+    /// Converts a DCS map position (where `x` points north and `y` east) to latitude/longitude.
+    pub fn to_lat_lng(self, pos: &Position) -> LatLngPosition {
+        let (lat, lng) = self.projection().inverse(pos.y, pos.x);
+        LatLngPosition {
+            lat,
+            lng,
+            alt: pos.alt,
+        }
+    }
+}
+
+impl TransverseMercator {
+    /// Converts easting/northing to latitude/longitude (in degrees), using the series expansion
+    /// described in Snyder's "Map Projections: A Working Manual" (p. 60ff).
+    fn inverse(&self, easting: f64, northing: f64) -> (f64, f64) {
+        let a = SEMI_MAJOR_AXIS;
+        let e2 = FLATTENING * (2.0 - FLATTENING);
+        let ep2 = e2 / (1.0 - e2);
+
+        let m = (northing - self.false_northing) / self.scale_factor;
+        let mu = m / (a * (1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0));
+
+        // footpoint latitude
+        let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+        let phi1 = mu
+            + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+            + (21.0 * e1.powi(2) / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+            + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+            + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+        let (sin_phi1, cos_phi1, tan_phi1) = (phi1.sin(), phi1.cos(), phi1.tan());
+        let c1 = ep2 * cos_phi1.powi(2);
+        let t1 = tan_phi1.powi(2);
+        let n1 = a / (1.0 - e2 * sin_phi1.powi(2)).sqrt();
+        let r1 = a * (1.0 - e2) / (1.0 - e2 * sin_phi1.powi(2)).powf(1.5);
+        let d = (easting - self.false_easting) / (n1 * self.scale_factor);
+
+        let lat = phi1
+            - (n1 * tan_phi1 / r1)
+                * (d.powi(2) / 2.0
+                    - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1.powi(2) - 9.0 * ep2) * d.powi(4)
+                        / 24.0
+                    + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1.powi(2)
+                        - 252.0 * ep2
+                        - 3.0 * c1.powi(2))
+                        * d.powi(6)
+                        / 720.0);
+        let lng = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+            + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1.powi(2) + 8.0 * ep2 + 24.0 * t1.powi(2))
+                * d.powi(5)
+                / 120.0)
+            / cos_phi1;
+
+        (lat.to_degrees(), self.central_meridian + lng.to_degrees())
+    }
+}
+
+impl FromStr for Theatre {
+    type Err = anyhow::Error;
+
+    /// Parses the theatre name as used in `env.mission.theatre`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Caucasus" => Ok(Theatre::Caucasus),
+            "Nevada" => Ok(Theatre::Nevada),
+            "PersianGulf" => Ok(Theatre::PersianGulf),
+            "Syria" => Ok(Theatre::Syria),
+            _ => Err(anyhow!("unsupported theatre {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Theatre;
+    use crate::station::Position;
+
+    fn assert_lat_lng(theatre: Theatre, x: f64, y: f64, lat: f64, lng: f64) {
+        let pos = theatre.to_lat_lng(&Position { x, y, alt: 42.0 });
+        assert!(
+            (pos.lat - lat).abs() < 0.001 && (pos.lng - lng).abs() < 0.001,
+            "expected {}, {} got {}, {}",
+            lat,
+            lng,
+            pos.lat,
+            pos.lng
+        );
+        assert_eq!(pos.alt, 42.0);
+    }
+
+    #[test]
+    fn test_caucasus() {
+        // Kutaisi
+        assert_lat_lng(Theatre::Caucasus, -284_988.0, 683_980.0, 42.1766, 42.4826);
+        // Batumi
+        assert_lat_lng(Theatre::Caucasus, -355_737.0, 617_334.0, 41.6103, 41.5997);
+    }
+
+    #[test]
+    fn test_nevada() {
+        // Nellis AFB
+        assert_lat_lng(Theatre::Nevada, -398_090.0, -17_352.0, 36.2362, -115.0343);
+    }
+
+    #[test]
+    fn test_persian_gulf() {
+        // Dubai Intl
+        assert_lat_lng(
+            Theatre::PersianGulf,
+            -100_989.0,
+            -88_967.0,
+            25.2528,
+            55.3644,
+        );
+    }
+
+    #[test]
+    fn test_syria() {
+        // Incirlik
+        assert_lat_lng(Theatre::Syria, 221_214.0, -35_257.0, 37.0021, 35.4259);
+    }
+
+    #[test]
+    fn test_theatre_from_str() {
+        assert_eq!("Syria".parse::<Theatre>().unwrap(), Theatre::Syria);
+        assert!("Marianas".parse::<Theatre>().is_err());
+    }
+}
@@ -1,8 +1,11 @@
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 
+use crate::projection::Theatre;
 use crate::station::{LatLngPosition, Position};
 use futures::channel::oneshot::{channel, Receiver, Sender};
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -11,11 +14,17 @@ pub enum Response {
     Error(String),
 }
 
+/// The method that queries the weather of multiple points in a single call.
+const WEATHER_BATCH_METHOD: &str = "get_weather_batch";
+
 #[derive(Debug)]
 pub struct PendingRequest {
     method: String,
     params: Option<Value>,
     tx: Sender<Response>,
+    /// The weather queries of other requests that have been merged into this one, with the
+    /// number of points each of them queried. Their results follow the ones of this request.
+    merged: Vec<(usize, Sender<Response>)>,
 }
 
 #[derive(Debug)]
@@ -24,11 +33,15 @@ pub struct MissionRpcInner {
     clouds: Option<Clouds>,
     fog_thickness: u32,  // in m
     fog_visibility: u32, // in m
+    theatre: Option<Theatre>,
+    /// Whether the mission supports batched weather queries. Older hook scripts don't, in which
+    /// case each point is queried on its own.
+    batch_weather: bool,
 }
 
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub struct Clouds {
-    pub base: u32, // in m
+    pub base: u32, // in m (above MSL)
     pub density: u32,
     pub thickness: u32,
     pub iprecptns: u32,
@@ -46,104 +59,219 @@ pub struct WeatherInfo {
     pub position: Position,
 }
 
+/// The weather at a single point, as returned by the mission.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WeatherData {
+    wind_speed: f64,
+    wind_dir: f64,
+    temp: f64,
+    pressure: f64,
+}
+
 #[derive(Clone)]
 pub struct MissionRpc(Arc<Mutex<MissionRpcInner>>);
 
+/// The mission's static weather, which is what a `MissionRpc` is (de)serialized as.
+#[derive(Serialize, Deserialize)]
+struct MissionWeather {
+    clouds: Option<Clouds>,
+    fog_thickness: u32,
+    fog_visibility: u32,
+    theatre: Option<Theatre>,
+}
+
+/// The weather and position queries the reports are generated from. Implemented by
+/// `MissionRpc`, which asks the running mission, and by mocks to test the report generation
+/// without DCS.
+pub trait WeatherSource: Send + Sync {
+    fn get_weather_at<'a>(
+        &'a self,
+        pos: &'a Position,
+    ) -> BoxFuture<'a, Result<WeatherInfo, anyhow::Error>>;
+
+    /// Returns `None` if the unit doesn't exist.
+    fn get_unit_position<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Position>, anyhow::Error>>;
+
+    /// Returns `None` if the unit doesn't exist.
+    fn get_unit_heading<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<f64>, anyhow::Error>>;
+
+    fn get_mission_hour(&self) -> BoxFuture<'_, Result<u16, anyhow::Error>>;
+
+    fn to_lat_lng<'a>(
+        &'a self,
+        pos: &'a Position,
+    ) -> BoxFuture<'a, Result<LatLngPosition, anyhow::Error>>;
+}
+
 impl MissionRpc {
     pub fn new(
         clouds: Option<Clouds>,
         fog_thickness: u32,
         fog_visibility: u32,
+        theatre: Option<Theatre>,
     ) -> Result<Self, anyhow::Error> {
         Ok(MissionRpc(Arc::new(Mutex::new(MissionRpcInner {
             queue: VecDeque::new(),
             clouds,
             fog_thickness,
             fog_visibility,
+            theatre,
+            batch_weather: true,
         }))))
     }
 
+    /// Returns the next request for the mission. All pending weather queries are merged into a
+    /// single request, so that the weather of all stations is fetched in one call.
     pub fn try_next(&self) -> Option<PendingRequest> {
-        if let Ok(mut inner) = self.0.try_lock() {
-            inner.queue.pop_front()
-        } else {
-            None
+        let mut inner = self.0.try_lock().ok()?;
+        let mut req = inner.queue.pop_front()?;
+        if req.method == WEATHER_BATCH_METHOD {
+            let (batches, rest) = inner
+                .queue
+                .drain(..)
+                .partition::<VecDeque<_>, _>(|other| other.method == WEATHER_BATCH_METHOD);
+            inner.queue = rest;
+            for other in batches {
+                req.merge(other);
+            }
         }
+        Some(req)
     }
 
     pub async fn get_weather_at(&self, pos: &Position) -> Result<WeatherInfo, anyhow::Error> {
-        #[derive(Deserialize)]
-        #[serde(rename_all = "camelCase")]
-        struct Data {
-            wind_speed: f64,
-            wind_dir: f64,
-            temp: f64,
-            pressure: f64,
-        }
-
-        let (rx, clouds, visibility) = {
-            let (req, rx) = PendingRequest::new(
-                "get_weather",
-                Some(json!({ "x": pos.x, "y": pos.y, "alt": 0})),
-            );
-
-            let mut inner = self.0.lock().unwrap();
-            inner.queue.push_back(req);
+        let mut weather = self.get_weather_batch(std::slice::from_ref(pos)).await?;
+        weather
+            .pop()
+            .ok_or_else(|| anyhow!("failed to get weather: no result"))
+    }
 
-            let clouds = inner.clouds.clone();
+    /// Returns the weather at each of the given positions, which are queried in a single call
+    /// (together with other pending weather queries). Falls back to querying each position on its
+    /// own if the mission doesn't support batched queries.
+    pub async fn get_weather_batch(
+        &self,
+        positions: &[Position],
+    ) -> Result<Vec<WeatherInfo>, anyhow::Error> {
+        if positions.is_empty() {
+            return Ok(Vec::new());
+        }
 
+        // the QNH is the pressure at sea level, the QFE the one at the position's altitude
+        let points = positions
+            .iter()
+            .flat_map(|pos| {
+                vec![
+                    json!({ "x": pos.x, "y": pos.y, "alt": 0}),
+                    json!({ "x": pos.x, "y": pos.y, "alt": pos.alt}),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let data = self.query_weather(points).await?;
+
+        let (clouds, visibility) = {
+            let inner = self.0.lock().unwrap();
             let visibility = if inner.fog_thickness > 200 {
                 Some(inner.fog_visibility)
             } else {
                 None
             };
-
-            (rx, clouds, visibility)
+            (inner.clouds.clone(), visibility)
         };
 
-        let data: Data = match rx.await? {
-            Response::Success(v) => serde_json::from_value(v)?,
-            Response::Error(err) => {
-                return Err(anyhow!("failed to get weather: {}", err));
-            }
-        };
-        let pressure_qnh = data.pressure;
+        Ok(positions
+            .iter()
+            .zip(data.chunks_exact(2))
+            .map(|(pos, data)| {
+                let (sea_level, data) = (&data[0], &data[1]);
+
+                // convert to degrees and rotate wind direction
+                let mut wind_dir = data.wind_dir.to_degrees() - 180.0;
+
+                // normalize wind direction
+                while wind_dir < 0.0 {
+                    wind_dir += 360.0;
+                }
+
+                WeatherInfo {
+                    clouds: clouds.clone(),
+                    visibility,
+                    wind_speed: data.wind_speed,
+                    wind_dir,
+                    temperature: data.temp,
+                    pressure_qnh: sea_level.pressure,
+                    pressure_qfe: data.pressure,
+                    position: pos.clone(),
+                }
+            })
+            .collect())
+    }
 
+    /// Queries the weather at the given points, in a single call if the mission supports it.
+    async fn query_weather(&self, points: Vec<Value>) -> Result<Vec<WeatherData>, anyhow::Error> {
         let rx = {
-            let (req, rx) = PendingRequest::new(
-                "get_weather",
-                Some(json!({ "x": pos.x, "y": pos.y, "alt": pos.alt})),
-            );
             let mut inner = self.0.lock().unwrap();
-            inner.queue.push_back(req);
-            rx
+            if inner.batch_weather {
+                let (req, rx) = PendingRequest::new(
+                    WEATHER_BATCH_METHOD,
+                    Some(json!({ "positions": points.clone() })),
+                );
+                inner.queue.push_back(req);
+                Some(rx)
+            } else {
+                None
+            }
         };
 
-        let data: Data = match rx.await? {
-            Response::Success(v) => serde_json::from_value(v)?,
-            Response::Error(err) => {
-                return Err(anyhow!("failed to get weather: {}", err));
+        if let Some(rx) = rx {
+            match rx.await? {
+                Response::Success(v) => {
+                    let data: Vec<WeatherData> = serde_json::from_value(v)?;
+                    if data.len() != points.len() {
+                        return Err(anyhow!(
+                            "failed to get weather: expected {} results, got {}",
+                            points.len(),
+                            data.len()
+                        ));
+                    }
+                    return Ok(data);
+                }
+                Response::Error(err) if err.starts_with("unknown method") => {
+                    let mut inner = self.0.lock().unwrap();
+                    if inner.batch_weather {
+                        inner.batch_weather = false;
+                        debug!("Falling back to single weather queries: {}", err);
+                    }
+                }
+                Response::Error(err) => {
+                    return Err(anyhow!("failed to get weather: {}", err));
+                }
             }
-        };
+        }
 
-        // convert to degrees and rotate wind direction
-        let mut wind_dir = data.wind_dir.to_degrees() - 180.0;
+        let mut data = Vec::with_capacity(points.len());
+        for point in points {
+            let rx = {
+                let (req, rx) = PendingRequest::new("get_weather", Some(point));
+                let mut inner = self.0.lock().unwrap();
+                inner.queue.push_back(req);
+                rx
+            };
 
-        // normalize wind direction
-        while wind_dir < 0.0 {
-            wind_dir += 360.0;
+            match rx.await? {
+                Response::Success(v) => data.push(serde_json::from_value(v)?),
+                Response::Error(err) => {
+                    return Err(anyhow!("failed to get weather: {}", err));
+                }
+            }
         }
-
-        Ok(WeatherInfo {
-            clouds,
-            visibility,
-            wind_speed: data.wind_speed,
-            wind_dir,
-            temperature: data.temp,
-            pressure_qnh,
-            pressure_qfe: data.pressure,
-            position: pos.clone(),
-        })
+        Ok(data)
     }
 
     pub async fn get_unit_position(&self, name: &str) -> Result<Option<Position>, anyhow::Error> {
@@ -213,11 +341,17 @@ impl MissionRpc {
 
     pub async fn to_lat_lng(&self, pos: &Position) -> Result<LatLngPosition, anyhow::Error> {
         let rx = {
+            let mut inner = self.0.lock().unwrap();
+
+            // convert positions on known maps right away, instead of asking the mission
+            if let Some(theatre) = inner.theatre {
+                return Ok(theatre.to_lat_lng(pos));
+            }
+
             let (req, rx) = PendingRequest::new(
                 "to_lat_lng",
                 Some(json!({ "x": pos.x, "y": pos.y, "alt": pos.alt})),
             );
-            let mut inner = self.0.lock().unwrap();
             inner.queue.push_back(req);
             rx
         };
@@ -237,6 +371,7 @@ impl PendingRequest {
                 method: method.to_string(),
                 params,
                 tx,
+                merged: Vec::new(),
             },
             rx,
         )
@@ -251,6 +386,286 @@ impl PendingRequest {
     }
 
     pub fn receive(self, res: Response) {
-        let _ = self.tx.send(res);
+        if self.merged.is_empty() {
+            let _ = self.tx.send(res);
+            return;
+        }
+
+        // split the results of merged weather queries among the requests they belong to
+        let mut results = match res {
+            Response::Success(Value::Array(results)) => results,
+            Response::Success(_) => {
+                let err = "failed to get weather: expected a list of results".to_string();
+                self.broadcast_error(err);
+                return;
+            }
+            Response::Error(err) => {
+                self.broadcast_error(err);
+                return;
+            }
+        };
+        let merged_len = self.merged.iter().map(|(len, _)| len).sum::<usize>();
+        let mut rest = results.split_off(results.len().saturating_sub(merged_len));
+        let _ = self.tx.send(Response::Success(Value::Array(results)));
+        for (len, tx) in self.merged {
+            let tail = rest.split_off(len.min(rest.len()));
+            let _ = tx.send(Response::Success(Value::Array(rest)));
+            rest = tail;
+        }
+    }
+
+    /// Appends the queried points of another weather query to this one.
+    fn merge(&mut self, mut other: PendingRequest) {
+        let points = other.points_mut().map(std::mem::take).unwrap_or_default();
+        // the points of the other request's own merged requests are at the end of its points
+        let merged_len = other.merged.iter().map(|(len, _)| len).sum::<usize>();
+        let len = points.len().saturating_sub(merged_len);
+        if let Some(own) = self.points_mut() {
+            own.extend(points);
+        }
+        self.merged.push((len, other.tx));
+        self.merged.extend(other.merged);
+    }
+
+    /// The queried points of a weather query.
+    fn points_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self.params.as_mut()?.get_mut("positions")? {
+            Value::Array(points) => Some(points),
+            _ => None,
+        }
+    }
+
+    fn broadcast_error(self, err: String) {
+        for (_, tx) in self.merged {
+            let _ = tx.send(Response::Error(err.clone()));
+        }
+        let _ = self.tx.send(Response::Error(err));
+    }
+}
+
+impl Serialize for MissionRpc {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let inner = self.0.lock().unwrap();
+        MissionWeather {
+            clouds: inner.clouds.clone(),
+            fog_thickness: inner.fog_thickness,
+            fog_visibility: inner.fog_visibility,
+            theatre: inner.theatre,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MissionRpc {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let weather = MissionWeather::deserialize(deserializer)?;
+        MissionRpc::new(
+            weather.clouds,
+            weather.fog_thickness,
+            weather.fog_visibility,
+            weather.theatre,
+        )
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+impl WeatherSource for MissionRpc {
+    fn get_weather_at<'a>(
+        &'a self,
+        pos: &'a Position,
+    ) -> BoxFuture<'a, Result<WeatherInfo, anyhow::Error>> {
+        MissionRpc::get_weather_at(self, pos).boxed()
+    }
+
+    fn get_unit_position<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Position>, anyhow::Error>> {
+        MissionRpc::get_unit_position(self, name).boxed()
+    }
+
+    fn get_unit_heading<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<f64>, anyhow::Error>> {
+        MissionRpc::get_unit_heading(self, name).boxed()
+    }
+
+    fn get_mission_hour(&self) -> BoxFuture<'_, Result<u16, anyhow::Error>> {
+        MissionRpc::get_mission_hour(self).boxed()
+    }
+
+    fn to_lat_lng<'a>(
+        &'a self,
+        pos: &'a Position,
+    ) -> BoxFuture<'a, Result<LatLngPosition, anyhow::Error>> {
+        MissionRpc::to_lat_lng(self, pos).boxed()
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::{WeatherInfo, WeatherSource};
+    use crate::station::{LatLngPosition, Position};
+    use futures::future::{self, BoxFuture, FutureExt};
+
+    /// A `WeatherSource` with a fixed weather everywhere. Positions are converted to
+    /// latitude/longitude by using `x` as latitude and `y` as longitude.
+    #[derive(Default)]
+    pub struct MockWeatherSource {
+        pub weather: WeatherInfo,
+        /// The position and heading of each unit by its name.
+        pub units: HashMap<String, (Position, f64)>,
+        pub mission_hour: u16,
+        /// The positions the weather has been queried at.
+        pub queried: Mutex<Vec<Position>>,
+    }
+
+    impl WeatherSource for MockWeatherSource {
+        fn get_weather_at<'a>(
+            &'a self,
+            pos: &'a Position,
+        ) -> BoxFuture<'a, Result<WeatherInfo, anyhow::Error>> {
+            self.queried.lock().unwrap().push(pos.clone());
+            future::ok(WeatherInfo {
+                position: pos.clone(),
+                ..self.weather.clone()
+            })
+            .boxed()
+        }
+
+        fn get_unit_position<'a>(
+            &'a self,
+            name: &'a str,
+        ) -> BoxFuture<'a, Result<Option<Position>, anyhow::Error>> {
+            future::ok(self.units.get(name).map(|(pos, _)| pos.clone())).boxed()
+        }
+
+        fn get_unit_heading<'a>(
+            &'a self,
+            name: &'a str,
+        ) -> BoxFuture<'a, Result<Option<f64>, anyhow::Error>> {
+            future::ok(self.units.get(name).map(|(_, heading)| *heading)).boxed()
+        }
+
+        fn get_mission_hour(&self) -> BoxFuture<'_, Result<u16, anyhow::Error>> {
+            future::ok(self.mission_hour).boxed()
+        }
+
+        fn to_lat_lng<'a>(
+            &'a self,
+            pos: &'a Position,
+        ) -> BoxFuture<'a, Result<LatLngPosition, anyhow::Error>> {
+            future::ok(LatLngPosition {
+                lat: pos.x,
+                lng: pos.y,
+                alt: pos.alt,
+            })
+            .boxed()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MissionRpc, Response};
+    use crate::station::Position;
+    use serde_json::Value;
+
+    fn position(x: f64) -> Position {
+        Position {
+            x,
+            y: 0.0,
+            alt: 100.0,
+        }
+    }
+
+    /// The weather the mission would return for the given point, with its pressure depending on
+    /// the point's x coordinate and altitude.
+    fn weather_data(point: &Value) -> Value {
+        let x = point["x"].as_f64().unwrap();
+        let alt = point["alt"].as_f64().unwrap();
+        json!({
+            "windSpeed": 5.0,
+            "windDir": std::f64::consts::PI,
+            "temp": 15.0,
+            "pressure": 100_000.0 + x - alt,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_weather_batch() {
+        let rpc = MissionRpc::new(None, 0, 0, None).unwrap();
+        let positions = vec![position(1.0), position(2.0)];
+
+        // answer the single (merged) weather request like the mission would do
+        let mission = async {
+            let mut req = rpc.try_next().unwrap();
+            assert!(rpc.try_next().is_none());
+            assert_eq!(req.method(), "get_weather_batch");
+            let params = req.take_params().unwrap();
+            let points = params["positions"].as_array().unwrap();
+            // each position is queried at sea level and at its altitude
+            assert_eq!(points.len(), 6);
+            req.receive(Response::Success(Value::Array(
+                points.iter().map(weather_data).collect(),
+            )));
+        };
+
+        let other = position(3.0);
+        let (batch, single, _) = futures::join!(
+            rpc.get_weather_batch(&positions),
+            rpc.get_weather_at(&other),
+            mission
+        );
+
+        let batch = batch.unwrap();
+        assert_eq!(batch.len(), 2);
+        for (weather, x) in batch.iter().zip(&[1.0, 2.0]) {
+            assert_eq!(weather.position, position(*x));
+            assert!((weather.pressure_qnh - (100_000.0 + x)).abs() < 0.001);
+            assert!((weather.pressure_qfe - (100_000.0 + x - 100.0)).abs() < 0.001);
+            assert!(weather.wind_dir.abs() < 0.001);
+        }
+
+        let single = single.unwrap();
+        assert_eq!(single.position, position(3.0));
+        assert!((single.pressure_qnh - 100_003.0).abs() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_weather_batch_fallback() {
+        let rpc = MissionRpc::new(None, 0, 0, None).unwrap();
+        let positions = vec![position(1.0), position(2.0)];
+
+        // answer the requests like a mission without support for batched queries would do
+        let mission = async {
+            let mut single_requests = 0;
+            while single_requests < 4 {
+                if let Some(mut req) = rpc.try_next() {
+                    match req.method() {
+                        "get_weather_batch" => req.receive(Response::Error(
+                            "unknown method get_weather_batch".to_string(),
+                        )),
+                        "get_weather" => {
+                            let params = req.take_params().unwrap();
+                            req.receive(Response::Success(weather_data(&params)));
+                            single_requests += 1;
+                        }
+                        method => panic!("unexpected request {}", method),
+                    }
+                }
+                tokio::task::yield_now().await;
+            }
+        };
+
+        let (batch, _) = futures::join!(rpc.get_weather_batch(&positions), mission);
+        let batch = batch.unwrap();
+        assert_eq!(batch.len(), 2);
+        assert!((batch[1].pressure_qnh - 100_002.0).abs() < 0.001);
+        assert!((batch[1].pressure_qfe - 99_902.0).abs() < 0.001);
     }
 }
@@ -1,52 +1,352 @@
-use crate::rpc::{Clouds, MissionRpc, WeatherInfo};
-use crate::tts::TextToSpeechProvider;
-use crate::utils::{m_to_ft, m_to_nm, pronounce_number, round};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use crate::metar::{self, MetarSource};
+use crate::rpc::{Clouds, WeatherInfo, WeatherSource};
+use crate::tts::{ssml, EnglishVariant, TextToSpeechProvider};
+use crate::utils::{
+    escape_ssml, m_to_ft, m_to_nm, number_to_words, pronounce_number, round, spoken_airfield_name,
+    truncate_at_sentence,
+};
 pub use srs::message::{LatLngPosition, Position};
 
 #[cfg(not(feature = "static-weather"))]
 use anyhow::Context;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Station {
     pub name: String,
     pub freq: u64,
+    /// The modulation the station transmits with.
+    #[serde(default)]
+    pub modulation: Modulation,
     pub tts: TextToSpeechProvider,
+    /// The gain (in dB) applied to the synthesized audio.
+    pub gain: f32,
+    /// How the wind direction is rounded in the station's reports.
+    #[serde(default)]
+    pub wind_rounding: WindRounding,
+    /// The convention the pressures are rounded with in the station's reports.
+    #[serde(default)]
+    pub pressure_rounding: PressureRounding,
+    /// Overrides the log level of the station's messages (e.g. to quiet a noisy station).
+    #[serde(default)]
+    pub log_level: Option<log::LevelFilter>,
     pub transmitter: Transmitter,
-    pub rpc: Option<MissionRpc>,
+    /// The port of the SRS server the station connects to, if it differs from the globally
+    /// configured one.
+    pub srs_port: Option<u16>,
+    /// The template of the station's name in the SRS client list (e.g. `{name} ({freq})`), if it
+    /// differs from the globally configured one.
+    #[serde(default)]
+    pub srs_name_template: Option<String>,
+    /// Where the weather and the positions of the reports come from (usually the mission). Not
+    /// (de)serialized.
+    #[serde(skip)]
+    pub rpc: Option<Arc<dyn WeatherSource>>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Transmitter {
     Airfield(Airfield),
     Carrier(Carrier),
     Custom(Custom),
     Weather(WeatherTransmitter),
+    /// A FARP or helipad, which has no runways.
+    Farp(Farp),
+    /// A short advisory broadcast on the traffic frequency of the given airfield.
+    TrafficAdvisory(Airfield),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Airfield {
     pub name: String,
     pub position: Position,
     pub runways: Vec<String>,
     pub traffic_freq: Option<u64>,
     pub info_ltr_offset: usize,
+    /// A real-world METAR used instead of the mission's weather.
+    pub metar: Option<MetarSource>,
+    /// Free-text remarks (e.g. closed runways) appended to the report.
+    pub remarks: Option<TextSource>,
+    /// A custom phrase (e.g. a welcome) spoken before the report.
+    pub intro: Option<TextSource>,
+    /// A custom phrase (e.g. a sign-off) spoken after the report.
+    pub outro: Option<TextSource>,
+    /// Runways pinned by the mission designer, used instead of the wind-derived runway.
+    pub runway_override: Option<RunwayOverride>,
+    /// The traffic pattern directions of individual runways; all other runways have left traffic.
+    pub traffic_patterns: Vec<TrafficPattern>,
+    /// Whether to only report the active runway and the altimeter instead of the full weather.
+    pub simple_report: bool,
+    /// The trend group (e.g. NOSIG) the full report ends with.
+    pub trend: Option<Trend>,
+    /// Whether to announce the headwind and crosswind components on the landing runway.
+    #[serde(default)]
+    pub wind_components: bool,
+    /// The unit the field elevation is announced in, if it is announced at all.
+    #[serde(default)]
+    pub elevation: Option<ElevationUnit>,
+    /// The altitude the wind is reported for.
+    #[serde(default)]
+    pub wind_altitude: WindAltitude,
+    /// Whether the report ends with a closing that repeats the information letter (e.g.
+    /// `Kutaisi information Bravo, out`), so that the end of each loop is clearly audible.
+    #[serde(default)]
+    pub closing: bool,
+    /// The magnetic declination (in degrees, positive to the east) at the airfield, to compare
+    /// the true wind direction with the magnetic runway headings.
+    #[serde(default)]
+    pub magnetic_declination: f64,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// The unit of the announced field elevation.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum ElevationUnit {
+    Feet,
+    Meters,
+}
+
+/// The altitude the wind of an airfield is reported for. The wind is queried relative to the
+/// field elevation, so that mountain airfields don't report the wind of a lower altitude.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum WindAltitude {
+    /// The wind at the field elevation.
+    Surface,
+    /// The wind at the traffic pattern altitude (1000ft above the field elevation).
+    Pattern,
+}
+
+impl Default for WindAltitude {
+    fn default() -> Self {
+        WindAltitude::Surface
+    }
+}
+
+/// Runways pinned for landing and departure. A runway that isn't pinned (`None`) is the
+/// wind-derived runway in use.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RunwayOverride {
+    pub landing: Option<String>,
+    pub departure: Option<String>,
+    /// Whether the airfield's other runways are announced as available besides the runways in
+    /// use.
+    #[serde(default)]
+    pub announce_available: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TrafficPattern {
+    pub runway: String,
+    pub direction: PatternDirection,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum PatternDirection {
+    Left,
+    Right,
+}
+
+/// The trend group an airfield's full report ends with.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Trend {
+    /// Announces NOSIG if the weather didn't change significantly since the previous report.
+    Observed(#[serde(skip)] PreviousObservation),
+    /// Always announces NOSIG.
+    NoSignificantChange,
+    /// A scripted BECMG forecast, e.g. `BKN015`.
+    Becoming(String),
+    /// A scripted TEMPO forecast, e.g. `RA`.
+    Temporary(String),
+}
+
+/// The weather of an airfield's previous report, which the observed trend is derived from. It is
+/// shared between the clones of the airfield, and is neither compared nor (de)serialized.
+#[derive(Debug, Clone, Default)]
+pub struct PreviousObservation(Arc<Mutex<Option<WeatherInfo>>>);
+
+impl PartialEq for PreviousObservation {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// The increment the reported wind direction is rounded to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum WindRounding {
+    Exact,
+    Nearest5,
+    Nearest10,
+}
+
+impl Default for WindRounding {
+    fn default() -> Self {
+        // real-world ATIS reports the wind direction to the nearest 10 degrees
+        WindRounding::Nearest10
+    }
+}
+
+impl WindRounding {
+    /// Rounds the wind direction (in degrees) and formats it with three digits (e.g. `050`).
+    /// Northerly winds are reported as `360`.
+    pub fn format(self, wind_dir: f64) -> String {
+        let increment = match self {
+            WindRounding::Exact => 1.0,
+            WindRounding::Nearest5 => 5.0,
+            WindRounding::Nearest10 => 10.0,
+        };
+        let wind_dir = ((wind_dir.rem_euclid(360.0) / increment).round() * increment) as u16;
+        let wind_dir = if wind_dir == 0 { 360 } else { wind_dir };
+        format!("{:0>3}", wind_dir)
+    }
+}
+
+impl FromStr for WindRounding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().as_str() {
+            "EXACT" | "1" => Ok(WindRounding::Exact),
+            "5" => Ok(WindRounding::Nearest5),
+            "10" => Ok(WindRounding::Nearest10),
+            _ => Err(anyhow!(
+                "invalid wind direction rounding `{}` (expected 10, 5 or EXACT)",
+                s
+            )),
+        }
+    }
+}
+
+/// The convention the pressures (QNH and QFE) are rounded with in the reports.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum PressureRounding {
+    /// US convention: rounded to the nearest hundredth of an inHg (and the nearest hPa).
+    Us,
+    /// ICAO convention: rounded down to a whole hPa (and to the nearest hundredth of an inHg).
+    Icao,
+}
+
+impl Default for PressureRounding {
+    fn default() -> Self {
+        PressureRounding::Us
+    }
+}
+
+impl PressureRounding {
+    /// Converts the pressure (in Pa) to hundredths of an inHg (e.g. `2992` for 29.92 inHg),
+    /// rounded to the nearest hundredth.
+    pub fn inhg(self, pressure: f64) -> f64 {
+        (pressure * 0.02953).round()
+    }
+
+    /// Converts the pressure (in Pa) to hPa, rounded down for the ICAO convention and to the
+    /// nearest hPa otherwise.
+    pub fn hpa(self, pressure: f64) -> f64 {
+        let hpa = pressure / 100.0;
+        match self {
+            PressureRounding::Us => hpa.round(),
+            PressureRounding::Icao => hpa.floor(),
+        }
+    }
+}
+
+impl FromStr for PressureRounding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().as_str() {
+            "US" => Ok(PressureRounding::Us),
+            "ICAO" => Ok(PressureRounding::Icao),
+            _ => Err(anyhow!(
+                "invalid pressure rounding `{}` (expected US or ICAO)",
+                s
+            )),
+        }
+    }
+}
+
+/// The modulation a station transmits with.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Modulation {
+    Am,
+    Fm,
+}
+
+impl Default for Modulation {
+    fn default() -> Self {
+        Modulation::Am
+    }
+}
+
+impl Modulation {
+    /// The name of the modulation, as expected by the SRS client.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Modulation::Am => "AM",
+            Modulation::Fm => "FM",
+        }
+    }
+}
+
+impl FromStr for Modulation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().as_str() {
+            "AM" => Ok(Modulation::Am),
+            "FM" => Ok(Modulation::Fm),
+            _ => Err(anyhow!("invalid modulation `{}` (expected AM or FM)", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Carrier {
     pub name: String,
     pub unit_id: u32,
     pub unit_name: String,
+    /// The weather minimums the announced recovery case is derived from.
+    pub recovery_thresholds: RecoveryThresholds,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// The minimum ceiling and visibility of a carrier recovery case.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct RecoveryMinimums {
+    /// in ft
+    pub ceiling: f64,
+    /// in nm
+    pub visibility: f64,
+}
+
+/// The weather minimums of Case I and Case II recoveries. Everything below the Case II minimums
+/// (and every night recovery) is a Case III recovery.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct RecoveryThresholds {
+    case1: RecoveryMinimums,
+    case2: RecoveryMinimums,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Custom {
     pub unit_id: u32,
     pub unit_name: String,
-    pub message: String,
+    pub message: TextSource,
+    /// Whether the message is hand-written SSML, which is sent to the TTS provider as is.
+    pub ssml: bool,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// A user provided text, that is either set inline or read from a file. Files are read each time
+/// the text is requested, so edits to the file take effect without reloading the mission.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum TextSource {
+    Inline(String),
+    File(PathBuf),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct WeatherTransmitter {
     pub name: String,
     pub unit_id: u32,
@@ -54,21 +354,243 @@ pub struct WeatherTransmitter {
     pub info_ltr_offset: usize,
 }
 
+/// A FARP or helipad. As it has no runways, its report announces the landing pad instead.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Farp {
+    pub name: String,
+    pub unit_id: u32,
+    pub unit_name: String,
+    /// The identifier of the landing pad arriving helicopters are sent to.
+    pub pad: Option<String>,
+    pub info_ltr_offset: usize,
+}
+
 pub struct Report {
     pub textual: String,
     pub spoken: String,
     pub position: LatLngPosition,
+    /// The weather and runways the report has been generated from.
+    pub inputs: ReportInputs,
+}
+
+/// The weather and runways a report has been generated from, as they are announced (e.g. with the
+/// wind direction rounded), to tell in which parts two reports differ without parsing their text.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ReportInputs {
+    /// The weather, for reports that announce it.
+    pub weather: Option<ReportedWeather>,
+    /// The landing and the departing runway, for reports that announce them.
+    pub runways: Option<(String, String)>,
+    /// The information letter, for stations whose reports have one.
+    pub information_letter: Option<&'static str>,
+}
+
+/// The weather as it is announced in a report.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReportedWeather {
+    /// The rounded wind direction (e.g. `050`) and the wind speed (in knots).
+    pub wind: (String, u32),
+    /// The visibility (in m).
+    pub visibility: Option<u32>,
+    pub clouds: Option<Clouds>,
+    /// The temperature (in 0.1 °C).
+    pub temperature: i64,
+    /// The QNH and the QFE, in hundredths of an inHg and in hPa.
+    pub altimeter: (u32, u32, u32, u32),
+}
+
+impl ReportInputs {
+    fn new(
+        weather: Option<&WeatherInfo>,
+        wind_rounding: WindRounding,
+        pressure_rounding: PressureRounding,
+    ) -> Self {
+        ReportInputs {
+            weather: weather.map(|weather| ReportedWeather {
+                wind: (
+                    wind_rounding.format(weather.wind_dir),
+                    (weather.wind_speed * 1.94384).round() as u32, // to knots
+                ),
+                visibility: weather.visibility,
+                clouds: weather.clouds.clone(),
+                temperature: (weather.temperature * 10.0).round() as i64,
+                altimeter: (
+                    pressure_rounding.inhg(weather.pressure_qnh) as u32,
+                    pressure_rounding.hpa(weather.pressure_qnh) as u32,
+                    pressure_rounding.inhg(weather.pressure_qfe) as u32,
+                    pressure_rounding.hpa(weather.pressure_qfe) as u32,
+                ),
+            }),
+            runways: None,
+            information_letter: None,
+        }
+    }
+}
+
+/// A part of a report, used to describe in which parts a report differs from a previous one.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ReportField {
+    Runway,
+    Wind,
+    Visibility,
+    Clouds,
+    Temperature,
+    Altimeter,
+    /// Everything else, e.g. remarks, trends or custom messages. Only reported if none of the
+    /// other parts changed, since their changes also change the report's text.
+    Other,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReportDiff {
+    Unchanged,
+    /// The parts of the report that changed, in the order they appear in an ATIS report.
+    Changed(Vec<ReportField>),
+}
+
+impl ReportDiff {
+    pub fn is_changed(&self) -> bool {
+        *self != ReportDiff::Unchanged
+    }
+}
+
+impl Report {
+    /// Compares the report to the `previous` report of the same station. The information letter is
+    /// not considered a change, since it advances with every report.
+    pub fn diff(&self, previous: &Report) -> ReportDiff {
+        let mut changed = Vec::new();
+        if self.inputs.runways != previous.inputs.runways {
+            changed.push(ReportField::Runway);
+        }
+        match (&self.inputs.weather, &previous.inputs.weather) {
+            (Some(current), Some(previous)) => {
+                if current.wind != previous.wind {
+                    changed.push(ReportField::Wind);
+                }
+                if current.visibility != previous.visibility {
+                    changed.push(ReportField::Visibility);
+                }
+                if current.clouds != previous.clouds {
+                    changed.push(ReportField::Clouds);
+                }
+                if current.temperature != previous.temperature {
+                    changed.push(ReportField::Temperature);
+                }
+                if current.altimeter != previous.altimeter {
+                    changed.push(ReportField::Altimeter);
+                }
+            }
+            (None, None) => {}
+            _ => changed.extend(&[
+                ReportField::Wind,
+                ReportField::Visibility,
+                ReportField::Clouds,
+                ReportField::Temperature,
+                ReportField::Altimeter,
+            ]),
+        }
+
+        if changed.is_empty()
+            && (self.content(&self.textual) != previous.content(&previous.textual)
+                || self.content(&self.spoken) != previous.content(&previous.spoken))
+        {
+            changed.push(ReportField::Other);
+        }
+
+        if changed.is_empty() {
+            ReportDiff::Unchanged
+        } else {
+            ReportDiff::Changed(changed)
+        }
+    }
+
+    /// The given text of the report without its information letter.
+    fn content(&self, text: &str) -> String {
+        match self.inputs.information_letter {
+            Some(letter) => text.replace(&format!("information {}", letter), "information"),
+            None => text.to_string(),
+        }
+    }
+
+    /// Truncates the spoken report to at most `max_len` characters (not counting the `<speak>`
+    /// element of generated reports), cutting it after the last sentence that fits. Returns
+    /// whether the report has been truncated. Raw SSML broadcasts are never truncated, since that
+    /// would break their markup.
+    pub fn truncate(&mut self, max_len: usize) -> bool {
+        let (start, end) = if self.spoken.starts_with(SPEAK_START_TAG) {
+            (SPEAK_START_TAG, "\n</speak>")
+        } else if self.spoken.starts_with('<') {
+            return false;
+        } else {
+            ("", "")
+        };
+        let content = self.spoken[start.len()..]
+            .strip_suffix(end)
+            .unwrap_or(&self.spoken[start.len()..]);
+        match truncate_at_sentence(content, max_len) {
+            Some(truncated) => {
+                self.spoken = format!("{}{}{}", start, truncated, end);
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 const SPEAK_START_TAG: &str = "<speak version=\"1.0\" xml:lang=\"en-US\">\n";
 
 impl Station {
+    /// Whether messages of the given level are logged for this station.
+    pub fn log_enabled(&self, level: log::Level) -> bool {
+        self.log_level.map(|max| level <= max).unwrap_or(true)
+    }
+
     #[cfg(not(feature = "static-weather"))]
     pub async fn generate_report(&self, report_nr: usize) -> Result<Option<Report>, anyhow::Error> {
         match (self.rpc.as_ref(), &self.transmitter) {
             (Some(rpc), Transmitter::Airfield(airfield)) => {
-                let weather = rpc
-                    .get_weather_at(&airfield.position)
+                let metar_weather = match &airfield.metar {
+                    Some(metar) => match metar.get_weather_at(&airfield.position).await {
+                        Ok(weather) => Some(weather),
+                        Err(err) => {
+                            station_log!(
+                                self,
+                                log::Level::Warn,
+                                "Failed to get METAR {} for {}, falling back to the mission's weather: {}",
+                                metar.station, self.name, err
+                            );
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let weather = match metar_weather {
+                    Some(weather) => weather,
+                    None => airfield
+                        .get_weather(rpc.as_ref())
+                        .await
+                        .context("failed to retrieve weather")?,
+                };
+                let position = rpc
+                    .to_lat_lng(&airfield.position)
+                    .await
+                    .context("failed to retrieve unit position")?;
+
+                let report = airfield.report_from_weather(
+                    report_nr,
+                    &weather,
+                    self.wind_rounding,
+                    self.pressure_rounding,
+                    self.tts.english_variant(),
+                    position,
+                )?;
+                airfield.record_observation(&weather);
+
+                Ok(Some(report))
+            }
+            (Some(rpc), Transmitter::TrafficAdvisory(airfield)) => {
+                let weather = airfield
+                    .get_weather(rpc.as_ref())
                     .await
                     .context("failed to retrieve weather")?;
                 let position = rpc
@@ -77,9 +599,10 @@ impl Station {
                     .context("failed to retrieve unit position")?;
 
                 Ok(Some(Report {
-                    textual: airfield.generate_report(report_nr, &weather, false)?,
-                    spoken: airfield.generate_report(report_nr, &weather, true)?,
+                    textual: airfield.generate_traffic_advisory(&weather, false)?,
+                    spoken: airfield.generate_traffic_advisory(&weather, true)?,
                     position,
+                    inputs: self.report_inputs(report_nr, Some(&weather)),
                 }))
             }
             (Some(rpc), Transmitter::Carrier(unit)) => {
@@ -104,9 +627,24 @@ impl Station {
                         .context("failed to retrieve unit position")?;
 
                     Ok(Some(Report {
-                        textual: unit.generate_report(&weather, heading, mission_hour, false)?,
-                        spoken: unit.generate_report(&weather, heading, mission_hour, true)?,
+                        textual: unit.generate_report(
+                            &weather,
+                            heading,
+                            mission_hour,
+                            self.wind_rounding,
+                            self.pressure_rounding,
+                            false,
+                        )?,
+                        spoken: unit.generate_report(
+                            &weather,
+                            heading,
+                            mission_hour,
+                            self.wind_rounding,
+                            self.pressure_rounding,
+                            true,
+                        )?,
                         position,
+                        inputs: self.report_inputs(report_nr, Some(&weather)),
                     }))
                 } else {
                     Ok(None)
@@ -118,16 +656,17 @@ impl Station {
                     .await
                     .context("failed to retrieve unit position")?;
 
-                if let Some(pos) = pos {
+                if let (Some(pos), Some((textual, spoken))) = (pos, custom.read_message()) {
                     let position = rpc
                         .to_lat_lng(&pos)
                         .await
                         .context("failed to retrieve unit position")?;
 
                     Ok(Some(Report {
-                        textual: custom.message.clone(),
-                        spoken: custom.message.clone(),
+                        textual,
+                        spoken,
                         position,
+                        inputs: self.report_inputs(report_nr, None),
                     }))
                 } else {
                     Ok(None)
@@ -150,9 +689,62 @@ impl Station {
                         .context("failed to retrieve unit position")?;
 
                     Ok(Some(Report {
-                        textual: weather.generate_report(report_nr, &weather_info, false)?,
-                        spoken: weather.generate_report(report_nr, &weather_info, true)?,
+                        textual: weather.generate_report(
+                            report_nr,
+                            &weather_info,
+                            self.wind_rounding,
+                            self.pressure_rounding,
+                            self.tts.english_variant(),
+                            false,
+                        )?,
+                        spoken: weather.generate_report(
+                            report_nr,
+                            &weather_info,
+                            self.wind_rounding,
+                            self.pressure_rounding,
+                            self.tts.english_variant(),
+                            true,
+                        )?,
+                        position,
+                        inputs: self.report_inputs(report_nr, Some(&weather_info)),
+                    }))
+                } else {
+                    Ok(None)
+                }
+            }
+            (Some(rpc), Transmitter::Farp(farp)) => {
+                let pos = rpc
+                    .get_unit_position(&farp.unit_name)
+                    .await
+                    .context("failed to retrieve unit position")?;
+
+                if let Some(pos) = pos {
+                    let weather = rpc
+                        .get_weather_at(&pos)
+                        .await
+                        .context("failed to retrieve weather")?;
+                    let position = rpc
+                        .to_lat_lng(&pos)
+                        .await
+                        .context("failed to retrieve unit position")?;
+
+                    Ok(Some(Report {
+                        textual: farp.generate_report(
+                            report_nr,
+                            &weather,
+                            self.wind_rounding,
+                            self.pressure_rounding,
+                            false,
+                        )?,
+                        spoken: farp.generate_report(
+                            report_nr,
+                            &weather,
+                            self.wind_rounding,
+                            self.pressure_rounding,
+                            true,
+                        )?,
                         position,
+                        inputs: self.report_inputs(report_nr, Some(&weather)),
                     }))
                 } else {
                     Ok(None)
@@ -162,6 +754,59 @@ impl Station {
         }
     }
 
+    /// Returns the current position of the carrier a carrier station is attached to, which is
+    /// used to keep the transmitter's position in sync with the moving ship. Returns `None` if
+    /// the carrier doesn't exist (anymore), or if the station isn't a carrier station.
+    pub async fn carrier_position(&self) -> Result<Option<LatLngPosition>, anyhow::Error> {
+        match (self.rpc.as_ref(), &self.transmitter) {
+            (Some(rpc), Transmitter::Carrier(carrier)) => {
+                match rpc.get_unit_position(&carrier.unit_name).await? {
+                    Some(pos) => Ok(Some(rpc.to_lat_lng(&pos).await?)),
+                    None => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The inputs of the report with the given number, generated from the given weather.
+    fn report_inputs(&self, report_nr: usize, weather: Option<&WeatherInfo>) -> ReportInputs {
+        match (&self.transmitter, weather) {
+            (Transmitter::Airfield(airfield), Some(weather)) => airfield.report_inputs(
+                report_nr,
+                weather,
+                self.wind_rounding,
+                self.pressure_rounding,
+            ),
+            // traffic advisories have no information letter
+            (Transmitter::TrafficAdvisory(airfield), Some(weather)) => ReportInputs {
+                information_letter: None,
+                ..airfield.report_inputs(
+                    report_nr,
+                    weather,
+                    self.wind_rounding,
+                    self.pressure_rounding,
+                )
+            },
+            _ => ReportInputs {
+                information_letter: self.information_letter(report_nr),
+                ..ReportInputs::new(weather, self.wind_rounding, self.pressure_rounding)
+            },
+        }
+    }
+
+    /// The information letter of the report with the given number, for stations whose reports
+    /// have one (airfields, FARPs and weather stations).
+    pub fn information_letter(&self, report_nr: usize) -> Option<&'static str> {
+        let info_ltr_offset = match &self.transmitter {
+            Transmitter::Airfield(airfield) => airfield.info_ltr_offset,
+            Transmitter::Farp(farp) => farp.info_ltr_offset,
+            Transmitter::Weather(weather) => weather.info_ltr_offset,
+            _ => return None,
+        };
+        Some(phonetic_alphabet::lookup(info_ltr_offset + report_nr))
+    }
+
     #[cfg(feature = "static-weather")]
     pub async fn generate_report(&self, report_nr: usize) -> Result<Option<Report>, anyhow::Error> {
         let weather_info = WeatherInfo {
@@ -176,36 +821,267 @@ impl Station {
         };
 
         match &self.transmitter {
-            Transmitter::Airfield(airfield) => Ok(Some(Report {
-                textual: airfield.generate_report(report_nr, &weather_info, false)?,
-                spoken: airfield.generate_report(report_nr, &weather_info, true)?,
+            Transmitter::Airfield(airfield) => {
+                let report = Report {
+                    textual: airfield.generate_report(
+                        report_nr,
+                        &weather_info,
+                        self.wind_rounding,
+                        self.pressure_rounding,
+                        self.tts.english_variant(),
+                        false,
+                    )?,
+                    spoken: airfield.generate_report(
+                        report_nr,
+                        &weather_info,
+                        self.wind_rounding,
+                        self.pressure_rounding,
+                        self.tts.english_variant(),
+                        true,
+                    )?,
+                    position: LatLngPosition::default(),
+                    inputs: self.report_inputs(report_nr, Some(&weather_info)),
+                };
+                airfield.record_observation(&weather_info);
+
+                Ok(Some(report))
+            }
+            Transmitter::TrafficAdvisory(airfield) => Ok(Some(Report {
+                textual: airfield.generate_traffic_advisory(&weather_info, false)?,
+                spoken: airfield.generate_traffic_advisory(&weather_info, true)?,
                 position: LatLngPosition::default(),
+                inputs: self.report_inputs(report_nr, Some(&weather_info)),
             })),
             Transmitter::Carrier(unit) => {
                 let heading = 180.0;
                 let mission_hour = 7;
 
                 Ok(Some(Report {
-                    textual: unit.generate_report(&weather_info, heading, mission_hour, false)?,
-                    spoken: unit.generate_report(&weather_info, heading, mission_hour, true)?,
+                    textual: unit.generate_report(
+                        &weather_info,
+                        heading,
+                        mission_hour,
+                        self.wind_rounding,
+                        self.pressure_rounding,
+                        false,
+                    )?,
+                    spoken: unit.generate_report(
+                        &weather_info,
+                        heading,
+                        mission_hour,
+                        self.wind_rounding,
+                        self.pressure_rounding,
+                        true,
+                    )?,
+                    position: LatLngPosition::default(),
+                    inputs: self.report_inputs(report_nr, Some(&weather_info)),
+                }))
+            }
+            Transmitter::Custom(custom) => {
+                Ok(custom.read_message().map(|(textual, spoken)| Report {
+                    textual,
+                    spoken,
                     position: LatLngPosition::default(),
+                    inputs: self.report_inputs(report_nr, None),
                 }))
             }
-            Transmitter::Custom(custom) => Ok(Some(Report {
-                textual: custom.message.clone(),
-                spoken: custom.message.clone(),
+            Transmitter::Weather(weather) => Ok(Some(Report {
+                textual: weather.generate_report(
+                    report_nr,
+                    &weather_info,
+                    self.wind_rounding,
+                    self.pressure_rounding,
+                    self.tts.english_variant(),
+                    false,
+                )?,
+                spoken: weather.generate_report(
+                    report_nr,
+                    &weather_info,
+                    self.wind_rounding,
+                    self.pressure_rounding,
+                    self.tts.english_variant(),
+                    true,
+                )?,
                 position: LatLngPosition::default(),
+                inputs: self.report_inputs(report_nr, Some(&weather_info)),
             })),
-            Transmitter::Weather(weather) => Ok(Some(Report {
-                textual: weather.generate_report(report_nr, &weather_info, false)?,
-                spoken: weather.generate_report(report_nr, &weather_info, true)?,
+            Transmitter::Farp(farp) => Ok(Some(Report {
+                textual: farp.generate_report(
+                    report_nr,
+                    &weather_info,
+                    self.wind_rounding,
+                    self.pressure_rounding,
+                    false,
+                )?,
+                spoken: farp.generate_report(
+                    report_nr,
+                    &weather_info,
+                    self.wind_rounding,
+                    self.pressure_rounding,
+                    true,
+                )?,
                 position: LatLngPosition::default(),
+                inputs: self.report_inputs(report_nr, Some(&weather_info)),
             })),
         }
     }
 }
 
 impl Airfield {
+    /// Generates the airfield's full report from the weather of the given source, without a
+    /// running mission or station, e.g. to preview reports in offline tooling. Unlike a station,
+    /// it always uses the weather of the source (never a real-world METAR). Rendering has no side
+    /// effects: the weather isn't recorded for the observed trend (see
+    /// [`Airfield::record_observation`]), so a preview doesn't change the trend of a live station.
+    #[cfg(not(feature = "static-weather"))]
+    pub async fn render_report(
+        &self,
+        source: &dyn WeatherSource,
+        report_nr: usize,
+        wind_rounding: WindRounding,
+        pressure_rounding: PressureRounding,
+        english: EnglishVariant,
+    ) -> Result<Report, anyhow::Error> {
+        let weather = self
+            .get_weather(source)
+            .await
+            .context("failed to retrieve weather")?;
+        let position = source
+            .to_lat_lng(&self.position)
+            .await
+            .context("failed to retrieve unit position")?;
+
+        self.report_from_weather(
+            report_nr,
+            &weather,
+            wind_rounding,
+            pressure_rounding,
+            english,
+            position,
+        )
+    }
+
+    /// Generates both the textual and the spoken report from the given weather.
+    #[cfg(not(feature = "static-weather"))]
+    fn report_from_weather(
+        &self,
+        report_nr: usize,
+        weather: &WeatherInfo,
+        wind_rounding: WindRounding,
+        pressure_rounding: PressureRounding,
+        english: EnglishVariant,
+        position: LatLngPosition,
+    ) -> Result<Report, anyhow::Error> {
+        Ok(Report {
+            textual: self.generate_report(
+                report_nr,
+                weather,
+                wind_rounding,
+                pressure_rounding,
+                english,
+                false,
+            )?,
+            spoken: self.generate_report(
+                report_nr,
+                weather,
+                wind_rounding,
+                pressure_rounding,
+                english,
+                true,
+            )?,
+            position,
+            inputs: self.report_inputs(report_nr, weather, wind_rounding, pressure_rounding),
+        })
+    }
+
+    /// The inputs of a report generated from the given weather.
+    fn report_inputs(
+        &self,
+        report_nr: usize,
+        weather: &WeatherInfo,
+        wind_rounding: WindRounding,
+        pressure_rounding: PressureRounding,
+    ) -> ReportInputs {
+        ReportInputs {
+            runways: self
+                .runways_in_use(weather.wind_dir)
+                .map(|(landing, departure)| (landing.to_string(), departure.to_string())),
+            information_letter: Some(phonetic_alphabet::lookup(self.info_ltr_offset + report_nr)),
+            ..ReportInputs::new(Some(weather), wind_rounding, pressure_rounding)
+        }
+    }
+
+    /// Retrieves the weather at the field elevation, with the wind taken from the configured
+    /// wind altitude.
+    #[cfg(not(feature = "static-weather"))]
+    async fn get_weather(&self, rpc: &dyn WeatherSource) -> Result<WeatherInfo, anyhow::Error> {
+        /// The height (in m) of the traffic pattern above the field elevation.
+        const PATTERN_HEIGHT: f64 = 304.8;
+
+        let mut weather = rpc.get_weather_at(&self.position).await?;
+        if self.wind_altitude == WindAltitude::Pattern {
+            let pattern_position = Position {
+                alt: self.position.alt + PATTERN_HEIGHT,
+                ..self.position.clone()
+            };
+            let pattern_weather = rpc.get_weather_at(&pattern_position).await?;
+            weather.wind_speed = pattern_weather.wind_speed;
+            weather.wind_dir = pattern_weather.wind_dir;
+        }
+        Ok(weather)
+    }
+
+    /// Whether the airfield has a runway with the given designation (e.g. `04` or `22R`).
+    pub fn has_runway(&self, rwy: &str) -> bool {
+        let rwy = normalize_runway(rwy);
+        self.runways.iter().any(|r| normalize_runway(r) == rwy)
+    }
+
+    /// Validates that the given runway exists at the airfield.
+    pub fn validate_runway(&self, rwy: &str) -> Result<(), anyhow::Error> {
+        if self.has_runway(rwy) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "runway {} does not exist at {} (available runways: {})",
+                rwy,
+                self.name,
+                self.runways.join(", ")
+            ))
+        }
+    }
+
+    /// Validates that the runways of the override exist at the airfield.
+    pub fn validate_runway_override(&self, rwy: &RunwayOverride) -> Result<(), anyhow::Error> {
+        for rwy in rwy.landing.iter().chain(&rwy.departure) {
+            self.validate_runway(rwy)?;
+        }
+        Ok(())
+    }
+
+    /// The traffic pattern direction of the given runway, which is left unless configured
+    /// otherwise. Since the wind-derived runway in use has no `L`/`R`/`C` suffix, a runway without
+    /// suffix also matches the pattern of a runway with suffix (e.g. `22` matches `22R`).
+    pub fn pattern_direction(&self, rwy: &str) -> PatternDirection {
+        self.configured_pattern(rwy)
+            .map(|pattern| pattern.direction)
+            .unwrap_or_default()
+    }
+
+    /// The traffic pattern configured for the given runway, if any.
+    fn configured_pattern(&self, rwy: &str) -> Option<&TrafficPattern> {
+        let lrc: &[_] = &['L', 'R', 'C'];
+        let rwy = normalize_runway(rwy);
+        self.traffic_patterns
+            .iter()
+            .find(|pattern| normalize_runway(&pattern.runway) == rwy)
+            .or_else(|| {
+                self.traffic_patterns
+                    .iter()
+                    .find(|pattern| normalize_runway(&pattern.runway).trim_end_matches(lrc) == rwy)
+            })
+    }
+
     fn get_active_runway(&self, wind_dir: f64) -> Option<&str> {
         let lr: &[_] = &['L', 'R'];
         for rwy in &self.runways {
@@ -225,103 +1101,377 @@ impl Airfield {
         None
     }
 
-    pub fn generate_report(
-        &self,
-        report_nr: usize,
-        weather: &WeatherInfo,
-        spoken: bool,
-    ) -> Result<String, anyhow::Error> {
-        #[cfg(not(test))]
-        let _break = if spoken { "\n" } else { "" };
-        #[cfg(test)]
-        let _break = if spoken { "| " } else { "" };
-
-        let information_letter = phonetic_alphabet::lookup(self.info_ltr_offset + report_nr);
-        let mut report = if spoken { SPEAK_START_TAG } else { "" }.to_string();
+    /// The landing and the departing runway. Runways that aren't pinned by the runway override
+    /// are the wind-derived runway in use.
+    fn runways_in_use(&self, wind_dir: f64) -> Option<(&str, &str)> {
+        let active = || self.get_active_runway(wind_dir);
+        let (landing, departure) = match &self.runway_override {
+            Some(rwy) => (
+                rwy.landing.as_deref().or_else(active),
+                rwy.departure.as_deref().or_else(active),
+            ),
+            None => {
+                let rwy = active();
+                (rwy, rwy)
+            }
+        };
+        Some((landing?, departure?))
+    }
 
-        report += &format!(
-            "This is {} information {}. {}",
-            self.name, information_letter, _break
-        );
+    /// Announces the runway in use, or the landing and the departing runway if they differ.
+    fn runway_report(&self, wind_dir: f64, spoken: bool) -> Option<String> {
+        let (landing, departure) = match self.runways_in_use(wind_dir) {
+            Some(rwys) => rwys,
+            None => {
+                // airfields without runways (e.g. seaplane bases) simply have none to announce
+                if !self.runways.is_empty() {
+                    error!("Could not find active runway for {}", self.name);
+                }
+                return None;
+            }
+        };
 
-        if let Some(rwy) = self.get_active_runway(weather.wind_dir) {
-            let rwy = pronounce_number(rwy, spoken);
-            report += &format!("Runway in use is {}. {}", rwy, _break);
+        let mut report = if normalize_runway(landing) == normalize_runway(departure) {
+            format!("Runway in use is {}", pronounce_number(landing, spoken))
         } else {
-            error!("Could not find active runway for {}", self.name);
+            format!(
+                "Landing runway {}, departing runway {}",
+                pronounce_number(landing, spoken),
+                pronounce_number(departure, spoken),
+            )
+        };
+
+        let announce_available = self
+            .runway_override
+            .as_ref()
+            .map(|rwy| rwy.announce_available)
+            .unwrap_or(false);
+        if announce_available {
+            let available = self.available_runways(&[landing, departure]);
+            if let Some((last, others)) = available.split_last() {
+                let last = pronounce_number(last, spoken);
+                if others.is_empty() {
+                    report += &format!(", runway {} available", last);
+                } else {
+                    let others = others
+                        .iter()
+                        .map(|rwy| pronounce_number(rwy, spoken))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    report += &format!(", runways {} and {} available", others, last);
+                }
+            }
         }
 
-        let wind_dir = format!("{:0>3}", weather.wind_dir.round().to_string());
-        report += &format!(
-            "Wind {} at {} knots. {}",
-            pronounce_number(wind_dir, spoken),
-            pronounce_number((weather.wind_speed * 1.94384).round(), spoken), // to knots
-            _break,
-        );
+        Some(report)
+    }
 
-        let mut visibility = None;
-        if let Some(ref clouds_report) = weather.clouds {
-            if self.position.alt > clouds_report.base as f64
-                && self.position.alt < clouds_report.base as f64 + clouds_report.thickness as f64
-                && clouds_report.density >= 9
+    /// The airfield's runways that aren't in use (e.g. `31` if `13` is in use).
+    fn available_runways(&self, in_use: &[&str]) -> Vec<&str> {
+        let in_use: Vec<_> = in_use.iter().map(|rwy| normalize_runway(rwy)).collect();
+        let mut available: Vec<&str> = Vec::new();
+        for rwy in &self.runways {
+            let normalized = normalize_runway(rwy);
+            if !in_use.contains(&normalized)
+                && !available.iter().any(|r| normalize_runway(r) == normalized)
             {
-                // the airport is within completely condensed clouds
-                visibility = Some(0);
+                available.push(rwy);
             }
         }
+        available
+    }
 
-        if let Some(visibility) = visibility.or(weather.visibility) {
-            // 9260 m = 5 nm
-            if visibility < 9_260 {
-                report += &format!("{}. {}", get_visibility_report(visibility, spoken), _break);
+    /// Announces the configured trend group, if any. An observed trend is only announced (as
+    /// NOSIG) if the weather didn't change significantly since the previously recorded one.
+    fn trend_report(&self, weather: &WeatherInfo, spoken: bool) -> Option<String> {
+        match self.trend.as_ref()? {
+            Trend::Observed(previous) => {
+                let previous = previous.0.lock().unwrap();
+                match previous.as_ref() {
+                    Some(previous) if !is_significant_change(previous, weather) => {
+                        Some(trend_phrase("NOSIG", "No significant change", None, spoken))
+                    }
+                    _ => None,
+                }
+            }
+            Trend::NoSignificantChange => {
+                Some(trend_phrase("NOSIG", "No significant change", None, spoken))
+            }
+            Trend::Becoming(text) => Some(trend_phrase("BECMG", "Becoming", Some(text), spoken)),
+            Trend::Temporary(text) => {
+                Some(trend_phrase("TEMPO", "Temporarily", Some(text), spoken))
             }
         }
+    }
 
-        if let Some(clouds_report) = weather
-            .clouds
-            .as_ref()
-            .and_then(|clouds| get_clouds_report(clouds, spoken))
-        {
-            report += &format!("{}. {}", clouds_report, _break);
+    /// Remembers the weather of a generated report, which the next report's observed trend is
+    /// compared against.
+    pub fn record_observation(&self, weather: &WeatherInfo) {
+        if let Some(Trend::Observed(previous)) = &self.trend {
+            *previous.0.lock().unwrap() = Some(weather.clone());
+        }
+    }
+
+    /// Announces the traffic pattern direction of the landing runway (e.g. `Right traffic runway
+    /// 13`). The default left traffic is only announced if `announce_default` is set or if it is
+    /// explicitly configured for the runway.
+    fn traffic_pattern_report(
+        &self,
+        wind_dir: f64,
+        spoken: bool,
+        announce_default: bool,
+    ) -> Option<String> {
+        let (rwy, _) = self.runways_in_use(wind_dir)?;
+        if !announce_default && self.configured_pattern(rwy).is_none() {
+            return None;
+        }
+
+        Some(format!(
+            "{} traffic runway {}",
+            self.pattern_direction(rwy),
+            pronounce_number(rwy, spoken)
+        ))
+    }
+
+    /// Announces the headwind (or tailwind) and crosswind components on the landing runway (e.g.
+    /// `Runway 13, headwind 6 knots, crosswind 8 knots from the right`), if enabled.
+    fn wind_components_report(&self, weather: &WeatherInfo, spoken: bool) -> Option<String> {
+        if !self.wind_components {
+            return None;
+        }
+
+        let (rwy, _) = self.runways_in_use(weather.wind_dir)?;
+        // the runway designator is magnetic, while the wind direction is true
+        let heading = runway_heading(rwy)? + self.magnetic_declination;
+        let wind_speed = weather.wind_speed * 1.94384; // to knots
+        let (headwind, crosswind) = wind_components(weather.wind_dir, wind_speed, heading);
+        let (headwind, crosswind) = (headwind.round(), crosswind.round());
+
+        // components that round to zero are omitted
+        let mut components = Vec::with_capacity(2);
+        if headwind < 0.0 {
+            components.push(format!(
+                "tailwind {} knots",
+                pronounce_number(-headwind, spoken)
+            ));
+        } else if headwind > 0.0 {
+            components.push(format!(
+                "headwind {} knots",
+                pronounce_number(headwind, spoken)
+            ));
+        }
+        if crosswind != 0.0 {
+            components.push(format!(
+                "crosswind {} knots from the {}",
+                pronounce_number(crosswind.abs(), spoken),
+                if crosswind < 0.0 { "left" } else { "right" }
+            ));
+        }
+        if components.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "Runway {}, {}",
+            pronounce_number(rwy, spoken),
+            components.join(", ")
+        ))
+    }
+
+    /// Announces the field elevation (e.g. `Field elevation 148 feet`), if enabled.
+    fn elevation_report(&self, spoken: bool) -> Option<String> {
+        let (elevation, unit) = match self.elevation? {
+            ElevationUnit::Feet => (m_to_ft(self.position.alt), "feet"),
+            ElevationUnit::Meters => (self.position.alt, "meters"),
+        };
+        Some(format!(
+            "Field elevation {} {}",
+            pronounce_number(elevation.round(), spoken),
+            unit
+        ))
+    }
+
+    pub fn generate_report(
+        &self,
+        report_nr: usize,
+        weather: &WeatherInfo,
+        wind_rounding: WindRounding,
+        pressure_rounding: PressureRounding,
+        english: EnglishVariant,
+        spoken: bool,
+    ) -> Result<String, anyhow::Error> {
+        #[cfg(not(test))]
+        let _break = if spoken { "\n" } else { "" };
+        #[cfg(test)]
+        let _break = if spoken { "| " } else { "" };
+
+        let information_letter = phonetic_alphabet::lookup(self.info_ltr_offset + report_nr);
+        let mut report = if spoken { SPEAK_START_TAG } else { "" }.to_string();
+
+        if let Some(intro) = self.intro.as_ref().and_then(|intro| intro.read()) {
+            report += &format!("{}. {}", phrase(&intro, spoken), _break);
         }
 
+        // the spoken report uses a pronounceable form of the name (e.g. without accents)
+        let name = if spoken {
+            escape(&spoken_airfield_name(&self.name), spoken)
+        } else {
+            self.name.clone()
+        };
         report += &format!(
-            "Temperature {} celcius. {}",
-            pronounce_number(round(weather.temperature, 1), spoken),
-            _break,
+            "This is {} information {}. {}",
+            name, information_letter, _break
         );
 
-        report += &format!(
-            "ALTIMETER {}. {}",
-            // inHg, but using 0.02953 instead of 0.0002953 since we don't want to speak the
-            // DECIMAL here
-            pronounce_number((weather.pressure_qnh * 0.02953).round(), spoken),
-            _break,
+        if let Some(runway_report) = self.runway_report(weather.wind_dir, spoken) {
+            report += &format!("{}. {}", runway_report, _break);
+        }
+
+        if !self.simple_report {
+            if let Some(pattern_report) =
+                self.traffic_pattern_report(weather.wind_dir, spoken, false)
+            {
+                report += &format!("{}. {}", pattern_report, _break);
+            }
+        }
+
+        let altimeter = format!(
+            "{}. {}",
+            altimeter_report(weather, pressure_rounding, spoken),
+            _break
         );
+        if self.simple_report {
+            report += &altimeter;
+        } else {
+            report += &format!(
+                "{}. {}",
+                wind_report(weather, wind_rounding, spoken),
+                _break
+            );
+
+            if let Some(components_report) = self.wind_components_report(weather, spoken) {
+                report += &format!("{}. {}", components_report, _break);
+            }
+
+            let mut visibility = None;
+            if let Some(ref clouds_report) = weather.clouds {
+                if self.position.alt > clouds_report.base as f64
+                    && self.position.alt
+                        < clouds_report.base as f64 + clouds_report.thickness as f64
+                    && clouds_report.density >= 9
+                {
+                    // the airport is within completely condensed clouds
+                    visibility = Some(0);
+                }
+            }
+
+            if let Some(visibility) = visibility.or(weather.visibility) {
+                // 9260 m = 5 nm
+                if visibility < 9_260 {
+                    report += &format!("{}. {}", get_visibility_report(visibility, spoken), _break);
+                }
+            }
+
+            if let Some(clouds_report) = weather
+                .clouds
+                .as_ref()
+                .and_then(|clouds| get_clouds_report(clouds, self.position.alt, english, spoken))
+            {
+                report += &format!("{}. {}", clouds_report, _break);
+            }
+
+            report += &format!(
+                "Temperature {} celcius. {}",
+                pronounce_number(round(weather.temperature, 1), spoken),
+                _break,
+            );
+
+            report += &altimeter;
+
+            if let Some(traffic_freq) = self.traffic_freq {
+                report += &format!(
+                    "Contact traffic on {}. {}",
+                    pronounce_number(format!("{:.3}", traffic_freq as f64 / 1_000_000.0), spoken),
+                    _break
+                );
+            }
 
-        if let Some(traffic_freq) = self.traffic_freq {
+            report += &format!("REMARKS. {}", _break,);
+            report += &format!(
+                "{}. {}",
+                qnh_hpa_report(weather, pressure_rounding, spoken),
+                _break
+            );
             report += &format!(
-                "Traffic frequency {}. {}",
-                pronounce_number(round(traffic_freq as f64 / 1_000_000.0, 3), spoken),
+                "{}. {}",
+                qfe_report(weather, pressure_rounding, spoken),
                 _break
             );
+
+            if let Some(elevation_report) = self.elevation_report(spoken) {
+                report += &format!("{}. {}", elevation_report, _break);
+            }
+
+            if let Some(trend_report) = self.trend_report(weather, spoken) {
+                report += &format!("{}. {}", trend_report, _break);
+            }
         }
 
-        report += &format!("REMARKS. {}", _break,);
-        report += &format!(
-            "{} hectopascal. {}",
-            pronounce_number((weather.pressure_qnh / 100.0).round(), spoken), // to hPA
-            _break,
-        );
-        report += &format!(
-            "QFE {} or {}. {}",
-            pronounce_number((weather.pressure_qfe * 0.02953).round(), spoken), // to inHg
-            pronounce_number((weather.pressure_qfe / 100.0).round(), spoken),   // to hPA
-            _break,
-        );
+        if let Some(remarks) = self.remarks.as_ref().and_then(|remarks| remarks.read()) {
+            report += &format!("Remarks, {}. {}", phrase(&remarks, spoken), _break);
+        }
 
         report += &format!("End information {}.", information_letter);
 
+        if let Some(outro) = self.outro.as_ref().and_then(|outro| outro.read()) {
+            report += &format!(" {}{}.", _break, phrase(&outro, spoken));
+        }
+
+        if self.closing {
+            report += &format!(
+                " {}{} information {}, out.",
+                _break, name, information_letter
+            );
+        }
+
+        if spoken {
+            report += "\n</speak>";
+        }
+
+        Ok(report)
+    }
+
+    /// Generates the short advisory that is broadcast on the airfield's traffic frequency. It
+    /// only announces the runway in use and its traffic pattern direction.
+    pub fn generate_traffic_advisory(
+        &self,
+        weather: &WeatherInfo,
+        spoken: bool,
+    ) -> Result<String, anyhow::Error> {
+        #[cfg(not(test))]
+        let _break = if spoken { "\n" } else { "" };
+        #[cfg(test)]
+        let _break = if spoken { "| " } else { "" };
+
+        let mut report = if spoken { SPEAK_START_TAG } else { "" }.to_string();
+
+        let name = if spoken {
+            escape(&spoken_airfield_name(&self.name), spoken)
+        } else {
+            self.name.clone()
+        };
+        report += &format!("{} traffic advisory. {}", name, _break);
+
+        if let Some(runway_report) = self.runway_report(weather.wind_dir, spoken) {
+            report += &format!("{}. {}", runway_report, _break);
+        }
+
+        if let Some(pattern_report) = self.traffic_pattern_report(weather.wind_dir, spoken, true) {
+            report += &format!("{}. {}", pattern_report, _break);
+        }
+
+        report += "End of advisory.";
+
         if spoken {
             report += "\n</speak>";
         }
@@ -336,6 +1486,8 @@ impl Carrier {
         weather: &WeatherInfo,
         heading: f64,
         mission_hour: u16,
+        wind_rounding: WindRounding,
+        pressure_rounding: PressureRounding,
         spoken: bool,
     ) -> Result<String, anyhow::Error> {
         #[cfg(not(test))]
@@ -347,10 +1499,10 @@ impl Carrier {
 
         report += &format!("{}, {}", pronounce_number(99, spoken), _break);
 
-        let wind_dir = format!("{:0>3}", weather.wind_dir.round().to_string());
+        let wind_dir = wind_rounding.format(weather.wind_dir);
         report += &format!(
             "{}'s wind {} at {} knots, {}",
-            self.name,
+            escape(&self.name, spoken),
             pronounce_number(wind_dir, spoken),
             pronounce_number(weather.wind_speed.round(), spoken),
             _break,
@@ -358,38 +1510,14 @@ impl Carrier {
 
         report += &format!(
             "altimeter {}, {}",
-            // inHg, but using 0.02953 instead of 0.0002953 since we don't want to speak the
-            // DECIMAL here
-            pronounce_number((weather.pressure_qnh * 0.02953).round(), spoken),
+            // in hundredths of an inHg, since we don't want to speak the DECIMAL here
+            pronounce_number(pressure_rounding.inhg(weather.pressure_qnh), spoken),
             _break,
         );
 
-        // Case 1: daytime, ceiling >= 3000ft; visibility distance >= 5nm
-        // Case 2: daytime, ceiling >= 1000ft; visibility distance >= 5nm
-        // Case 3: nighttime or daytime, ceiling < 1000ft and visibility distance <= 5nm
-        let mut case = 1;
-        if let Some(ceiling) = weather.clouds.as_ref().map(|clouds| clouds.base) {
-            let ft = m_to_ft(ceiling as f64);
-            if ft < 1_000.0 {
-                case = 3;
-            } else if ft < 3_000.0 {
-                case = 2;
-            }
-        }
-
-        if let Some(visibility) = weather.visibility {
-            // 9260 m = 5 nm
-            if visibility < 9_260 {
-                case = 3;
-            }
-        }
-
-        // night time is only estimated, it could be improved by somehow taking the different time-
-        // zones of the different maps and the mission date into account.
-        if mission_hour >= 21 || mission_hour <= 5 {
-            case = 3;
-        }
-
+        let case = self
+            .recovery_thresholds
+            .recovery_case(weather, mission_hour);
         report += &format!("CASE {}, {}", case, _break,);
 
         let brc = heading.to_degrees().round();
@@ -418,11 +1546,118 @@ impl Carrier {
     }
 }
 
+impl RecoveryThresholds {
+    /// Creates thresholds from the Case I and Case II minimums. Fails if the Case I minimums are
+    /// lower than the Case II minimums.
+    pub fn new(case1: RecoveryMinimums, case2: RecoveryMinimums) -> Result<Self, anyhow::Error> {
+        let is_valid = |v: f64| v.is_finite() && v >= 0.0;
+        for minimums in &[case1, case2] {
+            if !is_valid(minimums.ceiling) || !is_valid(minimums.visibility) {
+                return Err(anyhow!("the recovery minimums must not be negative"));
+            }
+        }
+        if case1.ceiling < case2.ceiling || case1.visibility < case2.visibility {
+            return Err(anyhow!(
+                "the Case I minimums ({}) must not be lower than the Case II minimums ({})",
+                case1,
+                case2
+            ));
+        }
+        Ok(RecoveryThresholds { case1, case2 })
+    }
+
+    pub fn case1(&self) -> RecoveryMinimums {
+        self.case1
+    }
+
+    pub fn case2(&self) -> RecoveryMinimums {
+        self.case2
+    }
+
+    /// Case 1: daytime, ceiling and visibility at or above the Case I minimums
+    /// Case 2: daytime, ceiling and visibility at or above the Case II minimums
+    /// Case 3: nighttime, or ceiling or visibility below the Case II minimums
+    pub fn recovery_case(&self, weather: &WeatherInfo, mission_hour: u16) -> u8 {
+        // night time is only estimated, it could be improved by somehow taking the different time-
+        // zones of the different maps and the mission date into account.
+        if mission_hour >= 21 || mission_hour <= 5 {
+            return 3;
+        }
+
+        let ceiling = weather
+            .clouds
+            .as_ref()
+            .map(|clouds| m_to_ft(clouds.base as f64));
+        let meets = |minimums: &RecoveryMinimums| {
+            ceiling.map(|ft| ft >= minimums.ceiling).unwrap_or(true)
+                && weather
+                    .visibility
+                    // 1 nm = 1852 m
+                    .map(|m| f64::from(m) >= minimums.visibility * 1_852.0)
+                    .unwrap_or(true)
+        };
+
+        if meets(&self.case1) {
+            1
+        } else if meets(&self.case2) {
+            2
+        } else {
+            3
+        }
+    }
+}
+
+impl Default for RecoveryThresholds {
+    fn default() -> Self {
+        RecoveryThresholds {
+            case1: RecoveryMinimums {
+                ceiling: 3_000.0,
+                visibility: 5.0,
+            },
+            case2: RecoveryMinimums {
+                ceiling: 1_000.0,
+                visibility: 5.0,
+            },
+        }
+    }
+}
+
+impl FromStr for RecoveryMinimums {
+    type Err = anyhow::Error;
+
+    /// Parses minimums in the form `{ceiling in ft}/{visibility in nm}` (e.g. `3000/5`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            anyhow!(
+                "invalid recovery minimums `{}` (expected {{ceiling in ft}}/{{visibility in nm}}, \
+                 e.g. 3000/5)",
+                s
+            )
+        };
+        let mut parts = s.splitn(2, '/');
+        let ceiling = parts.next().unwrap_or("").trim();
+        let visibility = parts.next().ok_or_else(invalid)?.trim();
+        Ok(RecoveryMinimums {
+            ceiling: ceiling.parse().map_err(|_| invalid())?,
+            visibility: visibility.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl fmt::Display for RecoveryMinimums {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}ft/{}nm", self.ceiling, self.visibility)
+    }
+}
+
 impl WeatherTransmitter {
     pub fn generate_report(
         &self,
         report_nr: usize,
         weather: &WeatherInfo,
+        wind_rounding: WindRounding,
+        pressure_rounding: PressureRounding,
+        english: EnglishVariant,
         spoken: bool,
     ) -> Result<String, anyhow::Error> {
         #[cfg(not(test))]
@@ -435,17 +1670,15 @@ impl WeatherTransmitter {
 
         report += &format!(
             "This is weather station {} information {}. {}",
-            self.name, information_letter, _break
+            escape(&self.name, spoken),
+            information_letter,
+            _break
         );
 
-        // TODO: reduce redundancy with ATIS report generation
-
-        let wind_dir = format!("{:0>3}", weather.wind_dir.round().to_string());
         report += &format!(
-            "Wind {} at {} knots. {}",
-            pronounce_number(wind_dir, spoken),
-            pronounce_number((weather.wind_speed * 1.94384).round(), spoken), // to knots
-            _break,
+            "{}. {}",
+            wind_report(weather, wind_rounding, spoken),
+            _break
         );
 
         let mut visibility = None;
@@ -469,7 +1702,7 @@ impl WeatherTransmitter {
         if let Some(clouds_report) = weather
             .clouds
             .as_ref()
-            .and_then(|clouds| get_clouds_report(clouds, spoken))
+            .and_then(|clouds| get_clouds_report(clouds, weather.position.alt, english, spoken))
         {
             report += &format!("{}. {}", clouds_report, _break);
         }
@@ -481,24 +1714,21 @@ impl WeatherTransmitter {
         );
 
         report += &format!(
-            "ALTIMETER {}. {}",
-            // inHg, but using 0.02953 instead of 0.0002953 since we don't want to speak the
-            // DECIMAL here
-            pronounce_number((weather.pressure_qnh * 0.02953).round(), spoken),
-            _break,
+            "{}. {}",
+            altimeter_report(weather, pressure_rounding, spoken),
+            _break
         );
 
         report += &format!("REMARKS. {}", _break,);
         report += &format!(
-            "{} hectopascal. {}",
-            pronounce_number((weather.pressure_qnh / 100.0).round(), spoken), // to hPA
-            _break,
+            "{}. {}",
+            qnh_hpa_report(weather, pressure_rounding, spoken),
+            _break
         );
         report += &format!(
-            "QFE {} or {}. {}",
-            pronounce_number((weather.pressure_qfe * 0.02953).round(), spoken), // to inHg
-            pronounce_number((weather.pressure_qfe / 100.0).round(), spoken),   // to hPA
-            _break,
+            "{}. {}",
+            qfe_report(weather, pressure_rounding, spoken),
+            _break
         );
 
         report += &format!("End information {}.", information_letter);
@@ -511,66 +1741,536 @@ impl WeatherTransmitter {
     }
 }
 
-fn get_visibility_report(visibility: u32, spoken: bool) -> String {
-    let visibility = round(m_to_nm(f64::from(visibility)), 1);
-    format!("Visibility {}", pronounce_number(visibility, spoken))
-}
+impl Farp {
+    pub fn generate_report(
+        &self,
+        report_nr: usize,
+        weather: &WeatherInfo,
+        wind_rounding: WindRounding,
+        pressure_rounding: PressureRounding,
+        spoken: bool,
+    ) -> Result<String, anyhow::Error> {
+        #[cfg(not(test))]
+        let _break = if spoken { "\n" } else { "" };
+        #[cfg(test)]
+        let _break = if spoken { "| " } else { "" };
+
+        let information_letter = phonetic_alphabet::lookup(self.info_ltr_offset + report_nr);
+        let mut report = if spoken { SPEAK_START_TAG } else { "" }.to_string();
 
-fn get_clouds_report(clouds: &Clouds, spoken: bool) -> Option<String> {
-    let density = match clouds.density {
-        2..=5 => Some("few"),
-        6..=7 => Some("scattered"),
-        8 => Some("broken"),
-        9..=10 => Some("overcast"),
-        _ => None,
-    };
-    if let Some(density) = density {
-        let mut report = String::new();
-        // convert m to ft, round to lowest 500ft increment and shortened (e.g. 17500 -> 175)
-        let base = m_to_ft(f64::from(clouds.base)).round() as u32;
-        let base = (base - (base % 500)) / 100;
         report += &format!(
-            "Cloud conditions {} {}",
-            density,
-            pronounce_number(base, spoken)
+            "This is FARP {} information {}. {}",
+            escape(&self.name, spoken),
+            information_letter,
+            _break
         );
-        match clouds.iprecptns {
-            1 => report += ", rain",
-            2 => report += ", rain and thunderstorm",
-            _ => {}
+
+        if let Some(pad) = &self.pad {
+            report += &format!("Landing pad {}. {}", escape(pad, spoken), _break);
         }
-        Some(report)
-    } else {
-        None
+
+        report += &format!(
+            "{}. {}",
+            wind_report(weather, wind_rounding, spoken),
+            _break
+        );
+
+        report += &format!(
+            "{}. {}",
+            altimeter_report(weather, pressure_rounding, spoken),
+            _break
+        );
+        report += &format!(
+            "{}. {}",
+            qfe_report(weather, pressure_rounding, spoken),
+            _break
+        );
+
+        report += &format!("End information {}.", information_letter);
+
+        if spoken {
+            report += "\n</speak>";
+        }
+
+        Ok(report)
     }
 }
 
-mod phonetic_alphabet {
-    static PHONETIC_ALPHABET: &[&str] = &[
-        "Alpha", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India",
-        "Juliett", "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo",
-        "Sierra", "Tango", "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
-    ];
-
-    pub fn lookup(idx: usize) -> &'static str {
-        PHONETIC_ALPHABET[idx % PHONETIC_ALPHABET.len()]
+impl Custom {
+    /// Reads the message and returns its textual and spoken form. Raw SSML messages are spoken as
+    /// is, but only if they are well-formed, all other messages are escaped.
+    fn read_message(&self) -> Option<(String, String)> {
+        let message = self.message.read()?;
+        if self.ssml {
+            match ssml::validate(&message) {
+                Ok(text) => Some((text, message)),
+                Err(err) => {
+                    error!("Not broadcasting {}: {}", self.unit_name, err);
+                    None
+                }
+            }
+        } else {
+            Some((message.clone(), escape_ssml(&message)))
+        }
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::tts::TextToSpeechProvider;
+impl Default for PatternDirection {
+    fn default() -> Self {
+        PatternDirection::Left
+    }
+}
 
-    #[test]
-    fn test_active_runway() {
-        let airfield = Airfield {
-            name: String::from("Kutaisi"),
-            position: Position::default(),
-            runways: vec![String::from("04"), String::from("22R")],
-            traffic_freq: None,
-            info_ltr_offset: 0,
-        };
+impl FromStr for PatternDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "LEFT" => Ok(PatternDirection::Left),
+            "RIGHT" => Ok(PatternDirection::Right),
+            _ => Err(anyhow!(
+                "invalid traffic pattern direction `{}` (expected LEFT or RIGHT)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PatternDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PatternDirection::Left => "Left",
+            PatternDirection::Right => "Right",
+        })
+    }
+}
+
+impl TextSource {
+    /// Returns the text, or `None` if it is read from a file that is missing or empty.
+    pub fn read(&self) -> Option<String> {
+        match self {
+            TextSource::Inline(text) => Some(text.clone()),
+            TextSource::File(path) => match fs::read_to_string(path) {
+                Ok(text) => {
+                    let text = text.trim();
+                    if text.is_empty() {
+                        warn!("Message file {} is empty", path.display());
+                        None
+                    } else {
+                        Some(text.to_string())
+                    }
+                }
+                Err(err) => {
+                    warn!("Failed to read message file {}: {}", path.display(), err);
+                    None
+                }
+            },
+        }
+    }
+}
+
+/// Prepares a user provided phrase to be embedded as a sentence into a report, by removing its
+/// trailing periods (the report adds one) and escaping it if the report is spoken.
+fn phrase(text: &str, spoken: bool) -> String {
+    escape(text.trim().trim_end_matches('.'), spoken)
+}
+
+/// Escapes user provided text (e.g. names and remarks) if it is embedded into a spoken (SSML)
+/// report, so that characters like `&` don't break the synthesis.
+fn escape(text: &str, spoken: bool) -> String {
+    if spoken {
+        escape_ssml(text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Normalizes runway designations for comparisons, e.g. `4l` to `04L`.
+/// The heading (in degrees) of a runway, e.g. `130` for `13L`.
+fn runway_heading(rwy: &str) -> Option<f64> {
+    let lr: &[_] = &['L', 'R', 'C'];
+    rwy.trim()
+        .trim_end_matches(lr)
+        .parse::<f64>()
+        .ok()
+        .map(|rwy| rwy * 10.0)
+}
+
+/// Splits the wind (direction in degrees it blows from) into its headwind and crosswind
+/// components relative to the given heading (in degrees). The headwind is negative for a tailwind,
+/// the crosswind is positive for wind from the right and negative for wind from the left.
+fn wind_components(wind_dir: f64, wind_speed: f64, heading: f64) -> (f64, f64) {
+    let angle = (wind_dir - heading).to_radians();
+    (wind_speed * angle.cos(), wind_speed * angle.sin())
+}
+
+fn normalize_runway(rwy: &str) -> String {
+    let rwy = rwy.trim().to_uppercase();
+    if rwy.chars().take_while(|c| c.is_ascii_digit()).count() == 1 {
+        format!("0{}", rwy)
+    } else {
+        rwy
+    }
+}
+
+/// The wind sentence of all reports with a wind, e.g. `Wind 220 at 10 knots`.
+fn wind_report(weather: &WeatherInfo, wind_rounding: WindRounding, spoken: bool) -> String {
+    format!(
+        "Wind {} at {} knots",
+        pronounce_number(wind_rounding.format(weather.wind_dir), spoken),
+        pronounce_number((weather.wind_speed * 1.94384).round(), spoken), // to knots
+    )
+}
+
+/// The QNH in hundredths of an inHg, e.g. `ALTIMETER 2997`.
+fn altimeter_report(
+    weather: &WeatherInfo,
+    pressure_rounding: PressureRounding,
+    spoken: bool,
+) -> String {
+    format!(
+        "ALTIMETER {}",
+        // in hundredths of an inHg, since we don't want to speak the DECIMAL here
+        pronounce_number(pressure_rounding.inhg(weather.pressure_qnh), spoken),
+    )
+}
+
+/// The QNH in hPa, e.g. `1015 hectopascal`.
+fn qnh_hpa_report(
+    weather: &WeatherInfo,
+    pressure_rounding: PressureRounding,
+    spoken: bool,
+) -> String {
+    format!(
+        "{} hectopascal",
+        pronounce_number(pressure_rounding.hpa(weather.pressure_qnh), spoken), // to hPA
+    )
+}
+
+/// The QFE in hundredths of an inHg and in hPa, e.g. `QFE 2983 or 1010`.
+fn qfe_report(weather: &WeatherInfo, pressure_rounding: PressureRounding, spoken: bool) -> String {
+    format!(
+        "QFE {} or {}",
+        pronounce_number(pressure_rounding.inhg(weather.pressure_qfe), spoken), // to inHg
+        pronounce_number(pressure_rounding.hpa(weather.pressure_qfe), spoken),  // to hPA
+    )
+}
+
+fn get_visibility_report(visibility: u32, spoken: bool) -> String {
+    let visibility = round(m_to_nm(f64::from(visibility)), 1);
+    format!("Visibility {}", pronounce_number(visibility, spoken))
+}
+
+/// Announces the clouds with their base above the ground, which is the given elevation (in m), as
+/// the clouds' base is above MSL. The spoken report spells out the base in words of the given
+/// English variant.
+fn get_clouds_report(
+    clouds: &Clouds,
+    elevation: f64,
+    english: EnglishVariant,
+    spoken: bool,
+) -> Option<String> {
+    if let Some(density) = cloud_cover(clouds.density) {
+        let mut report = String::new();
+        // convert m AGL to ft and round to lowest 500ft increment
+        let base = (f64::from(clouds.base) - elevation).max(0.0);
+        let base = m_to_ft(base).round() as u32;
+        let base = base - (base % 500);
+        let base = if spoken && english != EnglishVariant::Us {
+            number_to_words(base, english)
+        } else {
+            // shortened, e.g. 17500 -> 175
+            pronounce_number(base / 100, spoken)
+        };
+        report += &format!("Cloud conditions {} {}", density, base);
+        match clouds.iprecptns {
+            1 => report += ", rain",
+            2 => report += ", rain and thunderstorm",
+            _ => {}
+        }
+        Some(report)
+    } else {
+        None
+    }
+}
+
+/// Formats a trend group, using its abbreviation (e.g. `BECMG`) in the textual and its plain form
+/// (e.g. `Becoming`) in the spoken report. The spoken report also expands the METAR groups of the
+/// trend's text into words.
+fn trend_phrase(abbr: &str, plain: &str, text: Option<&str>, spoken: bool) -> String {
+    let group = if spoken { plain } else { abbr };
+    match text {
+        Some(text) if spoken => format!(
+            "{} {}",
+            group,
+            escape(&spoken_metar_groups(&phrase(text, false)), true)
+        ),
+        Some(text) => format!("{} {}", group, phrase(text, false)),
+        None => group.to_string(),
+    }
+}
+
+/// Expands the METAR groups of a text (e.g. `TEMPO 4000 -SHRA BKN015`) into words (e.g.
+/// `visibility 2 DECIMAL 2, light rain showers, broken 1 5`). Everything that isn't a METAR group
+/// is kept as it is.
+fn spoken_metar_groups(text: &str) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut previous_expanded = true;
+    for group in text.split_whitespace() {
+        match spoken_metar_group(group) {
+            Some(words) => {
+                parts.push(words);
+                previous_expanded = true;
+            }
+            None if previous_expanded => {
+                parts.push(group.to_string());
+                previous_expanded = false;
+            }
+            None => {
+                if let Some(last) = parts.last_mut() {
+                    *last += " ";
+                    *last += group;
+                }
+            }
+        }
+    }
+    parts.join(", ")
+}
+
+fn spoken_metar_group(group: &str) -> Option<String> {
+    match group {
+        "NSW" => return Some("no significant weather".to_string()),
+        "NSC" | "NCD" => return Some("no significant clouds".to_string()),
+        "SKC" | "CLR" => return Some("sky clear".to_string()),
+        "CAVOK" => return Some("CAV OK".to_string()),
+        "9999" => return Some("visibility 10 kilometers or more".to_string()),
+        _ => {}
+    }
+
+    for &(prefix, word) in &[("FM", "from"), ("TL", "until"), ("AT", "at")] {
+        if group.len() == 6
+            && group.starts_with(prefix)
+            && group[2..].chars().all(|c| c.is_ascii_digit())
+        {
+            return Some(format!("{} {}", word, pronounce_number(&group[2..], true)));
+        }
+    }
+
+    if let Some((dir, speed)) = metar::parse_wind(group) {
+        let dir = if group.starts_with("VRB") {
+            String::from("variable")
+        } else {
+            pronounce_number(format!("{:03}", dir.round() as u32), true)
+        };
+        let speed = pronounce_number((speed * 1.94384).round(), true); // to knots
+        return Some(format!("wind {} at {} knots", dir, speed));
+    }
+
+    if let Some(visibility) = metar::parse_visibility(group) {
+        let visibility = round(m_to_nm(f64::from(visibility)), 1);
+        return Some(format!("visibility {}", pronounce_number(visibility, true)));
+    }
+
+    spoken_cloud_group(group).or_else(|| spoken_weather_group(group))
+}
+
+/// Expands cloud layers like `BKN015` or `OVC008CB`, with the base in hundreds of feet as in the
+/// rest of the report.
+fn spoken_cloud_group(group: &str) -> Option<String> {
+    let cover = match group.get(..3)? {
+        "FEW" => "few",
+        "SCT" => "scattered",
+        "BKN" => "broken",
+        "OVC" => "overcast",
+        _ => {
+            if !group.starts_with("VV") {
+                return None;
+            }
+            let base = group.get(2..5)?;
+            let base = u32::from_str(base).ok()?;
+            return Some(format!(
+                "vertical visibility {}",
+                pronounce_number(base, true)
+            ));
+        }
+    };
+    let base = u32::from_str(group.get(3..6)?).ok()?;
+    let kind = match group.get(6..)? {
+        "" => "",
+        "CB" => " cumulonimbus",
+        "TCU" => " towering cumulus",
+        _ => return None,
+    };
+    Some(format!(
+        "{} {}{}",
+        cover,
+        pronounce_number(base, true),
+        kind
+    ))
+}
+
+/// Expands present weather groups like `-SHRA`, `TSRA` or `VCFG`.
+fn spoken_weather_group(group: &str) -> Option<String> {
+    let (intensity, group) = if group.starts_with('-') {
+        (Some("light"), &group[1..])
+    } else if group.starts_with('+') {
+        (Some("heavy"), &group[1..])
+    } else {
+        (None, group)
+    };
+    let (vicinity, group) = if group.starts_with("VC") {
+        (true, &group[2..])
+    } else {
+        (false, group)
+    };
+
+    let descriptor = group.get(..2).and_then(|descriptor| match descriptor {
+        "MI" => Some("shallow"),
+        "BC" => Some("patches of"),
+        "PR" => Some("partial"),
+        "DR" => Some("low drifting"),
+        "BL" => Some("blowing"),
+        "FZ" => Some("freezing"),
+        "SH" => Some("showers"),
+        "TS" => Some("thunderstorm"),
+        _ => None,
+    });
+    let group = if descriptor.is_some() {
+        &group[2..]
+    } else {
+        group
+    };
+
+    if group.len() % 2 != 0 || !group.is_ascii() {
+        return None;
+    }
+    let mut phenomena = Vec::new();
+    for ix in (0..group.len()).step_by(2) {
+        phenomena.push(match &group[ix..ix + 2] {
+            "DZ" => "drizzle",
+            "RA" => "rain",
+            "SN" => "snow",
+            "SG" => "snow grains",
+            "IC" => "ice crystals",
+            "PL" => "ice pellets",
+            "GR" => "hail",
+            "GS" => "small hail",
+            "UP" => "unknown precipitation",
+            "BR" => "mist",
+            "FG" => "fog",
+            "FU" => "smoke",
+            "VA" => "volcanic ash",
+            "DU" => "dust",
+            "SA" => "sand",
+            "HZ" => "haze",
+            "PO" => "dust whirls",
+            "SQ" => "squalls",
+            "FC" => "funnel cloud",
+            "SS" => "sandstorm",
+            "DS" => "duststorm",
+            _ => return None,
+        });
+    }
+    let phenomena = phenomena.join(" and ");
+
+    let mut words = match descriptor {
+        Some(descriptor) if phenomena.is_empty() => descriptor.to_string(),
+        Some("thunderstorm") => format!("thunderstorm with {}", phenomena),
+        Some("showers") => format!("{} showers", phenomena),
+        Some(descriptor) => format!("{} {}", descriptor, phenomena),
+        None if phenomena.is_empty() => return None,
+        None => phenomena,
+    };
+    if let Some(intensity) = intensity {
+        words = format!("{} {}", intensity, words);
+    }
+    if vicinity {
+        words += " in the vicinity";
+    }
+    Some(words)
+}
+
+/// Whether the weather changed enough between two reports that the trend must not be NOSIG. The
+/// thresholds loosely follow the ones for issuing a special report (SPECI).
+fn is_significant_change(previous: &WeatherInfo, current: &WeatherInfo) -> bool {
+    // 10 kt
+    const WIND_SPEED_THRESHOLD: f64 = 5.14;
+    const WIND_DIR_THRESHOLD: f64 = 60.0;
+    // in m
+    const VISIBILITY_THRESHOLD: i64 = 1_000;
+    // 500 ft
+    const CLOUD_BASE_THRESHOLD: i64 = 152;
+
+    if (current.wind_speed - previous.wind_speed).abs() >= WIND_SPEED_THRESHOLD {
+        return true;
+    }
+
+    let wind_dir_change = (current.wind_dir - previous.wind_dir).rem_euclid(360.0);
+    let wind_dir_change = wind_dir_change.min(360.0 - wind_dir_change);
+    if wind_dir_change >= WIND_DIR_THRESHOLD
+        && current.wind_speed.max(previous.wind_speed) >= WIND_SPEED_THRESHOLD
+    {
+        return true;
+    }
+
+    // no visibility means unrestricted visibility
+    let visibility = |weather: &WeatherInfo| i64::from(weather.visibility.unwrap_or(10_000));
+    if (visibility(current) - visibility(previous)).abs() >= VISIBILITY_THRESHOLD {
+        return true;
+    }
+
+    let clouds = |weather: &WeatherInfo| {
+        weather.clouds.as_ref().and_then(|clouds| {
+            cloud_cover(clouds.density).map(|cover| (cover, i64::from(clouds.base)))
+        })
+    };
+    match (clouds(previous), clouds(current)) {
+        (Some((previous_cover, previous_base)), Some((current_cover, current_base))) => {
+            previous_cover != current_cover
+                || (current_base - previous_base).abs() >= CLOUD_BASE_THRESHOLD
+        }
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+/// The reported cloud cover of a cloud density, or `None` if the clouds aren't reported.
+fn cloud_cover(density: u32) -> Option<&'static str> {
+    match density {
+        2..=5 => Some("few"),
+        6..=7 => Some("scattered"),
+        8 => Some("broken"),
+        9..=10 => Some("overcast"),
+        _ => None,
+    }
+}
+
+mod phonetic_alphabet {
+    static PHONETIC_ALPHABET: &[&str] = &[
+        "Alpha", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India",
+        "Juliett", "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo",
+        "Sierra", "Tango", "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+    ];
+
+    pub fn lookup(idx: usize) -> &'static str {
+        PHONETIC_ALPHABET[idx % PHONETIC_ALPHABET.len()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "static-weather"))]
+    use crate::rpc::mock::MockWeatherSource;
+    use crate::tts::TextToSpeechProvider;
+
+    #[test]
+    fn test_active_runway() {
+        let airfield = Airfield {
+            name: String::from("Kutaisi"),
+            runways: vec![String::from("04"), String::from("22R")],
+            ..Default::default()
+        };
 
         assert_eq!(airfield.get_active_runway(0.0), Some("04"));
         assert_eq!(airfield.get_active_runway(30.0), Some("04"));
@@ -582,46 +2282,994 @@ mod test {
         assert_eq!(airfield.get_active_runway(131.0), Some("22"));
     }
 
-    #[tokio::test]
-    async fn test_atis_report() {
-        let station = Station {
-            name: String::from("Kutaisi"),
-            freq: 251_000_000,
-            tts: TextToSpeechProvider::default(),
-            transmitter: Transmitter::Airfield(Airfield {
-                name: String::from("Kutaisi"),
-                position: Position::default(),
-                runways: vec![String::from("04"), String::from("22")],
-                traffic_freq: Some(249_500_000),
-                info_ltr_offset: 0,
+    #[cfg(not(feature = "static-weather"))]
+    #[tokio::test]
+    async fn test_atis_report_from_weather_source() {
+        let weather = MockWeatherSource {
+            weather: WeatherInfo {
+                clouds: Some(Clouds {
+                    base: 1500,
+                    density: 6,
+                    thickness: 400,
+                    iprecptns: 0,
+                }),
+                visibility: Some(5_000),
+                wind_speed: 5.0,
+                wind_dir: 220.0,
+                temperature: 12.4,
+                pressure_qnh: 101_500.0,
+                pressure_qfe: 101_000.0,
+                position: Position::default(),
+            },
+            ..Default::default()
+        };
+        let station = Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                position: Position {
+                    x: -284_860.0,
+                    y: 683_839.0,
+                    alt: 45.0,
+                },
+                runways: vec![String::from("04"), String::from("22")],
+                traffic_freq: Some(249_500_000),
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: Some(Arc::new(weather)),
+        };
+
+        let report = station.generate_report(1).await.unwrap().unwrap();
+        assert_eq!(report.textual, "This is Kutaisi information Bravo. Runway in use is 22. Wind 220 at 10 knots. Visibility 2.7. Cloud conditions scattered 45. Temperature 12.4 celcius. ALTIMETER 2997. Contact traffic on 249.500. REMARKS. 1015 hectopascal. QFE 2983 or 1010. End information Bravo.");
+        assert_eq!(
+            report.position,
+            LatLngPosition {
+                lat: -284_860.0,
+                lng: 683_839.0,
+                alt: 45.0,
+            }
+        );
+    }
+
+    #[cfg(not(feature = "static-weather"))]
+    #[tokio::test]
+    async fn test_render_report_without_mission() {
+        let airfield = Airfield {
+            name: String::from("Batumi"),
+            position: Position {
+                x: -355_810.0,
+                y: 617_386.0,
+                alt: 10.0,
+            },
+            runways: vec![String::from("13"), String::from("31")],
+            trend: Some(Trend::Observed(PreviousObservation::default())),
+            ..Default::default()
+        };
+        let weather = MockWeatherSource {
+            weather: WeatherInfo {
+                clouds: None,
+                visibility: None,
+                wind_speed: 4.0,
+                wind_dir: 300.0,
+                temperature: 18.0,
+                pressure_qnh: 101_500.0,
+                pressure_qfe: 101_400.0,
+                position: Position::default(),
+            },
+            ..Default::default()
+        };
+
+        let report = airfield
+            .render_report(
+                &weather,
+                2,
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.textual, "This is Batumi information Charlie. Runway in use is 31. Wind 300 at 8 knots. Temperature 18 celcius. ALTIMETER 2997. REMARKS. 1015 hectopascal. QFE 2994 or 1014. End information Charlie.");
+        assert!(report.spoken.starts_with(SPEAK_START_TAG));
+        assert_eq!(
+            report.position,
+            LatLngPosition {
+                lat: -355_810.0,
+                lng: 617_386.0,
+                alt: 10.0,
+            }
+        );
+        assert_eq!(
+            *weather.queried.lock().unwrap(),
+            vec![airfield.position.clone()]
+        );
+
+        // rendering doesn't record the weather for the observed trend
+        match &airfield.trend {
+            Some(Trend::Observed(previous)) => assert!(previous.0.lock().unwrap().is_none()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[cfg(not(feature = "static-weather"))]
+    #[tokio::test]
+    async fn test_weather_at_field_elevation() {
+        let mut airfield = Airfield {
+            name: String::from("Mountain"),
+            position: Position {
+                x: -284_860.0,
+                y: 683_839.0,
+                alt: 1_500.0,
+            },
+            runways: vec![String::from("04"), String::from("22")],
+            ..Default::default()
+        };
+
+        let weather = MockWeatherSource::default();
+        airfield.get_weather(&weather).await.unwrap();
+        assert_eq!(
+            *weather.queried.lock().unwrap(),
+            vec![airfield.position.clone()]
+        );
+
+        // the pattern altitude is relative to the field elevation
+        airfield.wind_altitude = WindAltitude::Pattern;
+        let weather = MockWeatherSource::default();
+        airfield.get_weather(&weather).await.unwrap();
+        let queried = weather.queried.lock().unwrap();
+        assert_eq!(queried.len(), 2);
+        assert_eq!(queried[0], airfield.position);
+        assert_eq!((queried[1].x, queried[1].y), (-284_860.0, 683_839.0));
+        assert!((queried[1].alt - 1_804.8).abs() < 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_atis_report() {
+        let station = Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                runways: vec![String::from("04"), String::from("22")],
+                traffic_freq: Some(249_500_000),
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(26).await.unwrap().unwrap();
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is Kutaisi information Alpha. | Runway in use is ZERO 4. | Wind ZERO 1 ZERO at 5 knots. | Temperature 2 2 celcius. | ALTIMETER 2 NINER NINER 7. | Contact traffic on 2 4 NINER DECIMAL 5 ZERO ZERO. | REMARKS. | 1 ZERO 1 5 hectopascal. | QFE 2 NINER NINER 7 or 1 ZERO 1 5. | End information Alpha.\n</speak>");
+        assert_eq!(report.textual, "This is Kutaisi information Alpha. Runway in use is 04. Wind 010 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. Contact traffic on 249.500. REMARKS. 1015 hectopascal. QFE 2997 or 1015. End information Alpha.");
+    }
+
+    #[tokio::test]
+    async fn test_report_with_info_letter_offset() {
+        let station = Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                runways: vec![String::from("04"), String::from("22")],
+                traffic_freq: Some(249_500_000),
+                info_ltr_offset: 15, // Should be "Papa"
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(26).await.unwrap().unwrap();
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is Kutaisi information Papa. | Runway in use is ZERO 4. | Wind ZERO 1 ZERO at 5 knots. | Temperature 2 2 celcius. | ALTIMETER 2 NINER NINER 7. | Contact traffic on 2 4 NINER DECIMAL 5 ZERO ZERO. | REMARKS. | 1 ZERO 1 5 hectopascal. | QFE 2 NINER NINER 7 or 1 ZERO 1 5. | End information Papa.\n</speak>");
+        assert_eq!(report.textual, "This is Kutaisi information Papa. Runway in use is 04. Wind 010 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. Contact traffic on 249.500. REMARKS. 1015 hectopascal. QFE 2997 or 1015. End information Papa.");
+    }
+
+    #[tokio::test]
+    async fn test_report_with_remarks() {
+        let station = Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                runways: vec![String::from("04"), String::from("22")],
+                remarks: Some(TextSource::Inline("Runway 13 closed.".to_string())),
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(26).await.unwrap().unwrap();
+        assert_eq!(report.textual, "This is Kutaisi information Alpha. Runway in use is 04. Wind 010 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. REMARKS. 1015 hectopascal. QFE 2997 or 1015. Remarks, Runway 13 closed. End information Alpha.");
+        assert!(report
+            .spoken
+            .ends_with("| Remarks, Runway 13 closed. | End information Alpha.\n</speak>"));
+    }
+
+    fn trend_station(trend: Trend) -> Station {
+        Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                runways: vec![String::from("04"), String::from("22")],
+                trend: Some(trend),
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_with_observed_trend() {
+        let station = trend_station(Trend::Observed(PreviousObservation::default()));
+
+        // there is no previous observation to compare the first report against
+        let report = station.generate_report(0).await.unwrap().unwrap();
+        assert!(!report.textual.contains("NOSIG"));
+
+        // the weather is steady
+        let report = station.generate_report(1).await.unwrap().unwrap();
+        assert_eq!(report.textual, "This is Kutaisi information Bravo. Runway in use is 04. Wind 010 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. REMARKS. 1015 hectopascal. QFE 2997 or 1015. NOSIG. End information Bravo.");
+        assert!(report
+            .spoken
+            .ends_with("| No significant change. | End information Bravo.\n</speak>"));
+    }
+
+    #[tokio::test]
+    async fn test_report_with_scripted_trend() {
+        let station = trend_station(Trend::Becoming("BKN015".to_string()));
+
+        let report = station.generate_report(0).await.unwrap().unwrap();
+        assert!(report
+            .textual
+            .ends_with("QFE 2997 or 1015. BECMG BKN015. End information Alpha."));
+        assert!(report
+            .spoken
+            .ends_with("| Becoming broken 1 5. | End information Alpha.\n</speak>"));
+    }
+
+    #[test]
+    fn test_spoken_metar_groups() {
+        assert_eq!(
+            spoken_metar_groups("FM1200 24015KT 4000 -SHRA BKN015CB"),
+            "from 1 2 ZERO ZERO, wind 2 4 ZERO at 1 5 knots, visibility 2 DECIMAL 2, light rain \
+             showers, broken 1 5 cumulonimbus"
+        );
+        assert_eq!(
+            spoken_metar_groups("TSRA VCFG +SN OVC008"),
+            "thunderstorm with rain, fog in the vicinity, heavy snow, overcast 8"
+        );
+        assert_eq!(
+            spoken_metar_groups("VRB03KT 9999 NSW"),
+            "wind variable at 3 knots, visibility 10 kilometers or more, no significant weather"
+        );
+        // everything else is kept as it is
+        assert_eq!(
+            spoken_metar_groups("RA in the afternoon"),
+            "rain, in the afternoon"
+        );
+    }
+
+    #[test]
+    fn test_significant_weather_change() {
+        let weather = WeatherInfo {
+            clouds: Some(Clouds {
+                base: 1_500,
+                density: 6,
+                thickness: 200,
+                iprecptns: 0,
+            }),
+            visibility: Some(8_000),
+            wind_speed: 6.0,
+            wind_dir: 220.0,
+            ..Default::default()
+        };
+        assert!(!is_significant_change(&weather, &weather));
+
+        let changed = |change: fn(&mut WeatherInfo)| {
+            let mut current = weather.clone();
+            change(&mut current);
+            is_significant_change(&weather, &current)
+        };
+        assert!(!changed(|w| w.wind_dir = 250.0));
+        assert!(changed(|w| w.wind_dir = 290.0));
+        assert!(changed(|w| w.wind_speed = 12.0));
+        assert!(!changed(|w| w.visibility = Some(7_500)));
+        assert!(changed(|w| w.visibility = None));
+        assert!(!changed(|w| w.clouds.as_mut().unwrap().density = 7));
+        assert!(changed(|w| w.clouds.as_mut().unwrap().density = 8));
+        assert!(changed(|w| w.clouds.as_mut().unwrap().base = 1_200));
+        assert!(changed(|w| w.clouds = None));
+    }
+
+    #[tokio::test]
+    async fn test_report_with_intro_and_outro() {
+        let station = Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                runways: vec![String::from("04"), String::from("22")],
+                intro: Some(TextSource::Inline(
+                    "Welcome to Kutaisi, home of the 123rd".to_string(),
+                )),
+                outro: Some(TextSource::Inline("Fly safe & check six.".to_string())),
+                simple_report: true,
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(26).await.unwrap().unwrap();
+        assert_eq!(report.textual, "Welcome to Kutaisi, home of the 123rd. This is Kutaisi information Alpha. Runway in use is 04. ALTIMETER 2997. End information Alpha. Fly safe & check six.");
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nWelcome to Kutaisi, home of the 123rd. | This is Kutaisi information Alpha. | Runway in use is ZERO 4. | ALTIMETER 2 NINER NINER 7. | End information Alpha. | Fly safe &amp; check six.\n</speak>");
+    }
+
+    #[tokio::test]
+    async fn test_report_with_closing() {
+        let station = Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                runways: vec![String::from("04"), String::from("22")],
+                outro: Some(TextSource::Inline("Fly safe".to_string())),
+                simple_report: true,
+                closing: true,
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(1).await.unwrap().unwrap();
+        assert_eq!(report.textual, "This is Kutaisi information Bravo. Runway in use is 04. ALTIMETER 2997. End information Bravo. Fly safe. Kutaisi information Bravo, out.");
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is Kutaisi information Bravo. | Runway in use is ZERO 4. | ALTIMETER 2 NINER NINER 7. | End information Bravo. | Fly safe. | Kutaisi information Bravo, out.\n</speak>");
+
+        // the closing follows the information letter of each report
+        let report = station.generate_report(2).await.unwrap().unwrap();
+        assert!(report
+            .textual
+            .ends_with("End information Charlie. Fly safe. Kutaisi information Charlie, out."));
+    }
+
+    #[tokio::test]
+    async fn test_report_with_runway_override() {
+        let station = Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                runways: vec![String::from("04"), String::from("22")],
+                runway_override: Some(RunwayOverride {
+                    landing: Some(String::from("22")),
+                    departure: Some(String::from("04")),
+                    announce_available: false,
+                }),
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(26).await.unwrap().unwrap();
+        assert_eq!(report.textual, "This is Kutaisi information Alpha. Landing runway 22, departing runway 04. Wind 010 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. REMARKS. 1015 hectopascal. QFE 2997 or 1015. End information Alpha.");
+    }
+
+    #[test]
+    fn test_runway_report_with_wind_derived_runways() {
+        let airfield = |landing: Option<&str>, departure: Option<&str>| Airfield {
+            name: String::from("Kutaisi"),
+            runways: vec![String::from("04"), String::from("22")],
+            runway_override: Some(RunwayOverride {
+                landing: landing.map(String::from),
+                departure: departure.map(String::from),
+                announce_available: false,
+            }),
+            ..Default::default()
+        };
+
+        // the wind from 006 makes 04 the runway in use; wind-derived landing runway, configured
+        // departing runway
+        let split = airfield(None, Some("22"));
+        assert_eq!(
+            split.runway_report(6.0, false).unwrap(),
+            "Landing runway 04, departing runway 22"
+        );
+        assert_eq!(
+            split.traffic_pattern_report(6.0, false, true).unwrap(),
+            "Left traffic runway 04"
+        );
+
+        // configured landing runway, wind-derived departing runway
+        assert_eq!(
+            airfield(Some("22"), None)
+                .runway_report(6.0, false)
+                .unwrap(),
+            "Landing runway 22, departing runway 04"
+        );
+
+        // the configured runway is the same as the wind-derived one
+        let same = airfield(None, Some("4"));
+        assert_eq!(
+            same.runway_report(6.0, false).unwrap(),
+            "Runway in use is 04"
+        );
+        assert_eq!(
+            same.traffic_pattern_report(6.0, false, true).unwrap(),
+            "Left traffic runway 04"
+        );
+    }
+
+    #[test]
+    fn test_runway_report_with_available_runways() {
+        let airfield = |runways: &[&str], announce_available: bool| Airfield {
+            name: String::from("Batumi"),
+            runways: runways.iter().map(|rwy| rwy.to_string()).collect(),
+            runway_override: Some(RunwayOverride {
+                landing: Some(String::from("13")),
+                departure: Some(String::from("13")),
+                announce_available,
+            }),
+            ..Default::default()
+        };
+
+        // the active runway is in use regardless of the wind, the others are available
+        let active = airfield(&["13", "31"], true);
+        assert_eq!(
+            active.runway_report(310.0, false).unwrap(),
+            "Runway in use is 13, runway 31 available"
+        );
+        assert_eq!(
+            active.runway_report(310.0, true).unwrap(),
+            "Runway in use is 1 3, runway 3 1 available"
+        );
+        assert_eq!(
+            airfield(&["04", "22", "13", "31", "22"], true)
+                .runway_report(310.0, false)
+                .unwrap(),
+            "Runway in use is 13, runways 04, 22 and 31 available"
+        );
+
+        // a pinned runway doesn't announce the available ones
+        assert_eq!(
+            airfield(&["13", "31"], false)
+                .runway_report(310.0, false)
+                .unwrap(),
+            "Runway in use is 13"
+        );
+
+        // nothing is announced if there are no other runways
+        assert_eq!(
+            airfield(&["13"], true).runway_report(310.0, false).unwrap(),
+            "Runway in use is 13"
+        );
+    }
+
+    #[test]
+    fn test_report_traffic_frequency() {
+        let airfield = Airfield {
+            name: String::from("Batumi"),
+            runways: vec![String::from("13"), String::from("31")],
+            traffic_freq: Some(255_000_000),
+            ..Default::default()
+        };
+        let weather = WeatherInfo {
+            clouds: None,
+            visibility: None,
+            wind_speed: 4.0,
+            wind_dir: 140.0,
+            temperature: 20.9,
+            pressure_qnh: 99_300.0,
+            pressure_qfe: 99_200.0,
+            position: Position::default(),
+        };
+
+        let report = airfield
+            .generate_report(
+                0,
+                &weather,
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert!(report.contains("ALTIMETER 2932. Contact traffic on 255.000. REMARKS."));
+        let report = airfield
+            .generate_report(
+                0,
+                &weather,
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                true,
+            )
+            .unwrap();
+        assert!(report.contains("Contact traffic on 2 5 5 DECIMAL ZERO ZERO ZERO."));
+
+        let airfield = Airfield {
+            traffic_freq: None,
+            ..airfield
+        };
+        let report = airfield
+            .generate_report(
+                0,
+                &weather,
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert!(report.contains("ALTIMETER 2932. REMARKS."));
+        assert!(!report.contains("traffic"));
+    }
+
+    #[test]
+    fn test_traffic_advisory() {
+        let airfield = Airfield {
+            name: String::from("Batumi"),
+            runways: vec![String::from("13"), String::from("31")],
+            traffic_freq: Some(255_000_000),
+            ..Default::default()
+        };
+        let weather = WeatherInfo {
+            clouds: None,
+            visibility: None,
+            wind_speed: 4.0,
+            wind_dir: 140.0,
+            temperature: 20.9,
+            pressure_qnh: 99_300.0,
+            pressure_qfe: 99_200.0,
+            position: Position::default(),
+        };
+
+        assert_eq!(
+            airfield.generate_traffic_advisory(&weather, false).unwrap(),
+            "Batumi traffic advisory. Runway in use is 13. Left traffic runway 13. End of advisory."
+        );
+        assert_eq!(
+            airfield.generate_traffic_advisory(&weather, true).unwrap(),
+            r#"<speak version="1.0" xml:lang="en-US">
+Batumi traffic advisory. | Runway in use is 1 3. | Left traffic runway 1 3. | End of advisory.
+</speak>"#
+        );
+    }
+
+    #[test]
+    fn test_traffic_pattern_report() {
+        let airfield = Airfield {
+            name: String::from("Kutaisi"),
+            runways: vec![String::from("07"), String::from("25R")],
+            traffic_patterns: vec![TrafficPattern {
+                runway: String::from("25R"),
+                direction: PatternDirection::Right,
+            }],
+            ..Default::default()
+        };
+        let weather = |wind_dir| WeatherInfo {
+            clouds: None,
+            visibility: None,
+            wind_speed: 4.0,
+            wind_dir,
+            temperature: 20.9,
+            pressure_qnh: 99_300.0,
+            pressure_qfe: 99_200.0,
+            position: Position::default(),
+        };
+
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(250.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert!(report.contains("Runway in use is 25. Right traffic runway 25. Wind"));
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(250.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                true,
+            )
+            .unwrap();
+        assert!(report.contains("Right traffic runway 2 5. |"));
+
+        // the default left traffic isn't announced
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(70.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert!(report.contains("Runway in use is 07. Wind"));
+        assert_eq!(
+            airfield.traffic_pattern_report(70.0, false, true).unwrap(),
+            "Left traffic runway 07"
+        );
+
+        // an explicitly configured left traffic is announced
+        let mut configured = airfield.clone();
+        configured.traffic_patterns.push(TrafficPattern {
+            runway: String::from("07"),
+            direction: PatternDirection::Left,
+        });
+        assert_eq!(
+            configured
+                .traffic_pattern_report(70.0, false, false)
+                .unwrap(),
+            "Left traffic runway 07"
+        );
+
+        assert_eq!(airfield.pattern_direction("25R"), PatternDirection::Right);
+        assert_eq!(airfield.pattern_direction("25"), PatternDirection::Right);
+        assert_eq!(airfield.pattern_direction("7"), PatternDirection::Left);
+    }
+
+    #[test]
+    fn test_parse_pattern_direction() {
+        assert_eq!(
+            PatternDirection::from_str("LEFT").unwrap(),
+            PatternDirection::Left
+        );
+        assert_eq!(
+            PatternDirection::from_str("right").unwrap(),
+            PatternDirection::Right
+        );
+        assert!(PatternDirection::from_str("UP").is_err());
+        assert!(PatternDirection::from_str("L").is_err());
+    }
+
+    #[test]
+    fn test_wind_components() {
+        let components = |wind_dir, heading| {
+            let (headwind, crosswind) = wind_components(wind_dir, 10.0, heading);
+            (round(headwind, 1), round(crosswind, 1))
+        };
+        assert_eq!(components(130.0, 130.0), (10.0, 0.0));
+        assert_eq!(components(180.0, 130.0), (6.4, 7.7));
+        assert_eq!(components(40.0, 130.0), (0.0, -10.0));
+        assert_eq!(components(310.0, 130.0), (-10.0, 0.0));
+        assert_eq!(components(360.0, 40.0), (7.7, -6.4));
+
+        assert_eq!(runway_heading("13"), Some(130.0));
+        assert_eq!(runway_heading("04L"), Some(40.0));
+        assert_eq!(runway_heading("X"), None);
+    }
+
+    #[test]
+    fn test_elevation_report() {
+        // Kutaisi's elevation is 45m (148ft)
+        let mut airfield = Airfield {
+            name: String::from("Kutaisi"),
+            position: Position {
+                x: -284_889.0,
+                y: 683_853.0,
+                alt: 45.0,
+            },
+            runways: vec![String::from("07"), String::from("25")],
+            elevation: Some(ElevationUnit::Feet),
+            ..Default::default()
+        };
+        let weather = WeatherInfo {
+            clouds: None,
+            visibility: None,
+            wind_speed: 5.0,
+            wind_dir: 250.0,
+            temperature: 22.0,
+            pressure_qnh: 101_500.0,
+            pressure_qfe: 101_000.0,
+            position: Position::default(),
+        };
+        let report = |airfield: &Airfield, spoken| {
+            airfield
+                .generate_report(
+                    0,
+                    &weather,
+                    WindRounding::default(),
+                    PressureRounding::default(),
+                    EnglishVariant::default(),
+                    spoken,
+                )
+                .unwrap()
+        };
+
+        assert!(report(&airfield, false)
+            .contains("QFE 2983 or 1010. Field elevation 148 feet. End information Alpha."));
+        assert!(report(&airfield, true).contains("| Field elevation 1 4 8 feet. |"));
+
+        airfield.elevation = Some(ElevationUnit::Meters);
+        assert!(report(&airfield, false).contains("Field elevation 45 meters."));
+
+        // the elevation isn't announced by default
+        airfield.elevation = None;
+        assert!(!report(&airfield, false).contains("elevation"));
+
+        // nor in the simple report
+        airfield.elevation = Some(ElevationUnit::Feet);
+        airfield.simple_report = true;
+        assert!(!report(&airfield, false).contains("elevation"));
+    }
+
+    #[test]
+    fn test_wind_components_report() {
+        let mut airfield = Airfield {
+            name: String::from("Batumi"),
+            runways: vec![String::from("13"), String::from("31")],
+            wind_components: true,
+            ..Default::default()
+        };
+        let weather = |wind_dir| WeatherInfo {
+            clouds: None,
+            visibility: None,
+            wind_speed: 5.144, // 10 knots
+            wind_dir,
+            temperature: 20.9,
+            pressure_qnh: 99_300.0,
+            pressure_qfe: 99_200.0,
+            position: Position::default(),
+        };
+
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(180.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert!(report.contains("Wind 180 at 10 knots. Runway 13, headwind 6 knots, crosswind 8 knots from the right. Temperature"));
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(180.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                true,
+            )
+            .unwrap();
+        assert!(
+            report.contains("Runway 1 3, headwind 6 knots, crosswind 8 knots from the right. |")
+        );
+
+        // the components are reported for pinned runways, too
+        airfield.runway_override = Some(RunwayOverride {
+            landing: Some(String::from("31")),
+            departure: None,
+            announce_available: false,
+        });
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(40.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        // components that round to zero are omitted
+        assert!(report.contains(
+            "Wind 040 at 10 knots. Runway 31, crosswind 10 knots from the right. Temperature"
+        ));
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(130.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert!(report.contains("Wind 130 at 10 knots. Runway 31, tailwind 10 knots. Temperature"));
+
+        // the (true) wind direction is compared with the magnetic runway heading
+        airfield.runway_override = None;
+        airfield.magnetic_declination = 6.0;
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(186.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert!(report.contains("Runway 13, headwind 6 knots, crosswind 8 knots from the right."));
+
+        airfield.wind_components = false;
+        let report = airfield
+            .generate_report(
+                0,
+                &weather(180.0),
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert!(!report.contains("headwind"));
+    }
+
+    #[test]
+    fn test_simple_report() {
+        let airfield = Airfield {
+            name: String::from("Batumi"),
+            runways: vec![String::from("13"), String::from("31")],
+            traffic_freq: Some(249_500_000),
+            simple_report: true,
+            ..Default::default()
+        };
+        let weather = WeatherInfo {
+            clouds: Some(Clouds {
+                base: 1500,
+                density: 6,
+                thickness: 400,
+                iprecptns: 1,
             }),
-            rpc: None,
+            visibility: Some(5_000),
+            wind_speed: 4.0,
+            wind_dir: 140.0,
+            temperature: 20.9,
+            pressure_qnh: 99_300.0,
+            pressure_qfe: 99_200.0,
+            position: Position::default(),
         };
 
-        let report = station.generate_report(26).await.unwrap().unwrap();
-        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is Kutaisi information Alpha. | Runway in use is ZERO 4. | Wind ZERO ZERO 6 at 5 knots. | Temperature 2 2 celcius. | ALTIMETER 2 NINER NINER 7. | Traffic frequency 2 4 NINER DECIMAL 5. | REMARKS. | 1 ZERO 1 5 hectopascal. | QFE 2 NINER NINER 7 or 1 ZERO 1 5. | End information Alpha.\n</speak>");
-        assert_eq!(report.textual, "This is Kutaisi information Alpha. Runway in use is 04. Wind 006 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. Traffic frequency 249.5. REMARKS. 1015 hectopascal. QFE 2997 or 1015. End information Alpha.");
+        let report = airfield
+            .generate_report(
+                0,
+                &weather,
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            report,
+            "This is Batumi information Alpha. Runway in use is 13. ALTIMETER 2932. End information Alpha."
+        );
+        for omitted in &["Wind", "Visibility", "Cloud", "Temperature", "QFE"] {
+            assert!(!report.contains(omitted), "report contains {}", omitted);
+        }
+
+        let full_report = Airfield {
+            simple_report: false,
+            trend: None,
+            wind_components: false,
+            ..airfield
+        }
+        .generate_report(
+            0,
+            &weather,
+            WindRounding::default(),
+            PressureRounding::default(),
+            EnglishVariant::default(),
+            false,
+        )
+        .unwrap();
+        assert!(full_report.contains("Temperature 20.9 celcius"));
+        assert!(full_report.contains("Visibility"));
     }
 
-    #[tokio::test]
-    async fn test_report_with_info_letter_offset() {
-        let station = Station {
+    #[test]
+    fn test_validate_runway_override() {
+        let airfield = Airfield {
             name: String::from("Kutaisi"),
-            freq: 251_000_000,
-            tts: TextToSpeechProvider::default(),
-            transmitter: Transmitter::Airfield(Airfield {
-                name: String::from("Kutaisi"),
-                position: Position::default(),
-                runways: vec![String::from("04"), String::from("22")],
-                traffic_freq: Some(249_500_000),
-                info_ltr_offset: 15, // Should be "Papa"
-            }),
-            rpc: None,
+            runways: vec![String::from("04"), String::from("22R")],
+            ..Default::default()
         };
 
-        let report = station.generate_report(26).await.unwrap().unwrap();
-        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is Kutaisi information Papa. | Runway in use is ZERO 4. | Wind ZERO ZERO 6 at 5 knots. | Temperature 2 2 celcius. | ALTIMETER 2 NINER NINER 7. | Traffic frequency 2 4 NINER DECIMAL 5. | REMARKS. | 1 ZERO 1 5 hectopascal. | QFE 2 NINER NINER 7 or 1 ZERO 1 5. | End information Papa.\n</speak>");
-        assert_eq!(report.textual, "This is Kutaisi information Papa. Runway in use is 04. Wind 006 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. Traffic frequency 249.5. REMARKS. 1015 hectopascal. QFE 2997 or 1015. End information Papa.");
+        let rwy = |landing: Option<&str>, departure: Option<&str>| RunwayOverride {
+            landing: landing.map(String::from),
+            departure: departure.map(String::from),
+            announce_available: false,
+        };
+        assert!(airfield
+            .validate_runway_override(&rwy(Some("04"), Some("04")))
+            .is_ok());
+        assert!(airfield
+            .validate_runway_override(&rwy(Some("4"), Some("22R")))
+            .is_ok());
+        assert!(airfield
+            .validate_runway_override(&rwy(None, Some("22R")))
+            .is_ok());
+        assert!(airfield
+            .validate_runway_override(&rwy(Some("22"), Some("22")))
+            .is_err());
+        assert!(airfield
+            .validate_runway_override(&rwy(Some("13"), Some("04")))
+            .is_err());
+        assert!(airfield
+            .validate_runway_override(&rwy(None, Some("13")))
+            .is_err());
+
+        // an active runway has to exist, too
+        let active = |rwy: &str| RunwayOverride {
+            landing: Some(rwy.to_string()),
+            departure: Some(rwy.to_string()),
+            announce_available: true,
+        };
+        assert!(airfield.validate_runway_override(&active("22R")).is_ok());
+        assert!(airfield.validate_runway_override(&active("31")).is_err());
     }
 
     #[test]
@@ -649,7 +3297,7 @@ mod test {
                 thickness: 0,
                 iprecptns,
             };
-            get_clouds_report(&clouds, true)
+            get_clouds_report(&clouds, 0.0, EnglishVariant::default(), true)
         }
 
         assert_eq!(create_clouds_report(8400, 1, 0), None);
@@ -671,23 +3319,161 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_clouds_report_english_variant() {
+        let clouds = Clouds {
+            base: 8400,
+            density: 2,
+            thickness: 0,
+            iprecptns: 0,
+        };
+        assert_eq!(
+            get_clouds_report(&clouds, 0.0, EnglishVariant::Gb, true),
+            Some("Cloud conditions few twenty-seven thousand five hundred".to_string())
+        );
+        // the textual report keeps the shortened base
+        assert_eq!(
+            get_clouds_report(&clouds, 0.0, EnglishVariant::Gb, false),
+            Some("Cloud conditions few 275".to_string())
+        );
+    }
+
+    #[test]
+    fn test_clouds_report_above_ground() {
+        let clouds = |base| Clouds {
+            base,
+            density: 8,
+            thickness: 200,
+            iprecptns: 0,
+        };
+        // 700m above the ground are ~2297ft
+        assert_eq!(
+            get_clouds_report(&clouds(2_500), 1_800.0, EnglishVariant::default(), false),
+            Some("Cloud conditions broken 20".to_string())
+        );
+        // clouds below the elevation are clamped to the ground
+        assert_eq!(
+            get_clouds_report(&clouds(1_500), 1_800.0, EnglishVariant::default(), false),
+            Some("Cloud conditions broken 0".to_string())
+        );
+
+        // a high-elevation airfield announces the ceiling above the airfield, not above MSL
+        let airfield = Airfield {
+            name: String::from("Tonopah"),
+            position: Position {
+                x: 0.0,
+                y: 0.0,
+                alt: 1_650.0,
+            },
+            runways: vec![String::from("14"), String::from("32")],
+            ..Default::default()
+        };
+        let weather = WeatherInfo {
+            clouds: Some(clouds(2_500)),
+            visibility: None,
+            wind_speed: 2.5,
+            wind_dir: 330.0,
+            temperature: 12.0,
+            pressure_qnh: 101_500.0,
+            pressure_qfe: 83_000.0,
+            position: airfield.position.clone(),
+        };
+        let report = airfield
+            .generate_report(
+                0,
+                &weather,
+                WindRounding::default(),
+                PressureRounding::default(),
+                EnglishVariant::default(),
+                false,
+            )
+            .unwrap();
+        // 850m above the airfield are ~2789ft
+        assert!(
+            report.contains("Cloud conditions broken 25. "),
+            "unexpected report: {}",
+            report
+        );
+    }
+
     #[tokio::test]
     async fn test_carrier_report() {
         let station = Station {
             name: String::from("Mother"),
             freq: 251_000_000,
+            modulation: Modulation::Am,
             tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
             transmitter: Transmitter::Carrier(Carrier {
                 name: "Stennis".to_string(),
                 unit_id: 42,
                 unit_name: "Stennis".to_string(),
+                recovery_thresholds: RecoveryThresholds::default(),
             }),
+            srs_port: None,
+            srs_name_template: None,
             rpc: None,
         };
 
         let report = station.generate_report(26).await.unwrap().unwrap();
-        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nNINER NINER, | Stennis\'s wind ZERO ZERO 6 at 3 knots, | altimeter 2 NINER NINER 7, | CASE 1, | BRC 1 ZERO 3 1 3, | expected final heading 1 ZERO 3 ZERO 4, | report initial.\n</speak>");
-        assert_eq!(report.textual, "99, Stennis\'s wind 006 at 3 knots, altimeter 2997, CASE 1, BRC 10313, expected final heading 10304, report initial.");
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nNINER NINER, | Stennis\'s wind ZERO 1 ZERO at 3 knots, | altimeter 2 NINER NINER 7, | CASE 1, | BRC 1 ZERO 3 1 3, | expected final heading 1 ZERO 3 ZERO 4, | report initial.\n</speak>");
+        assert_eq!(report.textual, "99, Stennis\'s wind 010 at 3 knots, altimeter 2997, CASE 1, BRC 10313, expected final heading 10304, report initial.");
+    }
+
+    #[test]
+    fn test_recovery_case_thresholds() {
+        let minimums = |s: &str| RecoveryMinimums::from_str(s).unwrap();
+        // ceiling of ~2001ft, visibility of exactly 5nm
+        let weather = WeatherInfo {
+            clouds: Some(Clouds {
+                base: 610,
+                density: 8,
+                thickness: 400,
+                iprecptns: 0,
+            }),
+            visibility: Some(9_260),
+            ..Default::default()
+        };
+
+        let thresholds = RecoveryThresholds::default();
+        assert_eq!(thresholds.recovery_case(&weather, 12), 2);
+        assert_eq!(thresholds.recovery_case(&weather, 22), 3);
+        assert_eq!(thresholds.recovery_case(&WeatherInfo::default(), 12), 1);
+
+        // lowering the Case I ceiling to just below the clouds
+        let thresholds = RecoveryThresholds::new(minimums("2000/5"), minimums("1000/5")).unwrap();
+        assert_eq!(thresholds.recovery_case(&weather, 12), 1);
+        let thresholds = RecoveryThresholds::new(minimums("2002/5"), minimums("1000/5")).unwrap();
+        assert_eq!(thresholds.recovery_case(&weather, 12), 2);
+
+        // raising the visibility minimums to just above the visibility
+        let thresholds = RecoveryThresholds::new(minimums("2000/5.1"), minimums("1000/5")).unwrap();
+        assert_eq!(thresholds.recovery_case(&weather, 12), 2);
+        let thresholds =
+            RecoveryThresholds::new(minimums("3000/5.1"), minimums("1000/5.1")).unwrap();
+        assert_eq!(thresholds.recovery_case(&weather, 12), 3);
+    }
+
+    #[test]
+    fn test_recovery_thresholds_validation() {
+        let minimums = |s: &str| RecoveryMinimums::from_str(s).unwrap();
+        assert!(RecoveryThresholds::new(minimums("3000/5"), minimums("3000/5")).is_ok());
+        assert!(RecoveryThresholds::new(minimums("1000/5"), minimums("3000/5")).is_err());
+        assert!(RecoveryThresholds::new(minimums("3000/3"), minimums("1000/5")).is_err());
+        assert!(RecoveryThresholds::new(minimums("3000/5"), minimums("-1000/5")).is_err());
+
+        assert_eq!(
+            minimums(" 3000 / 5 "),
+            RecoveryMinimums {
+                ceiling: 3_000.0,
+                visibility: 5.0,
+            }
+        );
+        assert!(RecoveryMinimums::from_str("3000").is_err());
+        assert!(RecoveryMinimums::from_str("3000ft/5nm").is_err());
     }
 
     #[tokio::test]
@@ -695,12 +3481,20 @@ mod test {
         let station = Station {
             name: String::from("Broadcast station"),
             freq: 251_000_000,
+            modulation: Modulation::Am,
             tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
             transmitter: Transmitter::Custom(Custom {
                 unit_id: 42,
                 unit_name: "Soldier".to_string(),
-                message: "Hello world".to_string(),
+                message: TextSource::Inline("Hello world".to_string()),
+                ssml: false,
             }),
+            srs_port: None,
+            srs_name_template: None,
             rpc: None,
         };
 
@@ -709,23 +3503,463 @@ mod test {
         assert_eq!(report.textual, "Hello world");
     }
 
+    #[tokio::test]
+    async fn test_custom_broadcast_is_escaped() {
+        let station = Station {
+            name: String::from("Broadcast station"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Custom(Custom {
+                unit_id: 42,
+                unit_name: "Soldier".to_string(),
+                message: TextSource::Inline("Fuel < 2000 lbs? Call Ops & RTB.".to_string()),
+                ssml: false,
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(26).await.unwrap().unwrap();
+        assert_eq!(report.spoken, "Fuel &lt; 2000 lbs? Call Ops &amp; RTB.");
+        assert_eq!(report.textual, "Fuel < 2000 lbs? Call Ops & RTB.");
+    }
+
+    #[tokio::test]
+    async fn test_report_with_special_characters_is_escaped() {
+        let station = Station {
+            name: String::from("Kutaisi"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Kutaisi"),
+                runways: vec![String::from("04"), String::from("22")],
+                remarks: Some(TextSource::Inline(
+                    "Taxiway <A> closed, use \"B\" & \"C\"".to_string(),
+                )),
+                intro: Some(TextSource::Inline("Welcome to K&K's".to_string())),
+                simple_report: true,
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(26).await.unwrap().unwrap();
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nWelcome to K&amp;K&apos;s. | This is Kutaisi information Alpha. | Runway in use is ZERO 4. | ALTIMETER 2 NINER NINER 7. | Remarks, Taxiway &lt;A&gt; closed, use &quot;B&quot; &amp; &quot;C&quot;. | End information Alpha.\n</speak>");
+        assert_eq!(report.textual, "Welcome to K&K's. This is Kutaisi information Alpha. Runway in use is 04. ALTIMETER 2997. Remarks, Taxiway <A> closed, use \"B\" & \"C\". End information Alpha.");
+
+        // besides the SSML tags of the report itself, no markup remains
+        let body = report
+            .spoken
+            .trim_start_matches(SPEAK_START_TAG)
+            .trim_end_matches("\n</speak>");
+        assert!(!body.contains('<') && !body.contains('>'));
+    }
+
+    #[tokio::test]
+    async fn test_report_with_unusual_airfield_name() {
+        let station = Station {
+            name: String::from("Évreux (Fauville)"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Airfield(Airfield {
+                name: String::from("Évreux (Fauville)"),
+                runways: vec![String::from("04"), String::from("22")],
+                simple_report: true,
+                ..Default::default()
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(26).await.unwrap().unwrap();
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is Evreux, Fauville information Alpha. | Runway in use is ZERO 4. | ALTIMETER 2 NINER NINER 7. | End information Alpha.\n</speak>");
+        assert_eq!(report.textual, "This is Évreux (Fauville) information Alpha. Runway in use is 04. ALTIMETER 2997. End information Alpha.");
+    }
+
+    #[test]
+    fn test_raw_ssml_broadcast() {
+        let mut custom = Custom {
+            unit_id: 42,
+            unit_name: "Soldier".to_string(),
+            message: TextSource::Inline(
+                "<speak>Range is <emphasis>hot</emphasis> &amp; active.</speak>".to_string(),
+            ),
+            ssml: true,
+        };
+        assert_eq!(
+            custom.read_message(),
+            Some((
+                "Range is hot & active.".to_string(),
+                "<speak>Range is <emphasis>hot</emphasis> &amp; active.</speak>".to_string()
+            ))
+        );
+
+        // malformed SSML isn't broadcasted
+        custom.message = TextSource::Inline("<speak>Range is <emphasis>hot</speak>".to_string());
+        assert_eq!(custom.read_message(), None);
+    }
+
+    #[tokio::test]
+    async fn test_custom_broadcast_from_missing_file() {
+        let station = Station {
+            name: String::from("Broadcast station"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Custom(Custom {
+                unit_id: 42,
+                unit_name: "Soldier".to_string(),
+                message: TextSource::File("does-not-exist/notams.txt".into()),
+                ssml: false,
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        assert!(station.generate_report(26).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_weather_report() {
         let station = Station {
             name: String::from("Mother"),
             freq: 251_000_000,
+            modulation: Modulation::Am,
             tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
             transmitter: Transmitter::Weather(WeatherTransmitter {
                 name: "Mountain Range".to_string(),
                 unit_id: 42,
                 unit_name: "Weather Post".to_string(),
                 info_ltr_offset: 15, // Should be "Papa"
             }),
+            srs_port: None,
+            srs_name_template: None,
             rpc: None,
         };
 
         let report = station.generate_report(26).await.unwrap().unwrap();
-        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is weather station Mountain Range information Papa. | Wind ZERO ZERO 6 at 5 knots. | Temperature 2 2 celcius. | ALTIMETER 2 NINER NINER 7. | REMARKS. | 1 ZERO 1 5 hectopascal. | QFE 2 NINER NINER 7 or 1 ZERO 1 5. | End information Papa.\n</speak>");
-        assert_eq!(report.textual, "This is weather station Mountain Range information Papa. Wind 006 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. REMARKS. 1015 hectopascal. QFE 2997 or 1015. End information Papa.");
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is weather station Mountain Range information Papa. | Wind ZERO 1 ZERO at 5 knots. | Temperature 2 2 celcius. | ALTIMETER 2 NINER NINER 7. | REMARKS. | 1 ZERO 1 5 hectopascal. | QFE 2 NINER NINER 7 or 1 ZERO 1 5. | End information Papa.\n</speak>");
+        assert_eq!(report.textual, "This is weather station Mountain Range information Papa. Wind 010 at 5 knots. Temperature 22 celcius. ALTIMETER 2997. REMARKS. 1015 hectopascal. QFE 2997 or 1015. End information Papa.");
+    }
+
+    #[tokio::test]
+    async fn test_farp_report() {
+        let station = Station {
+            name: String::from("FARP London"),
+            freq: 251_000_000,
+            modulation: Modulation::Am,
+            tts: TextToSpeechProvider::default(),
+            gain: 0.0,
+            wind_rounding: WindRounding::default(),
+            pressure_rounding: PressureRounding::default(),
+            log_level: None,
+            transmitter: Transmitter::Farp(Farp {
+                name: "London".to_string(),
+                unit_id: 42,
+                unit_name: "FARP London".to_string(),
+                pad: Some("2".to_string()),
+                info_ltr_offset: 0,
+            }),
+            srs_port: None,
+            srs_name_template: None,
+            rpc: None,
+        };
+
+        let report = station.generate_report(1).await.unwrap().unwrap();
+        assert_eq!(report.spoken, "<speak version=\"1.0\" xml:lang=\"en-US\">\nThis is FARP London information Bravo. | Landing pad 2. | Wind ZERO 1 ZERO at 5 knots. | ALTIMETER 2 NINER NINER 7. | QFE 2 NINER NINER 7 or 1 ZERO 1 5. | End information Bravo.\n</speak>");
+        assert_eq!(report.textual, "This is FARP London information Bravo. Landing pad 2. Wind 010 at 5 knots. ALTIMETER 2997. QFE 2997 or 1015. End information Bravo.");
+        assert_eq!(station.information_letter(1), Some("Bravo"));
+    }
+
+    #[test]
+    fn test_farp_report_without_pad() {
+        let farp = Farp {
+            name: "Anapa Pad".to_string(),
+            unit_id: 42,
+            unit_name: "FARP Anapa Pad".to_string(),
+            pad: None,
+            info_ltr_offset: 0,
+        };
+        let weather = WeatherInfo {
+            clouds: None,
+            visibility: None,
+            wind_speed: 5.0,
+            wind_dir: 90.0,
+            temperature: 22.0,
+            pressure_qnh: 101_500.0,
+            pressure_qfe: 101_500.0,
+            position: Position::default(),
+        };
+
+        let report = farp
+            .generate_report(
+                0,
+                &weather,
+                WindRounding::default(),
+                PressureRounding::default(),
+                false,
+            )
+            .unwrap();
+        assert_eq!(report, "This is FARP Anapa Pad information Alpha. Wind 090 at 10 knots. ALTIMETER 2997. QFE 2997 or 1015. End information Alpha.");
+    }
+
+    #[test]
+    fn test_report_truncation() {
+        let mut report = Report {
+            textual: String::new(),
+            spoken: format!(
+                "{}This is Kutaisi information Alpha. Runway in use is ZERO 4. End information Alpha.\n</speak>",
+                SPEAK_START_TAG
+            ),
+            position: LatLngPosition::default(),
+            inputs: ReportInputs::default(),
+        };
+        let untouched = report.spoken.clone();
+        assert!(!report.truncate(100));
+        assert_eq!(report.spoken, untouched);
+
+        assert!(report.truncate(60));
+        assert_eq!(
+            report.spoken,
+            format!(
+                "{}This is Kutaisi information Alpha. Runway in use is ZERO 4.\n</speak>",
+                SPEAK_START_TAG
+            )
+        );
+
+        // plain custom broadcasts
+        let mut report = Report {
+            textual: String::new(),
+            spoken: "Range is hot. Stay clear of the northern area.".to_string(),
+            position: LatLngPosition::default(),
+            inputs: ReportInputs::default(),
+        };
+        assert!(report.truncate(20));
+        assert_eq!(report.spoken, "Range is hot.");
+
+        // raw SSML
+        let mut report = Report {
+            textual: String::new(),
+            spoken: "<speak>Range is hot. Stay clear of the northern area.</speak>".to_string(),
+            position: LatLngPosition::default(),
+            inputs: ReportInputs::default(),
+        };
+        assert!(!report.truncate(20));
+    }
+
+    fn diff_airfield() -> Airfield {
+        Airfield {
+            name: String::from("Kutaisi"),
+            runways: vec![String::from("04"), String::from("22")],
+            ..Default::default()
+        }
+    }
+
+    fn diff_weather() -> WeatherInfo {
+        WeatherInfo {
+            clouds: Some(Clouds {
+                base: 1_500,
+                density: 6,
+                thickness: 200,
+                iprecptns: 0,
+            }),
+            visibility: None,
+            wind_speed: 2.5,
+            wind_dir: 6.0,
+            temperature: 22.0,
+            pressure_qnh: 101_500.0,
+            pressure_qfe: 101_500.0,
+            position: Position::default(),
+        }
+    }
+
+    fn diff_report(report_nr: usize, weather: &WeatherInfo, remarks: Option<&str>) -> Report {
+        let mut airfield = diff_airfield();
+        airfield.remarks = remarks.map(|remarks| TextSource::Inline(remarks.to_string()));
+        let generate = |spoken| {
+            airfield
+                .generate_report(
+                    report_nr,
+                    weather,
+                    WindRounding::default(),
+                    PressureRounding::default(),
+                    EnglishVariant::default(),
+                    spoken,
+                )
+                .unwrap()
+        };
+        Report {
+            textual: generate(false),
+            spoken: generate(true),
+            position: LatLngPosition::default(),
+            inputs: airfield.report_inputs(
+                report_nr,
+                weather,
+                WindRounding::default(),
+                PressureRounding::default(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_report_diff_unchanged() {
+        let previous = diff_report(0, &diff_weather(), None);
+        // only the information letter advanced
+        let current = diff_report(1, &diff_weather(), None);
+        assert_ne!(current.textual, previous.textual);
+        assert_eq!(current.diff(&previous), ReportDiff::Unchanged);
+        assert!(!current.diff(&previous).is_changed());
+
+        // changes that aren't announced (e.g. of a fraction of a knot) aren't a change either
+        let mut weather = diff_weather();
+        weather.wind_speed += 0.1;
+        let current = diff_report(1, &weather, None);
+        assert_eq!(current.diff(&previous), ReportDiff::Unchanged);
+    }
+
+    #[test]
+    fn test_report_diff_wind_changed() {
+        let previous = diff_report(0, &diff_weather(), None);
+        let mut weather = diff_weather();
+        weather.wind_dir = 220.0;
+        weather.wind_speed = 5.0;
+        let current = diff_report(1, &weather, None);
+        assert_eq!(
+            current.diff(&previous),
+            ReportDiff::Changed(vec![ReportField::Runway, ReportField::Wind])
+        );
+
+        // reports without runways (e.g. of carriers) are compared by their weather, too
+        let carrier_report = |weather: &WeatherInfo| Report {
+            textual: String::new(),
+            spoken: String::new(),
+            position: LatLngPosition::default(),
+            inputs: ReportInputs::new(
+                Some(weather),
+                WindRounding::default(),
+                PressureRounding::default(),
+            ),
+        };
+        weather.wind_dir = 10.0;
+        assert_eq!(
+            carrier_report(&weather).diff(&carrier_report(&diff_weather())),
+            ReportDiff::Changed(vec![ReportField::Wind])
+        );
+    }
+
+    #[test]
+    fn test_report_diff_altimeter_changed() {
+        let previous = diff_report(0, &diff_weather(), None);
+        let mut weather = diff_weather();
+        weather.pressure_qnh = 101_600.0;
+        weather.pressure_qfe = 101_600.0;
+        let current = diff_report(1, &weather, None);
+        assert_eq!(
+            current.diff(&previous),
+            ReportDiff::Changed(vec![ReportField::Altimeter])
+        );
+
+        // changes of the report that aren't caused by the weather or runways (e.g. new remarks)
+        let current = diff_report(1, &diff_weather(), Some("Runway 13 closed"));
+        assert_eq!(
+            current.diff(&previous),
+            ReportDiff::Changed(vec![ReportField::Other])
+        );
+    }
+
+    #[test]
+    fn test_wind_rounding() {
+        assert_eq!(WindRounding::Nearest10.format(184.0), "180");
+        assert_eq!(WindRounding::Nearest10.format(186.0), "190");
+        assert_eq!(WindRounding::Nearest10.format(355.0), "360");
+        assert_eq!(WindRounding::Nearest10.format(3.0), "360");
+        assert_eq!(WindRounding::Nearest10.format(6.0), "010");
+        assert_eq!(WindRounding::Nearest5.format(184.0), "185");
+        assert_eq!(WindRounding::Nearest5.format(358.0), "360");
+        assert_eq!(WindRounding::Exact.format(184.4), "184");
+        assert_eq!(WindRounding::Exact.format(359.6), "360");
+        assert_eq!(WindRounding::Exact.format(-10.0), "350");
+    }
+
+    #[test]
+    fn test_parse_wind_rounding() {
+        assert_eq!(
+            "10".parse::<WindRounding>().unwrap(),
+            WindRounding::Nearest10
+        );
+        assert_eq!(
+            " 5 ".parse::<WindRounding>().unwrap(),
+            WindRounding::Nearest5
+        );
+        assert_eq!(
+            "exact".parse::<WindRounding>().unwrap(),
+            WindRounding::Exact
+        );
+        assert!("15".parse::<WindRounding>().is_err());
+    }
+
+    #[test]
+    fn test_pressure_rounding() {
+        // ICAO rounds the QNH down to a whole hPa
+        assert_eq!(PressureRounding::Icao.hpa(101_390.0), 1013.0);
+        assert_eq!(PressureRounding::Icao.hpa(100_899.0), 1008.0);
+        assert_eq!(PressureRounding::Icao.hpa(101_300.0), 1013.0);
+        assert_eq!(PressureRounding::Us.hpa(101_390.0), 1014.0);
+        assert_eq!(PressureRounding::Us.hpa(100_899.0), 1009.0);
+
+        // US rounds the altimeter to the nearest hundredth of an inHg
+        assert_eq!(PressureRounding::Us.inhg(101_325.0), 2992.0);
+        assert_eq!(PressureRounding::Us.inhg(100_000.0), 2953.0);
+        assert_eq!(PressureRounding::Us.inhg(102_150.0), 3016.0);
+        assert_eq!(PressureRounding::Icao.inhg(101_325.0), 2992.0);
+    }
+
+    #[test]
+    fn test_parse_pressure_rounding() {
+        assert_eq!(
+            "icao".parse::<PressureRounding>().unwrap(),
+            PressureRounding::Icao
+        );
+        assert_eq!(
+            " US ".parse::<PressureRounding>().unwrap(),
+            PressureRounding::Us
+        );
+        assert!("metric".parse::<PressureRounding>().is_err());
+    }
+
+    #[test]
+    fn test_parse_modulation() {
+        assert_eq!("fm".parse::<Modulation>().unwrap(), Modulation::Fm);
+        assert_eq!(" AM ".parse::<Modulation>().unwrap(), Modulation::Am);
+        assert_eq!(Modulation::Fm.as_str(), "FM");
+        assert!("USB".parse::<Modulation>().is_err());
     }
 }
@@ -1,10 +1,14 @@
 use std::str::FromStr;
+use std::time::Duration;
 
-use audiopus::{coder::Encoder, Application, Channels, SampleRate};
 use rusoto_core::request::HttpClient;
-use rusoto_core::Region;
+use rusoto_core::{Region, RusotoError};
 use rusoto_credential::StaticProvider;
-use rusoto_polly::{Polly, PollyClient, SynthesizeSpeechInput};
+use rusoto_polly::{DescribeVoicesInput, Polly, PollyClient, SynthesizeSpeechInput};
+
+use super::proxy::ProxyConfig;
+use super::{with_timeout, AudioFormat};
+use crate::audio;
 
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum VoiceKind {
@@ -24,73 +28,220 @@ pub enum VoiceKind {
     Justin,
     Matthew,
     Geraint,
+    Olivia,
+    Kevin,
+    Aria,
+    Ayanda,
+}
+
+/// The Polly engine used to synthesize speech. Neural voices sound considerably more natural, but
+/// are only available for some of the voices (and regions).
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Engine {
+    Standard,
+    Neural,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::Standard
+    }
+}
+
+impl Engine {
+    fn as_str(self) -> &'static str {
+        match self {
+            Engine::Standard => "standard",
+            Engine::Neural => "neural",
+        }
+    }
+
+    /// Returns the requested engine if it is supported by the voice, or otherwise the engine the
+    /// voice does support.
+    pub fn for_voice(self, voice: VoiceKind) -> Engine {
+        match self {
+            Engine::Neural if !voice.supports_neural() => {
+                warn!(
+                    "AWS voice {:?} does not support the neural engine, falling back to the standard engine",
+                    voice
+                );
+                Engine::Standard
+            }
+            Engine::Standard if !voice.supports_standard() => Engine::Neural,
+            engine => engine,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct AmazonWebServicesConfig {
     pub voice: VoiceKind,
+    pub engine: Engine,
     pub key: String,
     pub secret: String,
     pub region: Region,
+    pub proxy: Option<ProxyConfig>,
+    pub timeout: Duration,
 }
 
+/// Synthesizes the SSML into audio of the given format and sample rate, which is either 8000 or
+/// 16000 (the only sample rates Polly supports for PCM), and decodes it into PCM. Polly doesn't
+/// support Ogg Opus.
 pub async fn text_to_speech(
     tts: &str,
     config: &AmazonWebServicesConfig,
-) -> Result<Vec<Vec<u8>>, anyhow::Error> {
-    let dispatcher = HttpClient::new()?;
-    let creds = StaticProvider::new(config.key.clone(), config.secret.clone(), None, None);
+    sample_rate: u32,
+    format: AudioFormat,
+) -> Result<Vec<i16>, anyhow::Error> {
+    with_timeout(config.timeout, synthesize(tts, config, sample_rate, format)).await
+}
+
+async fn synthesize(
+    tts: &str,
+    config: &AmazonWebServicesConfig,
+    sample_rate: u32,
+    format: AudioFormat,
+) -> Result<Vec<i16>, anyhow::Error> {
+    let output_format = match format {
+        AudioFormat::Pcm => "pcm",
+        AudioFormat::Mp3 => "mp3",
+        AudioFormat::OggOpus => return Err(anyhow!("Polly does not support Ogg Opus audio")),
+    };
 
     //Build text_to_speech request
     let req = SynthesizeSpeechInput {
-        engine: None, // TODO: allow usage of neural engine (only available for certain voices and regions!)
+        engine: Some(config.engine.as_str().to_string()),
         language_code: None,
         lexicon_names: None,
-        output_format: "pcm".to_string(),
-        sample_rate: None, // defaults to 16,000
+        output_format: output_format.to_string(),
+        sample_rate: Some(sample_rate.to_string()),
         speech_mark_types: None,
         text: tts.to_string(),
         text_type: Some("ssml".to_string()),
         voice_id: config.voice.to_string(),
     };
 
-    let client = PollyClient::new_with(dispatcher, creds, config.region.clone());
-    let response = client.synthesize_speech(req).await?;
+    let response = client(config)?
+        .synthesize_speech(req)
+        .await
+        .map_err(|err| request_error(err, config))?;
 
     let audio_stream = response
         .audio_stream
         .ok_or_else(|| anyhow!("Polly response did not contain an audio stream"))?;
-    let audio_stream = vector_i16(&audio_stream);
 
-    const MONO_20MS: usize = 16000 * 1 * 20 / 1000;
-    let enc = Encoder::new(SampleRate::Hz16000, Channels::Mono, Application::Voip)?;
-    let mut pos = 0;
-    let mut output = [0; 256];
-    let mut frames = Vec::new();
+    audio::decode(&audio_stream, format, sample_rate)
+}
 
-    while pos + MONO_20MS < audio_stream.len() {
-        let len = enc.encode(&audio_stream[pos..(pos + MONO_20MS)], &mut output)?;
-        frames.push(output[..len].to_vec());
+/// Checks whether the credentials are accepted by Polly, by listing the available voices (which
+/// is free of charge).
+pub async fn validate_credentials(config: &AmazonWebServicesConfig) -> Result<(), anyhow::Error> {
+    with_timeout(config.timeout, async {
+        let req = DescribeVoicesInput {
+            language_code: Some("en-US".to_string()),
+            ..Default::default()
+        };
+        client(config)?
+            .describe_voices(req)
+            .await
+            .map_err(|err| request_error(err, config))?;
+        Ok(())
+    })
+    .await
+}
 
-        pos += MONO_20MS;
-    }
+fn client(config: &AmazonWebServicesConfig) -> Result<PollyClient, anyhow::Error> {
+    let creds = StaticProvider::new(config.key.clone(), config.secret.clone(), None, None);
+    Ok(match config.proxy {
+        Some(ref proxy) => {
+            let dispatcher = HttpClient::from_connector(proxy.connector()?);
+            PollyClient::new_with(dispatcher, creds, config.region.clone())
+        }
+        None => PollyClient::new_with(HttpClient::new()?, creds, config.region.clone()),
+    })
+}
 
-    Ok(frames)
+fn request_error<E>(err: RusotoError<E>, config: &AmazonWebServicesConfig) -> anyhow::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    match config.proxy {
+        // rusoto doesn't expose the kind of an HTTP dispatch error, so mention the proxy for all
+        // of them
+        Some(ref proxy) if matches!(err, RusotoError::HttpDispatch(_)) => anyhow!(
+            "failed to send the request through the proxy {}: {}",
+            proxy.host(),
+            err
+        ),
+        _ => err.into(),
+    }
 }
 
-fn vector_i16(byte_stream: &[u8]) -> Vec<i16> {
-    let len = byte_stream.len();
-    let mut res: Vec<i16> = Vec::new();
-    let mut index_pos = 0;
-    //hopefully this pushed the bits 8 bits at a time into a stream of u8
-    while index_pos < len {
-        let this_byte = byte_stream[index_pos];
-        let next_byte = byte_stream[index_pos + 1];
-        let these_converted = i16::from_le_bytes([this_byte, next_byte]);
-        res.push(these_converted);
-        index_pos += 2;
+impl VoiceKind {
+    /// All supported voices.
+    pub const ALL: &'static [VoiceKind] = &[
+        VoiceKind::Nicole,
+        VoiceKind::Russell,
+        VoiceKind::Amy,
+        VoiceKind::Emma,
+        VoiceKind::Brian,
+        VoiceKind::Aditi,
+        VoiceKind::Raveena,
+        VoiceKind::Ivy,
+        VoiceKind::Joanna,
+        VoiceKind::Kendra,
+        VoiceKind::Kimberly,
+        VoiceKind::Salli,
+        VoiceKind::Joey,
+        VoiceKind::Justin,
+        VoiceKind::Matthew,
+        VoiceKind::Geraint,
+        VoiceKind::Olivia,
+        VoiceKind::Kevin,
+        VoiceKind::Aria,
+        VoiceKind::Ayanda,
+    ];
+
+    /// The voice's locale (e.g. `en-GB`).
+    pub fn locale(self) -> &'static str {
+        match self {
+            VoiceKind::Nicole | VoiceKind::Russell | VoiceKind::Olivia => "en-AU",
+            VoiceKind::Amy | VoiceKind::Emma | VoiceKind::Brian => "en-GB",
+            VoiceKind::Geraint => "en-GB-WLS",
+            VoiceKind::Aditi | VoiceKind::Raveena => "en-IN",
+            VoiceKind::Aria => "en-NZ",
+            VoiceKind::Ayanda => "en-ZA",
+            VoiceKind::Ivy
+            | VoiceKind::Joanna
+            | VoiceKind::Kendra
+            | VoiceKind::Kimberly
+            | VoiceKind::Salli
+            | VoiceKind::Joey
+            | VoiceKind::Justin
+            | VoiceKind::Matthew
+            | VoiceKind::Kevin => "en-US",
+        }
+    }
+
+    /// Whether the voice is available for the neural engine.
+    pub fn supports_neural(self) -> bool {
+        !matches!(
+            self,
+            VoiceKind::Nicole
+                | VoiceKind::Russell
+                | VoiceKind::Aditi
+                | VoiceKind::Raveena
+                | VoiceKind::Geraint
+        )
+    }
+
+    /// Whether the voice is available for the standard engine (some voices are neural only).
+    pub fn supports_standard(self) -> bool {
+        !matches!(
+            self,
+            VoiceKind::Olivia | VoiceKind::Kevin | VoiceKind::Aria | VoiceKind::Ayanda
+        )
     }
-    return res;
 }
 
 impl FromStr for VoiceKind {
@@ -106,3 +257,58 @@ impl ToString for VoiceKind {
         format!("{:?}", self)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Engine, VoiceKind};
+
+    #[test]
+    fn test_engine_for_voice() {
+        assert_eq!(Engine::Neural.for_voice(VoiceKind::Matthew), Engine::Neural);
+        assert_eq!(
+            Engine::Standard.for_voice(VoiceKind::Matthew),
+            Engine::Standard
+        );
+        // not available as neural voice
+        assert_eq!(
+            Engine::Neural.for_voice(VoiceKind::Geraint),
+            Engine::Standard
+        );
+        // only available as neural voice
+        assert_eq!(Engine::Standard.for_voice(VoiceKind::Kevin), Engine::Neural);
+    }
+
+    #[test]
+    fn test_all_voices() {
+        // doesn't compile once a voice is added, as a reminder to also add it to `VoiceKind::ALL`
+        fn position(voice: VoiceKind) -> usize {
+            match voice {
+                VoiceKind::Nicole => 0,
+                VoiceKind::Russell => 1,
+                VoiceKind::Amy => 2,
+                VoiceKind::Emma => 3,
+                VoiceKind::Brian => 4,
+                VoiceKind::Aditi => 5,
+                VoiceKind::Raveena => 6,
+                VoiceKind::Ivy => 7,
+                VoiceKind::Joanna => 8,
+                VoiceKind::Kendra => 9,
+                VoiceKind::Kimberly => 10,
+                VoiceKind::Salli => 11,
+                VoiceKind::Joey => 12,
+                VoiceKind::Justin => 13,
+                VoiceKind::Matthew => 14,
+                VoiceKind::Geraint => 15,
+                VoiceKind::Olivia => 16,
+                VoiceKind::Kevin => 17,
+                VoiceKind::Aria => 18,
+                VoiceKind::Ayanda => 19,
+            }
+        }
+
+        assert_eq!(VoiceKind::ALL.len(), 20);
+        for (i, voice) in VoiceKind::ALL.iter().enumerate() {
+            assert_eq!(position(*voice), i);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use super::TextToSpeechProvider;
+
+/// Distributes voices (and thereby their TTS providers) using a smooth weighted round-robin,
+/// e.g. to spread the usage across the free quotas of multiple cloud providers. A single balancer
+/// is shared by all stations without an explicit voice, so that the voices are balanced across
+/// all their synthesized reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceBalancer {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    voice: TextToSpeechProvider,
+    weight: i64,
+    current: i64,
+    count: usize,
+}
+
+impl VoiceBalancer {
+    pub fn new(voices: Vec<(TextToSpeechProvider, u32)>) -> Self {
+        VoiceBalancer {
+            entries: voices
+                .into_iter()
+                .filter(|(_, weight)| *weight > 0)
+                .map(|(voice, weight)| Entry {
+                    voice,
+                    weight: i64::from(weight),
+                    current: 0,
+                    count: 0,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The voices the balancer distributes.
+    pub fn voices(&self) -> impl Iterator<Item = &TextToSpeechProvider> {
+        self.entries.iter().map(|e| &e.voice)
+    }
+
+    /// Removes all voices for which the predicate returns `false`, e.g. voices of providers
+    /// without credentials.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&TextToSpeechProvider) -> bool,
+    {
+        self.entries.retain(|e| f(&e.voice));
+    }
+
+    /// Returns the next voice.
+    pub fn next_voice(&mut self) -> Option<TextToSpeechProvider> {
+        let total: i64 = self.entries.iter().map(|e| e.weight).sum();
+        for entry in &mut self.entries {
+            entry.current += entry.weight;
+        }
+
+        let entry = self.entries.iter_mut().max_by_key(|e| e.current)?;
+        entry.current -= total;
+        entry.count += 1;
+        Some(entry.voice.clone())
+    }
+
+    /// Returns how often each voice has been handed out so far.
+    pub fn counts(&self) -> Vec<(&TextToSpeechProvider, usize)> {
+        self.entries.iter().map(|e| (&e.voice, e.count)).collect()
+    }
+}
+
+impl FromStr for VoiceBalancer {
+    type Err = anyhow::Error;
+
+    /// Parses a comma separated list of voices with an optional weight each (defaults to 1),
+    /// e.g. `GC:en-US-Standard-C=2, AWS:Brian=1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut voices = Vec::new();
+        for entry in s.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            let mut parts = entry.splitn(2, '=');
+            let voice = parts.next().unwrap_or("").trim();
+            let weight = match parts.next() {
+                Some(weight) => u32::from_str(weight.trim())
+                    .map_err(|_| anyhow!("invalid weight `{}` for voice {}", weight, voice))?,
+                None => 1,
+            };
+            let voice = TextToSpeechProvider::from_str(voice)
+                .map_err(|err| anyhow!("invalid voice `{}`: {}", voice, err))?;
+            voices.push((voice, weight));
+        }
+
+        Ok(VoiceBalancer::new(voices))
+    }
+}
+
+#[cfg(all(test, feature = "gcloud", feature = "aws"))]
+mod test {
+    use std::str::FromStr;
+
+    use super::VoiceBalancer;
+    use crate::tts::{aws, gcloud, TextToSpeechProvider};
+
+    #[test]
+    fn distribution_matches_weights() {
+        let gc = TextToSpeechProvider::GoogleCloud {
+            voice: gcloud::VoiceKind::StandardC,
+        };
+        let aws = TextToSpeechProvider::AmazonWebServices {
+            voice: aws::VoiceKind::Brian,
+            engine: aws::Engine::Standard,
+        };
+        let mut balancer = VoiceBalancer::from_str("GC:en-US-Standard-C=3, AWS:Brian").unwrap();
+
+        let mut gc_count = 0;
+        for _ in 0..1000 {
+            if balancer.next_voice().unwrap() == gc {
+                gc_count += 1;
+            }
+        }
+
+        // 3:1 split, allowing for a tiny tolerance
+        assert!((748..=752).contains(&gc_count), "gc count: {}", gc_count);
+        assert_eq!(
+            balancer.counts(),
+            vec![(&gc, gc_count), (&aws, 1000 - gc_count)]
+        );
+    }
+
+    #[test]
+    fn interleaves_voices() {
+        let mut balancer = VoiceBalancer::from_str("WIN=1, AWS:Brian=1").unwrap();
+        let first = balancer.next_voice().unwrap();
+        let second = balancer.next_voice().unwrap();
+        assert_ne!(first, second);
+        assert_eq!(balancer.next_voice().unwrap(), first);
+    }
+
+    #[test]
+    fn empty_balancer() {
+        let mut balancer = VoiceBalancer::from_str("").unwrap();
+        assert!(balancer.is_empty());
+        assert_eq!(balancer.next_voice(), None);
+    }
+
+    #[test]
+    fn retain_voices() {
+        let mut balancer = VoiceBalancer::from_str("WIN=2, AWS:Brian=1").unwrap();
+        balancer.retain(|voice| *voice == TextToSpeechProvider::default());
+        assert_eq!(balancer.voices().count(), 1);
+        for _ in 0..3 {
+            assert_eq!(
+                balancer.next_voice().unwrap(),
+                TextToSpeechProvider::default()
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_weight() {
+        assert!(VoiceBalancer::from_str("AWS:Brian=abc").is_err());
+    }
+}
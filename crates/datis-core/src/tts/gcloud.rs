@@ -1,14 +1,50 @@
-use std::io::Cursor;
+use std::fs;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use ogg::reading::PacketReader;
-use reqwest::StatusCode;
+use super::proxy::{self, ProxyConfig};
+use super::service_account::ServiceAccount;
+use super::{with_timeout, AudioFormat};
+use crate::audio;
+use reqwest::{RequestBuilder, Response, StatusCode};
 use serde_json::Value;
 
 #[derive(Clone)]
 pub struct GoogleCloudConfig {
     pub voice: VoiceKind,
-    pub key: String,
+    pub credentials: Credentials,
+    /// The region of the endpoint the requests are sent to, or `None` for the global endpoint.
+    pub region: Option<String>,
+    pub proxy: Option<ProxyConfig>,
+    pub timeout: Duration,
+}
+
+/// The credentials the Text-to-Speech API is authenticated with.
+#[derive(Clone)]
+pub enum Credentials {
+    ApiKey(String),
+    /// Service account credentials, which are used to mint short-lived access tokens (shared
+    /// across all stations, so that tokens are only refreshed once).
+    ServiceAccount(Arc<ServiceAccount>),
+}
+
+impl Credentials {
+    /// Parses the configured Google Cloud key, which is either an API key, the path of a service
+    /// account's JSON key file or the content of such a file.
+    pub fn from_key(key: &str) -> Result<Self, anyhow::Error> {
+        let key = key.trim();
+        let json = if key.starts_with('{') {
+            key.to_string()
+        } else if key.to_lowercase().ends_with(".json") {
+            fs::read_to_string(key)
+                .map_err(|err| anyhow!("failed to read service account key {}: {}", key, err))?
+        } else {
+            return Ok(Credentials::ApiKey(key.to_string()));
+        };
+        let account = ServiceAccount::from_json(&json)?;
+        Ok(Credentials::ServiceAccount(Arc::new(account)))
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -68,31 +104,60 @@ pub enum VoiceKind {
     WavenetE,
     #[serde(rename = "en-US-Wavenet-F")]
     WavenetF,
+    #[serde(rename = "en-US-Wavenet-G")]
+    WavenetG,
+    #[serde(rename = "en-US-Wavenet-H")]
+    WavenetH,
+    #[serde(rename = "en-US-Wavenet-I")]
+    WavenetI,
+    #[serde(rename = "en-US-Wavenet-J")]
+    WavenetJ,
+    #[serde(rename = "en-US-Neural2-A")]
+    Neural2A,
+    #[serde(rename = "en-US-Neural2-C")]
+    Neural2C,
+    #[serde(rename = "en-US-Neural2-D")]
+    Neural2D,
+    #[serde(rename = "en-US-Neural2-E")]
+    Neural2E,
+    #[serde(rename = "en-US-Neural2-F")]
+    Neural2F,
+    #[serde(rename = "en-US-Neural2-G")]
+    Neural2G,
+    #[serde(rename = "en-US-Neural2-H")]
+    Neural2H,
+    #[serde(rename = "en-US-Neural2-I")]
+    Neural2I,
+    #[serde(rename = "en-US-Neural2-J")]
+    Neural2J,
 }
 
+/// Synthesizes the SSML into audio of the given format and sample rate, and decodes it into PCM.
 pub async fn text_to_speech(
     text: &str,
     config: &GoogleCloudConfig,
-) -> Result<Vec<Vec<u8>>, anyhow::Error> {
-    let payload = TextToSpeechRequest {
-        audio_config: AudioConfig {
-            audio_encoding: "OGG_OPUS",
-            sample_rate_hertz: 16_000,
-            speaking_rate: 0.9,
-        },
-        input: Input { ssml: text },
-        voice: Voice {
-            language_code: "en-US",
-            name: config.voice,
-        },
-    };
-
-    let url = format!(
-        "https://texttospeech.googleapis.com/v1/text:synthesize?key={}",
-        config.key
-    );
-    let client = reqwest::Client::new();
-    let res = client.post(&url).json(&payload).send().await?;
+    sample_rate: u32,
+    format: AudioFormat,
+) -> Result<Vec<i16>, anyhow::Error> {
+    with_timeout(
+        config.timeout,
+        synthesize(text, config, sample_rate, format),
+    )
+    .await
+}
+
+async fn synthesize(
+    text: &str,
+    config: &GoogleCloudConfig,
+    sample_rate: u32,
+    format: AudioFormat,
+) -> Result<Vec<i16>, anyhow::Error> {
+    let payload = synthesize_request(text, config.voice, sample_rate, format);
+
+    let url = format!("{}/v1/text:synthesize", endpoint(config.region.as_deref()));
+    let client = proxy::http_client(config.proxy.as_ref())?;
+    let req = authorize(&client, client.post(&url), &config.credentials).await?;
+    let res = send(req.json(&payload), config).await?;
     if res.status() != StatusCode::OK {
         let err: Value = res.json().await?;
         return Err(anyhow!("Gcloud TTL error: {}", err));
@@ -100,16 +165,136 @@ pub async fn text_to_speech(
 
     let data: TextToSpeechResponse = res.json().await?;
     let data = base64::decode(&data.audio_content)?;
-    let data = Cursor::new(data);
 
-    let mut frames = Vec::new();
+    // the LINEAR16 audio content contains a WAV header
+    audio::decode(&data, format, sample_rate)
+}
+
+/// Checks whether the credentials are accepted by the Text-to-Speech API, by listing the
+/// available voices (which is free of charge).
+pub async fn validate_credentials(config: &GoogleCloudConfig) -> Result<(), anyhow::Error> {
+    with_timeout(config.timeout, async {
+        let url = format!("{}/v1/voices", endpoint(config.region.as_deref()));
+        let client = proxy::http_client(config.proxy.as_ref())?;
+        let req = authorize(&client, client.get(&url), &config.credentials).await?;
+        let res = send(req.query(&[("languageCode", "en-US")]), config).await?;
+        if !res.status().is_success() {
+            let err: Value = res.json().await?;
+            return Err(anyhow!("Gcloud TTL error: {}", err));
+        }
+        Ok(())
+    })
+    .await
+}
+
+async fn authorize(
+    client: &reqwest::Client,
+    req: RequestBuilder,
+    credentials: &Credentials,
+) -> Result<RequestBuilder, anyhow::Error> {
+    Ok(match credentials {
+        Credentials::ApiKey(ref key) => req.query(&[("key", key)]),
+        Credentials::ServiceAccount(ref account) => {
+            req.bearer_auth(account.access_token(client).await?)
+        }
+    })
+}
 
-    let mut audio = PacketReader::new(data);
-    while let Some(pck) = audio.read_packet()? {
-        frames.push(pck.data.to_vec())
+async fn send(req: RequestBuilder, config: &GoogleCloudConfig) -> Result<Response, anyhow::Error> {
+    req.send().await.map_err(|err| match config.proxy {
+        Some(ref proxy) => proxy.connect_error(err),
+        None => err.into(),
+    })
+}
+
+/// Validates the region of a regional endpoint, e.g. `eu`, `us` or `asia-southeast1`.
+pub fn validate_region(region: &str) -> Result<(), anyhow::Error> {
+    let is_valid = region.starts_with(|c: char| c.is_ascii_lowercase())
+        && region.split('-').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        });
+    if is_valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "invalid Google Cloud region `{}` (expected e.g. `eu` or `asia-southeast1`)",
+            region
+        ))
     }
+}
 
-    Ok(frames)
+/// Returns the (regional) endpoint of the Text-to-Speech API.
+fn endpoint(region: Option<&str>) -> String {
+    match region {
+        Some(region) => format!("https://{}-texttospeech.googleapis.com", region),
+        None => "https://texttospeech.googleapis.com".to_string(),
+    }
+}
+
+fn synthesize_request(
+    text: &str,
+    voice: VoiceKind,
+    sample_rate: u32,
+    format: AudioFormat,
+) -> TextToSpeechRequest<'_> {
+    TextToSpeechRequest {
+        audio_config: AudioConfig {
+            audio_encoding: match format {
+                AudioFormat::Pcm => "LINEAR16",
+                AudioFormat::OggOpus => "OGG_OPUS",
+                AudioFormat::Mp3 => "MP3",
+            },
+            sample_rate_hertz: sample_rate,
+            speaking_rate: 0.9,
+        },
+        input: Input { ssml: text },
+        // the voice name also determines the model (Standard, WaveNet or Neural2) used by Google
+        voice: Voice {
+            language_code: "en-US",
+            name: voice,
+        },
+    }
+}
+
+impl VoiceKind {
+    /// All supported voices.
+    pub const ALL: &'static [VoiceKind] = &[
+        VoiceKind::StandardB,
+        VoiceKind::StandardC,
+        VoiceKind::StandardD,
+        VoiceKind::StandardE,
+        VoiceKind::WavenetA,
+        VoiceKind::WavenetB,
+        VoiceKind::WavenetC,
+        VoiceKind::WavenetD,
+        VoiceKind::WavenetE,
+        VoiceKind::WavenetF,
+        VoiceKind::WavenetG,
+        VoiceKind::WavenetH,
+        VoiceKind::WavenetI,
+        VoiceKind::WavenetJ,
+        VoiceKind::Neural2A,
+        VoiceKind::Neural2C,
+        VoiceKind::Neural2D,
+        VoiceKind::Neural2E,
+        VoiceKind::Neural2F,
+        VoiceKind::Neural2G,
+        VoiceKind::Neural2H,
+        VoiceKind::Neural2I,
+        VoiceKind::Neural2J,
+    ];
+
+    /// The voice's name as used by Google Cloud (e.g. `en-US-Standard-B`), which is also what
+    /// [`FromStr`] parses.
+    pub fn name(self) -> String {
+        match serde_json::to_value(self) {
+            Ok(Value::String(name)) => name,
+            _ => unreachable!("voices are serialized as strings"),
+        }
+    }
 }
 
 impl FromStr for VoiceKind {
@@ -119,3 +304,115 @@ impl FromStr for VoiceKind {
         serde_json::from_value(json!(s))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::{endpoint, synthesize_request, validate_region, Credentials, VoiceKind};
+    use crate::tts::AudioFormat;
+
+    #[test]
+    fn test_voice_families() {
+        assert_eq!(
+            VoiceKind::from_str("en-US-Standard-B").unwrap(),
+            VoiceKind::StandardB
+        );
+        assert_eq!(
+            VoiceKind::from_str("en-US-Wavenet-D").unwrap(),
+            VoiceKind::WavenetD
+        );
+        assert_eq!(
+            VoiceKind::from_str("en-US-Neural2-F").unwrap(),
+            VoiceKind::Neural2F
+        );
+        assert!(VoiceKind::from_str("en-US-Neural2-B").is_err());
+    }
+
+    #[test]
+    fn test_request_voice() {
+        let request = serde_json::to_value(synthesize_request(
+            "<speak/>",
+            VoiceKind::Neural2F,
+            16_000,
+            AudioFormat::Pcm,
+        ))
+        .unwrap();
+        assert_eq!(request["voice"]["name"], "en-US-Neural2-F");
+        assert_eq!(request["voice"]["languageCode"], "en-US");
+
+        let request = serde_json::to_value(synthesize_request(
+            "<speak/>",
+            VoiceKind::WavenetD,
+            16_000,
+            AudioFormat::Pcm,
+        ))
+        .unwrap();
+        assert_eq!(request["voice"]["name"], "en-US-Wavenet-D");
+    }
+
+    #[test]
+    fn test_request_sample_rate() {
+        let request = serde_json::to_value(synthesize_request(
+            "<speak/>",
+            VoiceKind::StandardC,
+            24_000,
+            AudioFormat::Pcm,
+        ))
+        .unwrap();
+        assert_eq!(request["audioConfig"]["sampleRateHertz"], 24_000);
+    }
+
+    #[test]
+    fn test_request_audio_encoding() {
+        let encoding = |format| {
+            let request = synthesize_request("<speak/>", VoiceKind::StandardC, 24_000, format);
+            serde_json::to_value(request).unwrap()["audioConfig"]["audioEncoding"].clone()
+        };
+        assert_eq!(encoding(AudioFormat::Pcm), "LINEAR16");
+        assert_eq!(encoding(AudioFormat::OggOpus), "OGG_OPUS");
+        assert_eq!(encoding(AudioFormat::Mp3), "MP3");
+    }
+
+    #[test]
+    fn test_regional_endpoint() {
+        assert_eq!(endpoint(None), "https://texttospeech.googleapis.com");
+        assert_eq!(
+            endpoint(Some("eu")),
+            "https://eu-texttospeech.googleapis.com"
+        );
+        assert_eq!(
+            endpoint(Some("asia-southeast1")),
+            "https://asia-southeast1-texttospeech.googleapis.com"
+        );
+
+        assert!(validate_region("eu").is_ok());
+        assert!(validate_region("europe-west3").is_ok());
+        assert!(validate_region("EU").is_err());
+        assert!(validate_region("evil.com/").is_err());
+        assert!(validate_region("").is_err());
+    }
+
+    #[test]
+    fn test_credentials_from_key() {
+        match Credentials::from_key(" AIzaSyExampleKey ").unwrap() {
+            Credentials::ApiKey(key) => assert_eq!(key, "AIzaSyExampleKey"),
+            _ => panic!("expected an API key"),
+        }
+
+        let json = serde_json::json!({
+            "type": "service_account",
+            "client_email": "datis@example.iam.gserviceaccount.com",
+            "private_key": include_str!("dummy_service_account_key.pem"),
+        });
+        match Credentials::from_key(&json.to_string()).unwrap() {
+            Credentials::ServiceAccount(account) => assert_eq!(
+                account.client_email(),
+                "datis@example.iam.gserviceaccount.com"
+            ),
+            _ => panic!("expected service account credentials"),
+        }
+
+        assert!(Credentials::from_key("./does-not-exist.json").is_err());
+    }
+}
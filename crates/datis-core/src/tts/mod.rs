@@ -1,65 +1,304 @@
+#[cfg(feature = "aws")]
 pub mod aws;
+pub mod balancer;
+#[cfg(feature = "gcloud")]
 pub mod gcloud;
+pub mod proxy;
+#[cfg(feature = "gcloud")]
+pub mod service_account;
+pub mod ssml;
+pub mod validate;
 pub mod win;
 
+use std::error::Error;
 use std::fmt;
+use std::future::Future;
 use std::str::FromStr;
+use std::time::Duration;
 
-#[derive(PartialEq, Clone)]
+#[cfg(any(feature = "gcloud", feature = "aws"))]
+use crate::utils::levenshtein;
+
+/// A TTS provider and its voice. The cloud providers are only available if DATIS is compiled with
+/// their feature (`gcloud` and `aws`, both enabled by default), the Windows built-in TTS is always
+/// available.
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
 pub enum TextToSpeechProvider {
-    GoogleCloud { voice: gcloud::VoiceKind },
-    AmazonWebServices { voice: aws::VoiceKind },
-    Windows { voice: Option<String> },
+    #[cfg(feature = "gcloud")]
+    GoogleCloud {
+        voice: gcloud::VoiceKind,
+    },
+    #[cfg(feature = "aws")]
+    AmazonWebServices {
+        voice: aws::VoiceKind,
+        engine: aws::Engine,
+    },
+    Windows {
+        voice: Option<String>,
+    },
+    /// A voice picked by the voice balancer (see [`balancer::VoiceBalancer`]) for each
+    /// synthesized report.
+    Balanced,
 }
 
 #[derive(Clone)]
 pub enum TextToSpeechConfig {
+    #[cfg(feature = "gcloud")]
     GoogleCloud(gcloud::GoogleCloudConfig),
+    #[cfg(feature = "aws")]
     AmazonWebServices(aws::AmazonWebServicesConfig),
     Windows(win::WindowsConfig),
 }
 
+impl TextToSpeechConfig {
+    /// The sample rate (in Hz) used for the provider unless configured otherwise. It is the rate
+    /// the provider synthesizes at natively (Google's voices are sampled at 24kHz), so that the
+    /// audio doesn't have to be resampled.
+    pub fn default_sample_rate(&self) -> u32 {
+        match self {
+            #[cfg(feature = "gcloud")]
+            TextToSpeechConfig::GoogleCloud(_) => 24_000,
+            #[cfg(feature = "aws")]
+            TextToSpeechConfig::AmazonWebServices(_) => 16_000,
+            TextToSpeechConfig::Windows(_) => 16_000,
+        }
+    }
+
+    /// The sample rate (in Hz) the provider is asked to synthesize at for the given target sample
+    /// rate. Polly only supports 8kHz and 16kHz PCM and the Windows TTS always outputs 16kHz, so
+    /// their audio is resampled for other target sample rates.
+    #[cfg_attr(not(any(feature = "gcloud", feature = "aws")), allow(unused_variables))]
+    pub fn synthesis_sample_rate(&self, sample_rate: u32) -> u32 {
+        match self {
+            #[cfg(feature = "gcloud")]
+            TextToSpeechConfig::GoogleCloud(_) => sample_rate,
+            #[cfg(feature = "aws")]
+            TextToSpeechConfig::AmazonWebServices(_) if sample_rate == 8_000 => 8_000,
+            #[cfg(feature = "aws")]
+            TextToSpeechConfig::AmazonWebServices(_) => 16_000,
+            TextToSpeechConfig::Windows(_) => 16_000,
+        }
+    }
+
+    /// The audio format the provider is asked to synthesize into for the requested format. Polly
+    /// doesn't support Ogg Opus and the Windows TTS always outputs WAV, so they fall back to PCM.
+    #[cfg_attr(not(any(feature = "gcloud", feature = "aws")), allow(unused_variables))]
+    pub fn synthesis_format(&self, format: AudioFormat) -> AudioFormat {
+        match self {
+            #[cfg(feature = "gcloud")]
+            TextToSpeechConfig::GoogleCloud(_) => format,
+            #[cfg(feature = "aws")]
+            TextToSpeechConfig::AmazonWebServices(_) if format == AudioFormat::OggOpus => {
+                AudioFormat::Pcm
+            }
+            #[cfg(feature = "aws")]
+            TextToSpeechConfig::AmazonWebServices(_) => format,
+            TextToSpeechConfig::Windows(_) => AudioFormat::Pcm,
+        }
+    }
+}
+
+/// The audio format the TTS providers are asked to synthesize into. The synthesized audio is
+/// always decoded to PCM before it is encoded for SRS, so this is mostly useful for debugging.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AudioFormat {
+    /// 16bit little-endian PCM (optionally with a WAV header).
+    Pcm,
+    /// Opus encoded audio in an Ogg container.
+    OggOpus,
+    Mp3,
+}
+
+impl Default for AudioFormat {
+    fn default() -> Self {
+        AudioFormat::Pcm
+    }
+}
+
+impl FromStr for AudioFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pcm" => Ok(AudioFormat::Pcm),
+            "ogg-opus" | "ogg_opus" => Ok(AudioFormat::OggOpus),
+            "mp3" => Ok(AudioFormat::Mp3),
+            _ => Err(anyhow!(
+                "invalid audio format `{}` (expected pcm, ogg-opus or mp3)",
+                s
+            )),
+        }
+    }
+}
+
+/// The English variant a voice speaks, which affects how numbers are spelled out in the spoken
+/// reports (e.g. `two hundred fifty` in en-US, but `two hundred and fifty` in en-GB).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EnglishVariant {
+    Us,
+    Gb,
+    Au,
+}
+
+impl Default for EnglishVariant {
+    fn default() -> Self {
+        EnglishVariant::Us
+    }
+}
+
+impl EnglishVariant {
+    /// The variant of a locale (e.g. `en-GB`). Locales other than en-GB and en-AU are en-US.
+    pub fn from_locale(locale: &str) -> Self {
+        let locale = locale.to_lowercase();
+        if locale.starts_with("en-gb") {
+            EnglishVariant::Gb
+        } else if locale.starts_with("en-au") {
+            EnglishVariant::Au
+        } else {
+            EnglishVariant::Us
+        }
+    }
+}
+
+/// The default timeout of a request to a cloud TTS provider.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Temporary errors of the cloud TTS providers, after which the request is retried.
+#[derive(Debug, PartialEq)]
+pub enum TextToSpeechError {
+    /// The TTS provider didn't respond within the timeout.
+    Timeout(Duration),
+}
+
+impl TextToSpeechError {
+    /// Whether the error is a temporary TTS error, i.e. whether the request should be retried.
+    pub fn is_retryable(err: &anyhow::Error) -> bool {
+        err.downcast_ref::<TextToSpeechError>().is_some()
+    }
+}
+
+impl fmt::Display for TextToSpeechError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextToSpeechError::Timeout(timeout) => write!(
+                f,
+                "TTS request timed out after {:.1}s",
+                timeout.as_secs_f32()
+            ),
+        }
+    }
+}
+
+impl Error for TextToSpeechError {}
+
+/// Fails with [`TextToSpeechError::Timeout`] if the request doesn't complete within the
+/// timeout.
+pub async fn with_timeout<F, T>(timeout: Duration, request: F) -> Result<T, anyhow::Error>
+where
+    F: Future<Output = Result<T, anyhow::Error>>,
+{
+    match tokio::time::timeout(timeout, request).await {
+        Ok(result) => result,
+        Err(_) => Err(TextToSpeechError::Timeout(timeout).into()),
+    }
+}
+
+/// A TTS provider and the voices it supports, e.g. for tools that let users choose a voice.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProviderVoices {
+    /// The provider's display name.
+    pub provider: &'static str,
+    /// The identifiers of the provider's voices, as accepted by
+    /// [`TextToSpeechProvider::from_str`] (e.g. `GC:en-US-Standard-B` or `AWS:Brian`).
+    pub voices: Vec<String>,
+}
+
+/// Lists all supported TTS providers (that DATIS has been compiled with) and their voices. The
+/// voices of the Windows built-in TTS depend on the installed voices, so only its default voice
+/// (`WIN`) is listed; installed voices can be used with `WIN:{voice name}`.
+pub fn available_voices() -> Vec<ProviderVoices> {
+    let mut providers = Vec::new();
+    #[cfg(feature = "gcloud")]
+    providers.push(ProviderVoices {
+        provider: "Google Cloud",
+        voices: gcloud::VoiceKind::ALL
+            .iter()
+            .map(|voice| format!("GC:{}", voice.name()))
+            .collect(),
+    });
+    #[cfg(feature = "aws")]
+    providers.push(ProviderVoices {
+        provider: "Amazon Web Services",
+        voices: aws::VoiceKind::ALL
+            .iter()
+            .map(|voice| format!("AWS:{}", voice.to_string()))
+            .collect(),
+    });
+    providers.push(ProviderVoices {
+        provider: "Windows built-in TTS",
+        voices: vec!["WIN".to_string()],
+    });
+    providers
+}
+
 impl Default for TextToSpeechProvider {
     fn default() -> Self {
         TextToSpeechProvider::Windows { voice: None }
     }
 }
 
+impl TextToSpeechProvider {
+    /// The English variant of the provider's voice. The locale of the voices of the Windows
+    /// built-in TTS isn't known, so they are en-US.
+    pub fn english_variant(&self) -> EnglishVariant {
+        match self {
+            #[cfg(feature = "gcloud")]
+            TextToSpeechProvider::GoogleCloud { voice } => {
+                EnglishVariant::from_locale(&voice.name())
+            }
+            #[cfg(feature = "aws")]
+            TextToSpeechProvider::AmazonWebServices { voice, .. } => {
+                EnglishVariant::from_locale(voice.locale())
+            }
+            TextToSpeechProvider::Windows { .. } | TextToSpeechProvider::Balanced => {
+                EnglishVariant::default()
+            }
+        }
+    }
+}
+
 impl fmt::Debug for TextToSpeechProvider {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
+            #[cfg(feature = "gcloud")]
             TextToSpeechProvider::GoogleCloud { voice } => {
                 write!(f, "Google Cloud (Voice: {:?})", voice)
             }
-            TextToSpeechProvider::AmazonWebServices { voice } => {
-                write!(f, "Amazon Web Services (Voice: {:?})", voice)
-            }
+            #[cfg(feature = "aws")]
+            TextToSpeechProvider::AmazonWebServices { voice, engine } => write!(
+                f,
+                "Amazon Web Services (Voice: {:?}, Engine: {:?})",
+                voice, engine
+            ),
             TextToSpeechProvider::Windows { voice } => write!(
                 f,
                 "Windows built-in TTS (Voice: {:?})",
                 voice.as_ref().map(|v| &**v).unwrap_or_else(|| "Default")
             ),
+            TextToSpeechProvider::Balanced => write!(f, "Balanced"),
         }
     }
 }
 
 impl FromStr for TextToSpeechProvider {
-    type Err = serde_json::Error;
+    type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let v: Vec<&str> = s.splitn(2, ':').collect();
         match v.as_slice() {
             &[prefix, voice] => match prefix {
-                "GC" | "gc" => {
-                    return Ok(TextToSpeechProvider::GoogleCloud {
-                        voice: gcloud::VoiceKind::from_str(voice)?,
-                    })
-                }
-                "AWS" | "aws" => {
-                    return Ok(TextToSpeechProvider::AmazonWebServices {
-                        voice: aws::VoiceKind::from_str(voice)?,
-                    })
-                }
+                "GC" | "gc" => return parse_gcloud_provider(voice),
+                "AWS" | "aws" => return parse_aws_provider(voice),
                 "WIN" | "win" => {
                     return Ok(TextToSpeechProvider::Windows {
                         voice: Some(voice.to_string()),
@@ -71,9 +310,7 @@ impl FromStr for TextToSpeechProvider {
                 if voice == "WIN" || voice == "win" {
                     return Ok(TextToSpeechProvider::Windows { voice: None });
                 } else {
-                    return Ok(TextToSpeechProvider::GoogleCloud {
-                        voice: gcloud::VoiceKind::from_str(voice)?,
-                    });
+                    return parse_gcloud_provider(voice);
                 }
             }
             _ => {}
@@ -84,12 +321,109 @@ impl FromStr for TextToSpeechProvider {
     }
 }
 
+#[cfg(feature = "gcloud")]
+fn parse_gcloud_provider(voice: &str) -> Result<TextToSpeechProvider, anyhow::Error> {
+    let voice = gcloud::VoiceKind::from_str(voice).map_err(|_| {
+        unknown_voice_error(
+            voice,
+            gcloud::VoiceKind::ALL
+                .iter()
+                .map(|v| (v.name(), format!("GC:{}", v.name()))),
+        )
+    })?;
+    Ok(TextToSpeechProvider::GoogleCloud { voice })
+}
+
+#[cfg(not(feature = "gcloud"))]
+fn parse_gcloud_provider(voice: &str) -> Result<TextToSpeechProvider, anyhow::Error> {
+    Err(compiled_out_error("Google Cloud", "gcloud", voice))
+}
+
+#[cfg(feature = "aws")]
+fn parse_aws_provider(voice: &str) -> Result<TextToSpeechProvider, anyhow::Error> {
+    // an optional modifier selects the engine, e.g. `AWS:Matthew NEURAL`
+    let mut parts = voice.split_whitespace();
+    let voice = parts.next().unwrap_or("");
+    let engine = match parts.next() {
+        None => aws::Engine::Standard,
+        Some(modifier) if modifier.eq_ignore_ascii_case("NEURAL") => aws::Engine::Neural,
+        Some(modifier) => {
+            return Err(anyhow!(
+                "unknown modifier `{}` for AWS voice {} (expected NEURAL)",
+                modifier,
+                voice
+            ))
+        }
+    };
+    let voice = aws::VoiceKind::from_str(voice).map_err(|_| {
+        unknown_voice_error(
+            voice,
+            aws::VoiceKind::ALL
+                .iter()
+                .map(|v| (v.to_string(), format!("AWS:{}", v.to_string()))),
+        )
+    })?;
+    Ok(TextToSpeechProvider::AmazonWebServices {
+        voice,
+        engine: engine.for_voice(voice),
+    })
+}
+
+#[cfg(not(feature = "aws"))]
+fn parse_aws_provider(voice: &str) -> Result<TextToSpeechProvider, anyhow::Error> {
+    Err(compiled_out_error("Amazon Web Services", "aws", voice))
+}
+
+/// Creates an error for a voice of a TTS provider that DATIS has been compiled without.
+#[cfg(not(all(feature = "gcloud", feature = "aws")))]
+fn compiled_out_error(provider: &str, feature: &str, voice: &str) -> anyhow::Error {
+    anyhow!(
+        "cannot use voice `{}`, this build of DATIS doesn't support {} TTS (compiled without the \
+         `{}` feature)",
+        voice,
+        provider,
+        feature
+    )
+}
+
+/// The maximum edit distance of a valid voice to an unknown one to be suggested as a correction.
+#[cfg(any(feature = "gcloud", feature = "aws"))]
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Creates an error for an unknown voice, which suggests the closest valid voices. The
+/// `candidates` are the provider's voice names and their full identifiers.
+#[cfg(any(feature = "gcloud", feature = "aws"))]
+fn unknown_voice_error(
+    voice: &str,
+    candidates: impl Iterator<Item = (String, String)>,
+) -> anyhow::Error {
+    let voice_lower = voice.to_lowercase();
+    let mut suggestions: Vec<(usize, String)> = candidates
+        .map(|(name, id)| (levenshtein(&voice_lower, &name.to_lowercase()), id))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .collect();
+    suggestions.sort_by_key(|(distance, _)| *distance);
+
+    if suggestions.is_empty() {
+        anyhow!("unknown voice `{}`", voice)
+    } else {
+        let suggestions = suggestions
+            .into_iter()
+            .take(3)
+            .map(|(_, id)| format!("`{}`", id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        anyhow!("unknown voice `{}`, did you mean {}?", voice, suggestions)
+    }
+}
+
 #[cfg(test)]
 mod test {
+    #[cfg(all(feature = "gcloud", feature = "aws"))]
     mod tts_provider_from_str {
         use std::str::FromStr;
 
-        use crate::tts::{aws, gcloud, TextToSpeechProvider};
+        use crate::tts::{available_voices, aws, gcloud, TextToSpeechProvider};
 
         #[test]
         fn fallback_on_empty_string() {
@@ -127,14 +461,235 @@ mod test {
             )
         }
 
+        #[test]
+        fn available_voices_round_trip() {
+            let providers = available_voices();
+            assert_eq!(providers.len(), 3);
+
+            let gc_voices = &providers[0].voices;
+            assert_eq!(gc_voices.len(), gcloud::VoiceKind::ALL.len());
+            for (id, voice) in gc_voices.iter().zip(gcloud::VoiceKind::ALL) {
+                assert_eq!(
+                    TextToSpeechProvider::from_str(id).unwrap(),
+                    TextToSpeechProvider::GoogleCloud { voice: *voice }
+                );
+            }
+
+            let aws_voices = &providers[1].voices;
+            assert_eq!(aws_voices.len(), aws::VoiceKind::ALL.len());
+            for (id, voice) in aws_voices.iter().zip(aws::VoiceKind::ALL) {
+                match TextToSpeechProvider::from_str(id).unwrap() {
+                    TextToSpeechProvider::AmazonWebServices { voice: parsed, .. } => {
+                        assert_eq!(parsed, *voice)
+                    }
+                    tts => panic!("expected AWS voice, got {:?}", tts),
+                }
+            }
+
+            assert_eq!(
+                TextToSpeechProvider::from_str(&providers[2].voices[0]).unwrap(),
+                TextToSpeechProvider::Windows { voice: None }
+            );
+        }
+
+        #[test]
+        fn aws_neural_modifier() {
+            assert_eq!(
+                TextToSpeechProvider::from_str("AWS:Matthew NEURAL").unwrap(),
+                TextToSpeechProvider::AmazonWebServices {
+                    voice: aws::VoiceKind::Matthew,
+                    engine: aws::Engine::Neural,
+                }
+            );
+            assert_eq!(
+                TextToSpeechProvider::from_str("AWS:Matthew neural").unwrap(),
+                TextToSpeechProvider::AmazonWebServices {
+                    voice: aws::VoiceKind::Matthew,
+                    engine: aws::Engine::Neural,
+                }
+            );
+            // falls back to the standard engine for voices without a neural version
+            assert_eq!(
+                TextToSpeechProvider::from_str("AWS:Geraint NEURAL").unwrap(),
+                TextToSpeechProvider::AmazonWebServices {
+                    voice: aws::VoiceKind::Geraint,
+                    engine: aws::Engine::Standard,
+                }
+            );
+            assert!(TextToSpeechProvider::from_str("AWS:Matthew LOUD").is_err());
+        }
+
+        #[test]
+        fn suggest_near_miss_voices() {
+            let err = TextToSpeechProvider::from_str("GC:en-US-Standrd-B").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "unknown voice `en-US-Standrd-B`, did you mean `GC:en-US-Standard-B`, \
+                 `GC:en-US-Standard-C`, `GC:en-US-Standard-D`?"
+            );
+
+            let err = TextToSpeechProvider::from_str("AWS:brain").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "unknown voice `brain`, did you mean `AWS:Brian`?"
+            );
+
+            let err = TextToSpeechProvider::from_str("AWS:Optimus").unwrap_err();
+            assert_eq!(err.to_string(), "unknown voice `Optimus`");
+        }
+
         #[test]
         fn prefix_aws() {
             assert_eq!(
                 TextToSpeechProvider::from_str("AWS:Brian").unwrap(),
                 TextToSpeechProvider::AmazonWebServices {
-                    voice: aws::VoiceKind::Brian
+                    voice: aws::VoiceKind::Brian,
+                    engine: aws::Engine::Standard,
                 }
             )
         }
     }
+
+    #[cfg(not(any(feature = "gcloud", feature = "aws")))]
+    mod offline {
+        use std::str::FromStr;
+
+        use crate::tts::{available_voices, TextToSpeechProvider};
+
+        #[test]
+        fn only_offline_provider_available() {
+            let providers = available_voices();
+            assert_eq!(providers.len(), 1);
+            assert_eq!(providers[0].provider, "Windows built-in TTS");
+            assert_eq!(
+                TextToSpeechProvider::from_str(&providers[0].voices[0]).unwrap(),
+                TextToSpeechProvider::Windows { voice: None }
+            );
+            assert_eq!(
+                TextToSpeechProvider::from_str("WIN:David").unwrap(),
+                TextToSpeechProvider::Windows {
+                    voice: Some("David".to_string())
+                }
+            );
+        }
+
+        #[test]
+        fn compiled_out_providers() {
+            let err = TextToSpeechProvider::from_str("GC:en-US-Standard-C").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "cannot use voice `en-US-Standard-C`, this build of DATIS doesn't support Google \
+                 Cloud TTS (compiled without the `gcloud` feature)"
+            );
+            // voices without a prefix are Google Cloud voices
+            assert!(TextToSpeechProvider::from_str("en-US-Standard-C").is_err());
+
+            let err = TextToSpeechProvider::from_str("AWS:Brian").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "cannot use voice `Brian`, this build of DATIS doesn't support Amazon Web \
+                 Services TTS (compiled without the `aws` feature)"
+            );
+        }
+    }
+
+    mod timeout {
+        use std::time::Duration;
+
+        use tokio::time::delay_for;
+
+        use crate::tts::{with_timeout, TextToSpeechError};
+
+        #[tokio::test]
+        async fn slow_request_times_out() {
+            let timeout = Duration::from_millis(50);
+            // a mocked TTS request that takes longer than the timeout
+            let err = with_timeout(timeout, async {
+                delay_for(Duration::from_millis(500)).await;
+                Ok(vec![0i16; 16])
+            })
+            .await
+            .unwrap_err();
+
+            assert_eq!(
+                err.downcast_ref::<TextToSpeechError>(),
+                Some(&TextToSpeechError::Timeout(timeout))
+            );
+            assert!(TextToSpeechError::is_retryable(&err));
+        }
+
+        #[tokio::test]
+        async fn fast_request_succeeds() {
+            let pcm = with_timeout(Duration::from_secs(1), async { Ok(vec![1i16, 2]) })
+                .await
+                .unwrap();
+            assert_eq!(pcm, vec![1, 2]);
+
+            let err = with_timeout(Duration::from_secs(1), async {
+                Err::<(), _>(anyhow!("Gcloud TTL error"))
+            })
+            .await
+            .unwrap_err();
+            assert!(!TextToSpeechError::is_retryable(&err));
+        }
+    }
+
+    mod sample_rate {
+        use crate::tts::{win, TextToSpeechConfig};
+
+        #[test]
+        fn resample_only_unsupported_rates() {
+            let config = TextToSpeechConfig::Windows(win::WindowsConfig {
+                executable_path: None,
+                voice: None,
+            });
+            assert_eq!(config.default_sample_rate(), 16_000);
+            assert_eq!(config.synthesis_sample_rate(16_000), 16_000);
+            assert_eq!(config.synthesis_sample_rate(48_000), 16_000);
+        }
+    }
+
+    mod english_variant {
+        use crate::tts::{EnglishVariant, TextToSpeechProvider};
+
+        #[test]
+        fn from_locale() {
+            assert_eq!(EnglishVariant::from_locale("en-GB"), EnglishVariant::Gb);
+            assert_eq!(EnglishVariant::from_locale("en-GB-WLS"), EnglishVariant::Gb);
+            assert_eq!(EnglishVariant::from_locale("en-au"), EnglishVariant::Au);
+            assert_eq!(EnglishVariant::from_locale("en-US"), EnglishVariant::Us);
+            assert_eq!(EnglishVariant::from_locale("en-IN"), EnglishVariant::Us);
+        }
+
+        #[test]
+        fn windows_voices_are_us() {
+            let provider = TextToSpeechProvider::Windows {
+                voice: Some("Microsoft Hazel Desktop".to_string()),
+            };
+            assert_eq!(provider.english_variant(), EnglishVariant::Us);
+        }
+
+        #[cfg(feature = "aws")]
+        #[test]
+        fn aws_voices() {
+            use crate::tts::aws;
+
+            let provider = |voice| TextToSpeechProvider::AmazonWebServices {
+                voice,
+                engine: aws::Engine::Standard,
+            };
+            assert_eq!(
+                provider(aws::VoiceKind::Brian).english_variant(),
+                EnglishVariant::Gb
+            );
+            assert_eq!(
+                provider(aws::VoiceKind::Russell).english_variant(),
+                EnglishVariant::Au
+            );
+            assert_eq!(
+                provider(aws::VoiceKind::Joanna).english_variant(),
+                EnglishVariant::Us
+            );
+        }
+    }
 }
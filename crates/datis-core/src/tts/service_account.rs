@@ -0,0 +1,273 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use tokio::sync::Mutex;
+
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+/// The lifetime of the signed assertion exchanged for an access token (the maximum Google
+/// accepts).
+const ASSERTION_LIFETIME: Duration = Duration::from_secs(3600);
+/// Access tokens are refreshed this long before they expire, so that they don't expire while a
+/// request is in flight.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Google Cloud service account credentials, used to mint short-lived OAuth access tokens, which
+/// are cached and refreshed shortly before they expire.
+pub struct ServiceAccount {
+    client_email: String,
+    token_uri: String,
+    key: EncodingKey,
+    token: Mutex<Option<AccessToken>>,
+}
+
+struct AccessToken {
+    value: String,
+    expires_at: Instant,
+}
+
+/// The relevant fields of a service account's JSON key file.
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    #[serde(rename = "type")]
+    kind: String,
+    client_email: String,
+    private_key: String,
+    token_uri: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+impl ServiceAccount {
+    /// Reads the credentials from the content of a service account's JSON key file.
+    pub fn from_json(json: &str) -> Result<Self, anyhow::Error> {
+        let key: ServiceAccountKey = serde_json::from_str(json)
+            .map_err(|err| anyhow!("invalid Google Cloud service account key: {}", err))?;
+        if key.kind != "service_account" {
+            return Err(anyhow!(
+                "expected Google Cloud credentials of type `service_account`, got `{}`",
+                key.kind
+            ));
+        }
+
+        Ok(ServiceAccount {
+            key: EncodingKey::from_rsa_pem(key.private_key.as_bytes()).map_err(|err| {
+                anyhow!(
+                    "invalid private key of service account {}: {}",
+                    key.client_email,
+                    err
+                )
+            })?,
+            client_email: key.client_email,
+            token_uri: key
+                .token_uri
+                .unwrap_or_else(|| DEFAULT_TOKEN_URI.to_string()),
+            token: Mutex::new(None),
+        })
+    }
+
+    pub fn client_email(&self) -> &str {
+        &self.client_email
+    }
+
+    /// Returns a valid access token, requesting a new one if there is none yet or if the current
+    /// one is about to expire.
+    pub async fn access_token(&self, client: &reqwest::Client) -> Result<String, anyhow::Error> {
+        let mut token = self.token.lock().await;
+        if let Some(ref token) = *token {
+            if token.expires_at > Instant::now() + REFRESH_MARGIN {
+                return Ok(token.value.clone());
+            }
+        }
+
+        debug!(
+            "Requesting a new Google Cloud access token for {}",
+            self.client_email
+        );
+        let new_token = self.request_token(client).await?;
+        let value = new_token.value.clone();
+        *token = Some(new_token);
+        Ok(value)
+    }
+
+    async fn request_token(&self, client: &reqwest::Client) -> Result<AccessToken, anyhow::Error> {
+        let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let claims = Claims {
+            iss: &self.client_email,
+            scope: SCOPE,
+            aud: &self.token_uri,
+            iat,
+            exp: iat + ASSERTION_LIFETIME.as_secs(),
+        };
+        let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key)?;
+
+        let requested_at = Instant::now();
+        let res = client
+            .post(&self.token_uri)
+            .form(&[("grant_type", GRANT_TYPE), ("assertion", &assertion)])
+            .send()
+            .await?;
+        if !res.status().is_success() {
+            let err = res.text().await?;
+            return Err(anyhow!(
+                "failed to obtain a Google Cloud access token for {}: {}",
+                self.client_email,
+                err
+            ));
+        }
+
+        let data: TokenResponse = res.json().await?;
+        Ok(AccessToken {
+            value: data.access_token,
+            expires_at: requested_at + Duration::from_secs(data.expires_in),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::thread;
+
+    use super::ServiceAccount;
+
+    /// A throwaway RSA key, only used to sign the tokens requested by the tests (allowlisted for
+    /// secret scanners in `.gitleaks.toml`).
+    const TEST_KEY: &str = include_str!("dummy_service_account_key.pem");
+
+    fn service_account(token_uri: &str) -> ServiceAccount {
+        let json = serde_json::json!({
+            "type": "service_account",
+            "client_email": "datis@example.iam.gserviceaccount.com",
+            "private_key": TEST_KEY,
+            "token_uri": token_uri,
+        });
+        ServiceAccount::from_json(&json.to_string()).unwrap()
+    }
+
+    /// A mocked token endpoint, that responds with the given `(token, expires_in)` pairs (one per
+    /// request) and returns the received requests once all responses have been sent.
+    fn token_endpoint(
+        tokens: Vec<(&'static str, u64)>,
+    ) -> (SocketAddr, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (token, expires_in) in tokens {
+                let (mut stream, _) = listener.accept().unwrap();
+                let req = read_request(&mut stream);
+                requests.push(req);
+
+                let body = format!(
+                    r#"{{"access_token":"{}","expires_in":{},"token_type":"Bearer"}}"#,
+                    token, expires_in
+                );
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                     content-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+            requests
+        });
+        (addr, handle)
+    }
+
+    /// Reads the request head and its body (as announced by its `content-length`).
+    fn read_request(stream: &mut TcpStream) -> String {
+        let mut req = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            req.extend_from_slice(&buf[..n]);
+
+            let text = String::from_utf8_lossy(&req).to_string();
+            if let Some(head_len) = text.find("\r\n\r\n") {
+                let content_length = text[..head_len]
+                    .lines()
+                    .filter_map(|line| {
+                        let mut parts = line.splitn(2, ':');
+                        let name = parts.next()?;
+                        let value = parts.next()?;
+                        if name.eq_ignore_ascii_case("content-length") {
+                            value.trim().parse::<usize>().ok()
+                        } else {
+                            None
+                        }
+                    })
+                    .next()
+                    .unwrap_or(0);
+                if req.len() >= head_len + 4 + content_length {
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&req).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_reuse_valid_token() {
+        let (addr, handle) = token_endpoint(vec![("token-1", 3600)]);
+        let account = service_account(&format!("http://{}/token", addr));
+        let client = reqwest::Client::new();
+
+        assert_eq!(account.access_token(&client).await.unwrap(), "token-1");
+        assert_eq!(account.access_token(&client).await.unwrap(), "token-1");
+
+        let requests = handle.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("POST /token HTTP/1.1\r\n"));
+        assert!(requests[0]
+            .contains("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer"));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_expiring_token() {
+        // the first token expires within the refresh margin, so it has to be refreshed before it
+        // is used for the next synthesis request
+        let (addr, handle) = token_endpoint(vec![("token-1", 30), ("token-2", 3600)]);
+        let account = service_account(&format!("http://{}/token", addr));
+        let client = reqwest::Client::new();
+
+        assert_eq!(account.access_token(&client).await.unwrap(), "token-1");
+        assert_eq!(account.access_token(&client).await.unwrap(), "token-2");
+        assert_eq!(account.access_token(&client).await.unwrap(), "token-2");
+
+        assert_eq!(handle.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_service_account() {
+        assert!(ServiceAccount::from_json("{}").is_err());
+        assert!(ServiceAccount::from_json(
+            r#"{"type":"authorized_user","client_email":"a","private_key":"b"}"#
+        )
+        .is_err());
+        assert!(ServiceAccount::from_json(
+            r#"{"type":"service_account","client_email":"a","private_key":"b"}"#
+        )
+        .is_err());
+    }
+}
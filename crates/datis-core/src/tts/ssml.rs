@@ -0,0 +1,61 @@
+/// Checks that a hand-written SSML document (e.g. of a raw SSML broadcast) is well-formed, since
+/// the TTS providers fail the whole synthesis otherwise. Returns the document's plain text.
+pub fn validate(ssml: &str) -> Result<String, anyhow::Error> {
+    let doc = roxmltree::Document::parse(ssml).map_err(|err| anyhow!("malformed SSML: {}", err))?;
+
+    let root = doc.root_element();
+    if root.tag_name().name() != "speak" {
+        return Err(anyhow!(
+            "malformed SSML: expected a <speak> root element, got <{}>",
+            root.tag_name().name()
+        ));
+    }
+
+    // the parser accepts documents that end before the root element is closed
+    let is_closed = match ssml.trim_end().strip_suffix('>') {
+        Some(end) => end.ends_with('/') || end.trim_end().ends_with("</speak"),
+        None => false,
+    };
+    if !is_closed {
+        return Err(anyhow!("malformed SSML: unclosed <speak> root element"));
+    }
+
+    let text = root
+        .descendants()
+        .filter(|node| node.is_text())
+        .filter_map(|node| node.text())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::validate;
+
+    #[test]
+    fn test_valid_ssml() {
+        assert_eq!(
+            validate(
+                r#"<speak>Attention all aircraft. <break time="1s"/><emphasis level="strong">Range</emphasis> is hot &amp; active.</speak>"#
+            )
+            .unwrap(),
+            "Attention all aircraft. Range is hot & active."
+        );
+    }
+
+    #[test]
+    fn test_malformed_ssml() {
+        assert!(validate("<speak>Range is hot")
+            .unwrap_err()
+            .to_string()
+            .starts_with("malformed SSML: "));
+        assert!(validate("<speak>Range is <b>hot</speak>").is_err());
+        assert!(validate("<speak>Café").is_err());
+        assert!(validate("<speak>Tom & Jerry</speak>").is_err());
+        assert_eq!(
+            validate("<p>Range is hot</p>").unwrap_err().to_string(),
+            "malformed SSML: expected a <speak> root element, got <p>"
+        );
+    }
+}
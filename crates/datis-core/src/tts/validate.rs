@@ -0,0 +1,383 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+
+#[cfg(feature = "aws")]
+use super::aws;
+#[cfg(feature = "gcloud")]
+use super::gcloud;
+use super::TextToSpeechConfig;
+
+/// The TTS providers whose credentials are validated at startup.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Provider {
+    GoogleCloud,
+    AmazonWebServices,
+    Windows,
+}
+
+/// A TTS provider that failed the credential validation, and the stations that will be silent
+/// because they depend on it.
+#[derive(Debug, PartialEq)]
+pub struct InvalidCredentials {
+    pub provider: Provider,
+    pub error: String,
+    pub stations: Vec<String>,
+}
+
+impl TextToSpeechConfig {
+    pub fn provider(&self) -> Provider {
+        match self {
+            #[cfg(feature = "gcloud")]
+            TextToSpeechConfig::GoogleCloud(_) => Provider::GoogleCloud,
+            #[cfg(feature = "aws")]
+            TextToSpeechConfig::AmazonWebServices(_) => Provider::AmazonWebServices,
+            TextToSpeechConfig::Windows(_) => Provider::Windows,
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Provider::GoogleCloud => "Google Cloud",
+            Provider::AmazonWebServices => "Amazon Web Services",
+            Provider::Windows => "Windows built-in TTS",
+        })
+    }
+}
+
+/// The values of the `<prosody>` attributes a TTS provider accepts. The rate is in percent of the
+/// voice's default rate, the pitch is a change relative to the voice's default pitch.
+struct ProsodyLimits {
+    rate: (f64, f64),
+    /// The pitch change in percent, e.g. `+10%`.
+    pitch: Option<(f64, f64)>,
+    /// The pitch change in semitones, e.g. `-2st`.
+    pitch_semitones: Option<(f64, f64)>,
+}
+
+/// The keywords of the `rate` and `pitch` attributes, which all providers accept.
+const PROSODY_KEYWORDS: &[&str] = &[
+    "x-slow", "slow", "medium", "fast", "x-fast", "x-low", "low", "high", "x-high", "default",
+];
+
+impl Provider {
+    /// The Windows built-in TTS doesn't reject any prosody, so it isn't clamped.
+    fn prosody_limits(self) -> Option<ProsodyLimits> {
+        match self {
+            // a speaking rate of 0.25 to 4.0 and a pitch of -20st to +20st (-68.5% to +217.5%)
+            Provider::GoogleCloud => Some(ProsodyLimits {
+                rate: (25.0, 400.0),
+                pitch: Some((-68.5, 217.5)),
+                pitch_semitones: Some((-20.0, 20.0)),
+            }),
+            // Polly doesn't support semitones
+            Provider::AmazonWebServices => Some(ProsodyLimits {
+                rate: (20.0, 200.0),
+                pitch: Some((-33.3, 50.0)),
+                pitch_semitones: None,
+            }),
+            Provider::Windows => None,
+        }
+    }
+}
+
+/// Clamps the `rate` and `pitch` of the `<prosody>` elements of a hand-written SSML document (e.g.
+/// of a raw SSML broadcast) into the ranges the provider accepts, since the provider fails the
+/// whole synthesis for values out of its range. Values the provider doesn't support at all (e.g.
+/// semitones for AWS) are replaced with `medium`. Returns the SSML to synthesize, together with a
+/// warning for each changed value.
+pub fn clamp_prosody(ssml: &str, provider: Provider) -> (Cow<'_, str>, Vec<String>) {
+    let limits = match provider.prosody_limits() {
+        Some(limits) if ssml.contains("<prosody") => limits,
+        _ => return (Cow::Borrowed(ssml), Vec::new()),
+    };
+    let doc = match roxmltree::Document::parse(ssml) {
+        Ok(doc) => doc,
+        // already rejected when the broadcast was read
+        Err(_) => return (Cow::Borrowed(ssml), Vec::new()),
+    };
+
+    let mut replacements = Vec::new();
+    let mut warnings = Vec::new();
+    let prosody = doc
+        .descendants()
+        .filter(|node| node.tag_name().name() == "prosody");
+    for node in prosody {
+        for attr in node.attributes() {
+            if attr.name() != "rate" && attr.name() != "pitch" {
+                continue;
+            }
+            match clamp_prosody_value(attr.name(), attr.value(), &limits) {
+                Some(Ok(value)) => {
+                    warnings.push(format!(
+                        "clamped the SSML prosody {} `{}` to `{}` for {}",
+                        attr.name(),
+                        attr.value(),
+                        value,
+                        provider
+                    ));
+                    replacements.push((attr.value_range(), value));
+                }
+                Some(Err(value)) => {
+                    warnings.push(format!(
+                        "replaced the SSML prosody {} `{}`, which isn't supported by {}, with `{}`",
+                        attr.name(),
+                        attr.value(),
+                        provider,
+                        value
+                    ));
+                    replacements.push((attr.value_range(), value));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if replacements.is_empty() {
+        return (Cow::Borrowed(ssml), warnings);
+    }
+
+    let mut clamped = String::with_capacity(ssml.len());
+    let mut pos = 0;
+    for (range, value) in replacements {
+        clamped += &ssml[pos..range.start];
+        clamped += &value;
+        pos = range.end;
+    }
+    clamped += &ssml[pos..];
+    (Cow::Owned(clamped), warnings)
+}
+
+/// Returns `None` if the value is within the limits, the clamped value if it isn't, or an error
+/// with the value to use instead if the provider doesn't support the value at all.
+fn clamp_prosody_value(
+    attr: &str,
+    value: &str,
+    limits: &ProsodyLimits,
+) -> Option<Result<String, String>> {
+    let value = value.trim();
+    if PROSODY_KEYWORDS.contains(&value) {
+        return None;
+    }
+
+    let (number, unit) = if let Some(number) = value.strip_suffix('%') {
+        (number, "%")
+    } else if let Some(number) = value.strip_suffix("st") {
+        (number, "st")
+    } else {
+        (value, "")
+    };
+    let number = match number.parse::<f64>() {
+        Ok(number) if number.is_finite() => number,
+        _ => return Some(Err("medium".to_string())),
+    };
+    let (number, range) = match (attr, unit) {
+        ("rate", "%") => (number, Some(limits.rate)),
+        // a rate without a unit is a multiple of the default rate
+        ("rate", "") => (number * 100.0, Some(limits.rate)),
+        ("pitch", "%") => (number, limits.pitch),
+        ("pitch", "st") => (number, limits.pitch_semitones),
+        _ => (number, None),
+    };
+    let (min, max) = match range {
+        Some(range) => range,
+        None => return Some(Err("medium".to_string())),
+    };
+
+    if number >= min && number <= max {
+        None
+    } else if attr == "rate" {
+        Some(Ok(format!("{}%", number.max(min).min(max))))
+    } else {
+        Some(Ok(format!("{:+}{}", number.max(min).min(max), unit)))
+    }
+}
+
+/// Does a lightweight check of the provider's credentials, without synthesizing anything. The
+/// Windows built-in TTS doesn't need credentials.
+pub async fn validate_credentials(config: TextToSpeechConfig) -> Result<(), anyhow::Error> {
+    match config {
+        #[cfg(feature = "gcloud")]
+        TextToSpeechConfig::GoogleCloud(ref config) => gcloud::validate_credentials(config).await,
+        #[cfg(feature = "aws")]
+        TextToSpeechConfig::AmazonWebServices(ref config) => {
+            aws::validate_credentials(config).await
+        }
+        TextToSpeechConfig::Windows(_) => Ok(()),
+    }
+}
+
+/// Validates the credentials of each provider used by the given `(station name, TTS config)`
+/// pairs (once per provider, since all stations share the same credentials) and returns the
+/// providers that failed the validation together with the stations depending on them.
+pub async fn validate_stations<F, Fut>(
+    stations: &[(String, TextToSpeechConfig)],
+    validate: F,
+) -> Vec<InvalidCredentials>
+where
+    F: Fn(TextToSpeechConfig) -> Fut,
+    Fut: Future<Output = Result<(), anyhow::Error>>,
+{
+    let mut checked = Vec::new();
+    let mut invalid = Vec::new();
+
+    for (_, config) in stations {
+        let provider = config.provider();
+        if checked.contains(&provider) {
+            continue;
+        }
+        checked.push(provider);
+
+        match validate(config.clone()).await {
+            Ok(()) => info!("{} TTS credentials are valid", provider),
+            Err(err) => invalid.push(InvalidCredentials {
+                provider,
+                error: err.to_string(),
+                stations: stations
+                    .iter()
+                    .filter(|(_, config)| config.provider() == provider)
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            }),
+        }
+    }
+
+    invalid
+}
+
+#[cfg(all(test, feature = "gcloud"))]
+mod test {
+    use std::time::Duration;
+
+    use super::{clamp_prosody, validate_stations, InvalidCredentials, Provider};
+    use crate::tts::{gcloud, win, TextToSpeechConfig};
+
+    fn gcloud_config() -> TextToSpeechConfig {
+        TextToSpeechConfig::GoogleCloud(gcloud::GoogleCloudConfig {
+            voice: gcloud::VoiceKind::StandardC,
+            credentials: gcloud::Credentials::ApiKey("invalid".to_string()),
+            region: None,
+            proxy: None,
+            timeout: Duration::from_secs(10),
+        })
+    }
+
+    fn win_config() -> TextToSpeechConfig {
+        TextToSpeechConfig::Windows(win::WindowsConfig {
+            executable_path: None,
+            voice: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_invalid_credentials_mark_stations() {
+        let stations = vec![
+            ("Batumi".to_string(), gcloud_config()),
+            ("Kutaisi".to_string(), win_config()),
+            ("Senaki".to_string(), gcloud_config()),
+        ];
+
+        let invalid = validate_stations(&stations, |config| async move {
+            match config {
+                TextToSpeechConfig::GoogleCloud(_) => Err(anyhow!("API key not valid")),
+                _ => Ok(()),
+            }
+        })
+        .await;
+
+        assert_eq!(
+            invalid,
+            vec![InvalidCredentials {
+                provider: Provider::GoogleCloud,
+                error: "API key not valid".to_string(),
+                stations: vec!["Batumi".to_string(), "Senaki".to_string()],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_each_provider_once() {
+        let stations = vec![
+            ("Batumi".to_string(), gcloud_config()),
+            ("Senaki".to_string(), gcloud_config()),
+            ("Kutaisi".to_string(), win_config()),
+        ];
+
+        let validated = std::sync::Mutex::new(Vec::new());
+        let invalid = validate_stations(&stations, |config| {
+            validated.lock().unwrap().push(config.provider());
+            async { Ok(()) }
+        })
+        .await;
+
+        assert!(invalid.is_empty());
+        assert_eq!(
+            *validated.lock().unwrap(),
+            vec![Provider::GoogleCloud, Provider::Windows]
+        );
+    }
+
+    #[test]
+    fn test_clamp_prosody_gcloud() {
+        let (ssml, warnings) = clamp_prosody(
+            r#"<speak><prosody rate="500%" pitch="-25st">Range is hot.</prosody> <prosody rate="slow" pitch="+50%">Stay clear.</prosody></speak>"#,
+            Provider::GoogleCloud,
+        );
+        assert_eq!(
+            ssml,
+            r#"<speak><prosody rate="400%" pitch="-20st">Range is hot.</prosody> <prosody rate="slow" pitch="+50%">Stay clear.</prosody></speak>"#
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "clamped the SSML prosody rate `500%` to `400%` for Google Cloud".to_string(),
+                "clamped the SSML prosody pitch `-25st` to `-20st` for Google Cloud".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clamp_prosody_aws() {
+        let (ssml, warnings) = clamp_prosody(
+            r#"<speak><prosody rate="300%" pitch="+60%">Range is hot.</prosody> <prosody rate='0.1' pitch="-2st">Stay clear.</prosody></speak>"#,
+            Provider::AmazonWebServices,
+        );
+        assert_eq!(
+            ssml,
+            r#"<speak><prosody rate="200%" pitch="+50%">Range is hot.</prosody> <prosody rate='20%' pitch="medium">Stay clear.</prosody></speak>"#
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "clamped the SSML prosody rate `300%` to `200%` for Amazon Web Services"
+                    .to_string(),
+                "clamped the SSML prosody pitch `+60%` to `+50%` for Amazon Web Services"
+                    .to_string(),
+                "clamped the SSML prosody rate `0.1` to `20%` for Amazon Web Services".to_string(),
+                "replaced the SSML prosody pitch `-2st`, which isn't supported by Amazon Web \
+                 Services, with `medium`"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clamp_prosody_within_limits() {
+        let ssml = r#"<speak><prosody rate="150%" pitch="-10%">Range is hot.</prosody></speak>"#;
+        for provider in &[
+            Provider::GoogleCloud,
+            Provider::AmazonWebServices,
+            Provider::Windows,
+        ] {
+            let (clamped, warnings) = clamp_prosody(ssml, *provider);
+            assert_eq!(clamped, ssml);
+            assert!(warnings.is_empty());
+        }
+
+        // the Windows built-in TTS isn't clamped
+        let ssml = r#"<speak><prosody rate="500%">Range is hot.</prosody></speak>"#;
+        assert_eq!(clamp_prosody(ssml, Provider::Windows).0, ssml);
+    }
+}
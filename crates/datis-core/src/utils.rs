@@ -1,3 +1,7 @@
+use std::mem;
+
+use crate::tts::EnglishVariant;
+
 pub fn round(n: f64, max_decimal_places: i32) -> f64 {
     if max_decimal_places == 0 {
         return n.round();
@@ -27,6 +31,77 @@ where
         .join(" ")
 }
 
+static NUMBER_WORDS: &[&str] = &[
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+static TENS_WORDS: &[&str] = &[
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+/// Spells out a number in words, e.g. `one thousand two hundred fifty` in en-US. en-GB and en-AU
+/// join the tens and units to the hundreds (or a larger unit) with `and`, e.g. `one thousand two
+/// hundred and fifty` and `one thousand and fifty`.
+pub fn number_to_words(n: u32, variant: EnglishVariant) -> String {
+    if n == 0 {
+        return NUMBER_WORDS[0].to_string();
+    }
+
+    let mut words: Vec<String> = Vec::new();
+    let scales = [
+        (1_000_000_000, Some("billion")),
+        (1_000_000, Some("million")),
+        (1_000, Some("thousand")),
+        (1, None),
+    ];
+    for &(scale, name) in &scales {
+        let group = (n / scale % 1_000) as usize;
+        if group == 0 {
+            continue;
+        }
+
+        let (hundreds, rest) = (group / 100, group % 100);
+        if hundreds > 0 {
+            words.push(NUMBER_WORDS[hundreds].to_string());
+            words.push("hundred".to_string());
+        }
+        if rest > 0 {
+            if variant != EnglishVariant::Us && (hundreds > 0 || (scale == 1 && !words.is_empty()))
+            {
+                words.push("and".to_string());
+            }
+            words.push(match rest {
+                0..=19 => NUMBER_WORDS[rest].to_string(),
+                _ if rest % 10 == 0 => TENS_WORDS[rest / 10].to_string(),
+                _ => format!("{}-{}", TENS_WORDS[rest / 10], NUMBER_WORDS[rest % 10]),
+            });
+        }
+        if let Some(name) = name {
+            words.push(name.to_string());
+        }
+    }
+
+    words.join(" ")
+}
+
 pub fn m_to_nm(n: f64) -> f64 {
     n * 0.000_539_957
 }
@@ -34,3 +109,398 @@ pub fn m_to_nm(n: f64) -> f64 {
 pub fn m_to_ft(n: f64) -> f64 {
     n * 3.28084
 }
+
+/// Splits the text into segments of at most `max_len` bytes. Segments are split at sentence
+/// boundaries, only sentences that are longer than `max_len` on their own are split between words.
+pub fn split_into_segments(text: &str, max_len: usize) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+
+    for sentence in split_sentences(text) {
+        if !current.is_empty() && current.len() + 1 + sentence.len() > max_len {
+            segments.push(mem::take(&mut current));
+        }
+
+        if sentence.len() > max_len {
+            for word in sentence.split_whitespace() {
+                if !current.is_empty() && current.len() + 1 + word.len() > max_len {
+                    segments.push(mem::take(&mut current));
+                }
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(word);
+            }
+        } else {
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(sentence);
+        }
+    }
+
+    if !current.is_empty() {
+        segments.push(current);
+    }
+
+    segments
+}
+
+/// Truncates the text to at most `max_len` characters. The text is cut after the last sentence
+/// that fits, or between words if not even its first sentence fits. Returns `None` if the text
+/// doesn't exceed `max_len`.
+pub fn truncate_at_sentence(text: &str, max_len: usize) -> Option<&str> {
+    // the byte index of the first character that exceeds the limit
+    let (limit, _) = text.char_indices().nth(max_len)?;
+    let end = sentence_ends(text)
+        .take_while(|end| *end <= limit)
+        .last()
+        .or_else(|| text[..limit].rfind(char::is_whitespace))
+        .unwrap_or(limit);
+    Some(text[..end].trim_end())
+}
+
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+
+    for end in sentence_ends(text) {
+        let sentence = text[start..end].trim();
+        if !sentence.is_empty() {
+            sentences.push(sentence);
+        }
+        start = end;
+    }
+
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+
+    sentences
+}
+
+/// Returns the byte indices right after the punctuation that ends each sentence of the text.
+fn sentence_ends(text: &str) -> impl Iterator<Item = usize> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        while let Some((i, c)) = chars.next() {
+            if !matches!(c, '.' | '!' | '?') {
+                continue;
+            }
+
+            // only treat the punctuation as the end of a sentence if it is followed by a
+            // whitespace (to not split decimal numbers like 251.5)
+            let is_end = chars
+                .peek()
+                .map(|(_, next)| next.is_whitespace())
+                .unwrap_or(true);
+            if is_end {
+                return Some(i + c.len_utf8());
+            }
+        }
+        None
+    })
+}
+
+/// Returns the Levenshtein (edit) distance between both strings, which is the number of single
+/// character insertions, deletions and substitutions to get from one to the other.
+#[cfg(any(feature = "gcloud", feature = "aws"))]
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Escapes the characters of a user provided text that have a special meaning in SSML, so that
+/// the text can be embedded into an SSML document.
+pub fn escape_ssml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Replaces accented latin letters with their closest ASCII equivalent (e.g. `Évreux` ->
+/// `Evreux`) and typographic apostrophes with plain ones. Other characters are left untouched.
+pub fn transliterate(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        let replacement = match c {
+            'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+            'Æ' => "AE",
+            'æ' => "ae",
+            'Ç' | 'Ć' | 'Č' => "C",
+            'ç' | 'ć' | 'č' => "c",
+            'Ď' | 'Đ' => "D",
+            'ď' | 'đ' => "d",
+            'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+            'Ğ' => "G",
+            'ğ' => "g",
+            'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'İ' => "I",
+            'ì' | 'í' | 'î' | 'ï' | 'ī' | 'ı' => "i",
+            'Ł' => "L",
+            'ł' => "l",
+            'Ñ' | 'Ń' | 'Ň' => "N",
+            'ñ' | 'ń' | 'ň' => "n",
+            'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+            'Œ' => "OE",
+            'œ' => "oe",
+            'Ř' => "R",
+            'ř' => "r",
+            'Ś' | 'Ş' | 'Š' => "S",
+            'ś' | 'ş' | 'š' => "s",
+            'ß' => "ss",
+            'Ţ' | 'Ť' => "T",
+            'ţ' | 'ť' => "t",
+            'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' => "U",
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+            'Ý' | 'Ÿ' => "Y",
+            'ý' | 'ÿ' => "y",
+            'Ź' | 'Ż' | 'Ž' => "Z",
+            'ź' | 'ż' | 'ž' => "z",
+            '’' | '‘' | 'ʼ' => "'",
+            c => {
+                result.push(c);
+                continue;
+            }
+        };
+        result.push_str(replacement);
+    }
+    result
+}
+
+/// Normalizes an airfield name for matching it against the names used in station configs:
+/// accents are transliterated, apostrophes and parenthesized parts are removed, any other
+/// punctuation separates words and the case is ignored, e.g. `Sde Dov (Tel Aviv)` -> `sde dov`.
+pub fn airfield_match_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+    let mut parentheses = 0usize;
+    for c in transliterate(name).chars() {
+        match c {
+            '(' => {
+                parentheses += 1;
+                key.push(' ');
+            }
+            ')' => {
+                parentheses = parentheses.saturating_sub(1);
+                key.push(' ');
+            }
+            _ if parentheses > 0 => {}
+            '\'' => {}
+            c if c.is_alphanumeric() => key.extend(c.to_lowercase()),
+            _ => key.push(' '),
+        }
+    }
+    key.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns a pronounceable form of an airfield name: accents are transliterated, abbreviations
+/// expanded (e.g. `Intl` -> `International`), apostrophes removed and parentheses turned into a
+/// pause, e.g. `Sde Dov (Tel Aviv)` -> `Sde Dov, Tel Aviv`.
+pub fn spoken_airfield_name(name: &str) -> String {
+    let mut words: Vec<String> = Vec::new();
+    for word in transliterate(name).split_whitespace() {
+        if word.starts_with('(') {
+            if let Some(previous) = words.last_mut() {
+                previous.push(',');
+            }
+        }
+        let word = word.trim_matches(|c| c == '(' || c == ')');
+        if !word.is_empty() {
+            words.push(expand_abbreviation(word).replace('\'', ""));
+        }
+    }
+    words.join(" ")
+}
+
+fn expand_abbreviation(word: &str) -> &str {
+    match word.trim_end_matches('.') {
+        "Intl" | "INTL" | "Int'l" => "International",
+        "Apt" => "Airport",
+        "AB" => "Air Base",
+        "AFB" => "Air Force Base",
+        "St" => "Saint",
+        "Mt" => "Mount",
+        "Ft" => "Fort",
+        _ => word,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escape_ssml() {
+        assert_eq!(escape_ssml("Welcome to Batumi"), "Welcome to Batumi");
+        assert_eq!(
+            escape_ssml("Tom & Jerry's <wing> \"123rd\""),
+            "Tom &amp; Jerry&apos;s &lt;wing&gt; &quot;123rd&quot;"
+        );
+    }
+
+    #[test]
+    fn test_short_text_is_a_single_segment() {
+        assert_eq!(
+            split_into_segments("Runway 13 closed. Expect delays.", 100),
+            vec!["Runway 13 closed. Expect delays.".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_number_to_words() {
+        assert_eq!(number_to_words(0, EnglishVariant::Us), "zero");
+        assert_eq!(number_to_words(13, EnglishVariant::Us), "thirteen");
+        assert_eq!(number_to_words(40, EnglishVariant::Gb), "forty");
+        assert_eq!(number_to_words(27, EnglishVariant::Gb), "twenty-seven");
+        assert_eq!(
+            number_to_words(27_500, EnglishVariant::Us),
+            "twenty-seven thousand five hundred"
+        );
+        assert_eq!(
+            number_to_words(2_000_017, EnglishVariant::Us),
+            "two million seventeen"
+        );
+    }
+
+    #[test]
+    fn test_number_to_words_variants() {
+        assert_eq!(
+            number_to_words(1_250, EnglishVariant::Us),
+            "one thousand two hundred fifty"
+        );
+        assert_eq!(
+            number_to_words(1_250, EnglishVariant::Gb),
+            "one thousand two hundred and fifty"
+        );
+        assert_eq!(
+            number_to_words(1_250, EnglishVariant::Au),
+            "one thousand two hundred and fifty"
+        );
+
+        assert_eq!(
+            number_to_words(1_050, EnglishVariant::Us),
+            "one thousand fifty"
+        );
+        assert_eq!(
+            number_to_words(1_050, EnglishVariant::Gb),
+            "one thousand and fifty"
+        );
+
+        // round numbers and numbers below 100 are the same in all variants
+        assert_eq!(
+            number_to_words(4_500, EnglishVariant::Gb),
+            number_to_words(4_500, EnglishVariant::Us)
+        );
+        assert_eq!(number_to_words(99, EnglishVariant::Au), "ninety-nine");
+    }
+
+    #[test]
+    fn test_long_text_is_split_at_sentence_boundaries() {
+        let text = "Runway 13 closed until further notice. Bird activity reported in the vicinity \
+                    of the field. Tower frequency is 251.5 today! Expect delays?";
+        assert_eq!(
+            split_into_segments(text, 60),
+            vec![
+                "Runway 13 closed until further notice.".to_string(),
+                "Bird activity reported in the vicinity of the field.".to_string(),
+                "Tower frequency is 251.5 today! Expect delays?".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlong_sentence_is_split_between_words() {
+        assert_eq!(
+            split_into_segments("one two three four five six", 10),
+            vec![
+                "one two".to_string(),
+                "three four".to_string(),
+                "five six".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncate_at_sentence() {
+        let text = "Runway 13 closed. Tower frequency is 251.5 today! Expect delays.";
+        assert_eq!(truncate_at_sentence(text, 100), None);
+        assert_eq!(truncate_at_sentence(text, text.len()), None);
+        assert_eq!(
+            truncate_at_sentence(text, 55),
+            Some("Runway 13 closed. Tower frequency is 251.5 today!")
+        );
+        assert_eq!(truncate_at_sentence(text, 30), Some("Runway 13 closed."));
+        // an overlong first sentence is cut between words
+        assert_eq!(truncate_at_sentence(text, 12), Some("Runway 13"));
+    }
+
+    #[test]
+    #[cfg(any(feature = "gcloud", feature = "aws"))]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("Brian", "Brian"), 0);
+        assert_eq!(levenshtein("Brain", "Brian"), 2);
+        assert_eq!(levenshtein("en-US-Standrd-B", "en-US-Standard-B"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_accented_airfield_name() {
+        assert_eq!(transliterate("Évreux-Fauville"), "Evreux-Fauville");
+        assert_eq!(airfield_match_key("Évreux-Fauville"), "evreux fauville");
+        assert_eq!(
+            airfield_match_key("Évreux-Fauville"),
+            airfield_match_key("Evreux Fauville")
+        );
+        assert_eq!(spoken_airfield_name("Évreux-Fauville"), "Evreux-Fauville");
+    }
+
+    #[test]
+    fn test_parenthesized_airfield_name() {
+        assert_eq!(airfield_match_key("Sde Dov (Tel Aviv)"), "sde dov");
+        assert_eq!(airfield_match_key("Sde Dov"), "sde dov");
+        assert_eq!(
+            spoken_airfield_name("Sde Dov (Tel Aviv)"),
+            "Sde Dov, Tel Aviv"
+        );
+    }
+
+    #[test]
+    fn test_airfield_name_decorations() {
+        assert_eq!(
+            airfield_match_key("Ra’s al Khaimah Intl."),
+            "ras al khaimah intl"
+        );
+        assert_eq!(
+            spoken_airfield_name("Ra’s al Khaimah Intl."),
+            "Ras al Khaimah International"
+        );
+        assert_eq!(spoken_airfield_name("Al Dhafra AB"), "Al Dhafra Air Base");
+        assert_eq!(spoken_airfield_name("Kutaisi"), "Kutaisi");
+    }
+}
@@ -11,10 +11,13 @@ extern crate anyhow;
 mod macros;
 mod mission;
 
+use std::convert::TryFrom;
 use std::ffi::{CStr, CString};
 use std::ptr;
+use std::time::Duration;
 
 use anyhow::Context;
+use datis_core::audio;
 use datis_core::rpc::{MissionRpc, Response};
 use datis_core::Datis;
 use hlua51::{Lua, LuaFunction, LuaTable};
@@ -94,8 +97,106 @@ pub extern "C" fn start(state: *mut ffi::lua_State) -> c_int {
             match mission::extract(lua).and_then(|info| {
                 let mut datis = Datis::new(info.stations)?;
                 datis.set_port(info.srs_port);
+                if !info.srs_coalition_password.is_empty() {
+                    datis.set_coalition_password(info.srs_coalition_password);
+                }
+                datis.set_srs_name_affixes(&info.srs_name_prefix, &info.srs_name_suffix);
+                if !info.srs_local_address.trim().is_empty() {
+                    if let Err(err) = datis.set_local_addr(&info.srs_local_address) {
+                        warn!("Connecting to SRS from any local address: {}", err);
+                    }
+                }
+                if let Some(interval) = info.srs_voice_ping_interval {
+                    if let Err(err) = datis.set_voice_ping_interval(Duration::from_secs(interval)) {
+                        warn!("Using the default SRS voice ping interval: {}", err);
+                    }
+                }
+                if let Some(interval) = info.srs_position_update_interval {
+                    if let Err(err) =
+                        datis.set_position_update_interval(Duration::from_secs(interval))
+                    {
+                        warn!("Using the default SRS position update interval: {}", err);
+                    }
+                }
+                if let Some(interval) = info.srs_radio_update_interval {
+                    if let Err(err) = datis.set_radio_update_interval(Duration::from_secs(interval))
+                    {
+                        warn!("Using the default SRS radio update interval: {}", err);
+                    }
+                }
+                datis.set_reconnect_on_blocked_udp(info.srs_reconnect_on_blocked_udp);
+                if let Err(err) = datis.set_udp_buffer_sizes(
+                    info.srs_udp_send_buffer_size,
+                    info.srs_udp_recv_buffer_size,
+                ) {
+                    warn!("Using the OS default UDP buffer sizes: {}", err);
+                }
+                if let Some(hop_count) = info.srs_hop_count {
+                    match u8::try_from(hop_count) {
+                        Ok(hop_count) => datis.set_hop_count(hop_count),
+                        Err(_) => warn!(
+                            "Using the default SRS hop count: {} exceeds {}",
+                            hop_count,
+                            u8::MAX
+                        ),
+                    }
+                }
+                if let Some(intensity) = info.radio_effects {
+                    if let Err(err) = datis.set_radio_effects(intensity as f32 / 100.0) {
+                        warn!("Disabling the radio effects: {}", err);
+                    }
+                }
+                if let Some(tone) = info.lead_in_tone {
+                    match audio::parse_lead_in_tone(&tone) {
+                        Ok(tone) => datis.set_lead_in_tone(tone)?,
+                        Err(err) => warn!("Ignoring the lead-in tone: {}", err),
+                    }
+                }
+                if let Some(timeout) = info.watchdog_timeout {
+                    // 0 disables the watchdog
+                    let timeout = Some(timeout)
+                        .filter(|timeout| *timeout > 0)
+                        .map(Duration::from_secs);
+                    datis.set_watchdog_timeout(timeout)?;
+                }
+                if let Some(max_len) = info.max_report_length {
+                    if let Err(err) = datis.set_max_report_length(max_len) {
+                        warn!("Using the default maximum report length: {}", err);
+                    }
+                }
+                datis.set_multiplex_stations(info.multiplex_stations);
+                if !info.voice_balancer.is_empty() {
+                    datis.set_voice_balancer(info.voice_balancer);
+                }
+                if let Some(template) = info.srs_name_template {
+                    if let Err(err) = datis.set_srs_name_template(&template) {
+                        warn!("Using the default SRS client names: {}", err);
+                    }
+                }
+                if let Some(sample_rate) = info.sample_rate {
+                    if let Err(err) = datis.set_sample_rate(sample_rate) {
+                        warn!("Using the TTS providers' default sample rates: {}", err);
+                    }
+                }
                 if !info.gcloud_key.is_empty() {
-                    datis.set_gcloud_key(info.gcloud_key);
+                    if let Err(err) = datis.set_gcloud_key(&info.gcloud_key) {
+                        error!("Invalid Google Cloud key: {}", err);
+                    }
+                }
+                if let Some(region) = info.gcloud_region {
+                    if let Err(err) = datis.set_gcloud_region(region) {
+                        warn!("Using the global Google Cloud endpoint: {}", err);
+                    }
+                }
+                if let Some(proxy) = info.proxy {
+                    if let Err(err) = datis.set_proxy(&proxy) {
+                        warn!("Ignoring proxy: {}", err);
+                    }
+                }
+                if let Some(timeout) = info.tts_timeout {
+                    if let Err(err) = datis.set_tts_timeout(Duration::from_secs(timeout)) {
+                        warn!("Using the default TTS timeout: {}", err);
+                    }
                 }
                 if !info.aws_key.is_empty()
                     && !info.aws_secret.is_empty()
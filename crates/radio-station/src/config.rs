@@ -0,0 +1,165 @@
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+/// Describes multiple stations that are run concurrently from one process, e.g.:
+///
+/// ```toml
+/// [[station]]
+/// name = "Music"
+/// path = "./music"
+/// freq = 251000000
+/// loop = true
+/// ```
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(rename = "station", default)]
+    pub stations: Vec<StationConfig>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StationConfig {
+    /// The name of the station's SRS client, also used to label its log messages.
+    pub name: String,
+    /// The audio file or the directory of audio files to play.
+    pub path: String,
+    /// The SRS frequency (in Hz).
+    #[serde(default = "default_freq")]
+    pub freq: u64,
+    #[serde(default = "default_modulation")]
+    pub modulation: String,
+    /// The host (a hostname, an IPv4 or an IPv6 address) of the SRS server.
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub lat: f64,
+    #[serde(default)]
+    pub lng: f64,
+    #[serde(default = "default_alt")]
+    pub alt: f64,
+    #[serde(rename = "loop", default)]
+    pub should_loop: bool,
+    pub coalition_password: Option<String>,
+    /// The local address (e.g. `192.168.1.10`) the SRS connections are made from.
+    pub local_address: Option<IpAddr>,
+}
+
+fn default_freq() -> u64 {
+    251_000_000
+}
+
+fn default_modulation() -> String {
+    "AM".to_string()
+}
+
+fn default_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    5002
+}
+
+fn default_alt() -> f64 {
+    8000.0
+}
+
+impl Config {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, anyhow::Error> {
+        let content = fs::read_to_string(path)?;
+        content.parse()
+    }
+}
+
+impl FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config: Config = toml::from_str(s)?;
+        if config.stations.is_empty() {
+            return Err(anyhow!("no stations configured"));
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Config, StationConfig};
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = r#"
+            [[station]]
+            name = "Music"
+            path = "./music"
+            loop = true
+
+            [[station]]
+            name = "Tower"
+            path = "./tower.ogg"
+            freq = 255000000
+            modulation = "FM"
+            host = "srs.example.com"
+            port = 5003
+            lat = 42.17
+            lng = 42.48
+            alt = 45.0
+            coalition_password = "secret"
+            local_address = "192.168.1.10"
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            config.stations,
+            vec![
+                StationConfig {
+                    name: "Music".to_string(),
+                    path: "./music".to_string(),
+                    freq: 251_000_000,
+                    modulation: "AM".to_string(),
+                    host: "127.0.0.1".to_string(),
+                    port: 5002,
+                    lat: 0.0,
+                    lng: 0.0,
+                    alt: 8000.0,
+                    should_loop: true,
+                    coalition_password: None,
+                    local_address: None,
+                },
+                StationConfig {
+                    name: "Tower".to_string(),
+                    path: "./tower.ogg".to_string(),
+                    freq: 255_000_000,
+                    modulation: "FM".to_string(),
+                    host: "srs.example.com".to_string(),
+                    port: 5003,
+                    lat: 42.17,
+                    lng: 42.48,
+                    alt: 45.0,
+                    should_loop: false,
+                    coalition_password: Some("secret".to_string()),
+                    local_address: Some("192.168.1.10".parse().unwrap()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!("".parse::<Config>().is_err());
+        assert!("[[station]]\nname = \"Music\"".parse::<Config>().is_err());
+        assert!("[[station]]\nname = \"Music\"\npath = \"a\"\nvolume = 3"
+            .parse::<Config>()
+            .is_err());
+    }
+}
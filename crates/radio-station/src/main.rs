@@ -3,27 +3,60 @@
 #[macro_use]
 extern crate log;
 
+mod config;
 mod radio_station;
+#[cfg(target_os = "windows")]
+mod service;
+mod shutdown;
 
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::anyhow;
+use config::Config;
+use futures::future;
+use log::LevelFilter;
 use radio_station::RadioStation;
+use shutdown::Shutdown;
+use tokio::signal;
 
-#[tokio::main]
-pub async fn main() -> Result<(), anyhow::Error> {
-    env_logger::Builder::new()
-        .filter_level(log::LevelFilter::Info)
-        .try_init()
-        .unwrap();
+/// The frequencies (in Hz) SRS radios can be tuned to.
+const SRS_FREQ_RANGE: RangeInclusive<u64> = 1_000_000..=1_000_000_000;
 
-    let matches = clap::App::new("dcs-radio-station")
+pub fn main() -> Result<(), anyhow::Error> {
+    let matches = app().get_matches();
+    if matches.is_present("service") {
+        #[cfg(target_os = "windows")]
+        return service::run();
+        #[cfg(not(target_os = "windows"))]
+        return Err(anyhow!("--service is only supported on Windows"));
+    }
+
+    // use the log levels of RUST_LOG (defaulting to info), with the default level replaced by a
+    // verbosity flag
+    let mut logger =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    if let Some(level) = verbosity_level(matches.occurrences_of("verbose")) {
+        logger.filter_level(level);
+    }
+    logger.try_init().unwrap();
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(run(matches, Arc::new(Shutdown::new()), true))
+}
+
+fn app() -> clap::App<'static, 'static> {
+    clap::App::new("dcs-radio-station")
         .version(env!("CARGO_PKG_VERSION"))
         .arg(
             clap::Arg::with_name("frequency")
                 .short("f")
                 .long("freq")
                 .default_value("251000000")
-                .help("Sets the SRS frequency (in Hz, e.g. 255000000 for 255MHz)")
+                .help("Sets the SRS frequency (in Hz or MHz, e.g. 255000000, 255.0 or 255MHz)")
                 .takes_value(true),
         )
 		.arg(
@@ -48,47 +81,325 @@ pub async fn main() -> Result<(), anyhow::Error> {
 				.help("Sets the SRS Port")
 				.takes_value(true),
 		)
+        .arg(
+            clap::Arg::with_name("host")
+                .long("host")
+                .alias("address")
+                .default_value("127.0.0.1")
+                .help("Sets the SRS server's host (a hostname, an IPv4 or an IPv6 address)")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("local_address")
+                .long("local-address")
+                .help("Sets the local address (e.g. 192.168.1.10) the SRS connections are made from, to use a specific network interface")
+                .takes_value(true),
+        )
+		.arg(
+			clap::Arg::with_name("coalition_password")
+				.long("coalition-password")
+				.help("Sets the password used to join a password protected SRS coalition")
+				.takes_value(true),
+		)
+        .arg(
+            clap::Arg::with_name("voice_ping_interval")
+                .long("voice-ping-interval")
+                .default_value("5")
+                .help("Sets the interval (in seconds) of the SRS voice connection pings")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("position_update_interval")
+                .long("position-update-interval")
+                .default_value("60")
+                .help("Sets the interval (in seconds) of the SRS position update checks")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("radio_update_interval")
+                .long("radio-update-interval")
+                .default_value("30")
+                .help("Sets the interval (in seconds) in which the radio info is re-sent to the SRS server")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("hop_count")
+                .long("hop-count")
+                .default_value("0")
+                .help("Sets the hop count (0-255) of the sent voice packets, for setups that relay the transmissions through retransmitters")
+                .takes_value(true),
+        )
         .arg(
             clap::Arg::with_name("loop")
                 .short("l")
                 .long("loop")
                 .help("Enables endlessly looping the audio file(s)"),
         )
+        .arg(
+            clap::Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .help("Sets the default log level to debug (-v) or trace (-vv); per-module levels of RUST_LOG still apply"),
+        )
+        .arg(
+            clap::Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .help("Runs all stations of the given TOML config file instead of a single one")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("service")
+                .long("service")
+                .help("Runs as a Windows service (logs to the event log instead of the console)"),
+        )
         .arg(
             clap::Arg::with_name("PATH")
                 .help("Sets the path audio file(s) should be read from")
-                .required(true)
+                .required_unless("config")
                 .index(1),
         )
-        .get_matches();
-
-    // Calling .unwrap() is safe here because "INPUT" is required
-    let path = matches.value_of("PATH").unwrap();
-    let should_loop = matches.is_present("loop");
-	let port = matches.value_of("port").unwrap();
-	let port = if let Ok(n) = u16::from_str(port) {
-		n
-	} else {
-		error! ("The provided Port is not a valid number");
-		return Ok(());
-	};
-	let radio_name = matches.value_of("station_name").unwrap();
-    let radio_modulation = matches.value_of("radio_modulation").unwrap();
-    let freq = matches.value_of("frequency").unwrap();
-    let freq = if let Ok(n) = u64::from_str(freq) {
+}
+
+/// Runs the stations until they have finished playing or the shutdown is triggered. In the
+/// console mode, the shutdown is triggered by Ctrl-C.
+async fn run(
+    matches: clap::ArgMatches<'static>,
+    shutdown: Arc<Shutdown>,
+    console: bool,
+) -> Result<(), anyhow::Error> {
+    let voice_ping_interval = matches.value_of("voice_ping_interval").unwrap();
+    let voice_ping_interval = if let Ok(n) = u64::from_str(voice_ping_interval) {
+        Duration::from_secs(n)
+    } else {
+        error!("The provided voice ping interval is not a valid number");
+        return Ok(());
+    };
+
+    let position_update_interval = matches.value_of("position_update_interval").unwrap();
+    let position_update_interval = if let Ok(n) = u64::from_str(position_update_interval) {
+        Duration::from_secs(n)
+    } else {
+        error!("The provided position update interval is not a valid number");
+        return Ok(());
+    };
+
+    let radio_update_interval = matches.value_of("radio_update_interval").unwrap();
+    let radio_update_interval = if let Ok(n) = u64::from_str(radio_update_interval) {
+        Duration::from_secs(n)
+    } else {
+        error!("The provided radio update interval is not a valid number");
+        return Ok(());
+    };
+
+    let hop_count = matches.value_of("hop_count").unwrap();
+    let hop_count = if let Ok(n) = u8::from_str(hop_count) {
         n
     } else {
-        error!("The provided frequency is not a valid number");
+        error!("The provided hop count is not a number from 0 to 255");
         return Ok(());
     };
 
-    let mut station = RadioStation::new(radio_name,radio_modulation);
-    station.set_frequency(freq);
-    station.set_position(0.0, 0.0, 8000.);
-    station.set_port(port);
+    // the stations to run, each with the path of its audio file(s) and whether to loop them
+    let mut stations = Vec::new();
+    if let Some(config_path) = matches.value_of("config") {
+        let config = match Config::from_file(config_path) {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Failed to read config {}: {}", config_path, err);
+                return Ok(());
+            }
+        };
+
+        for station_config in config.stations {
+            let mut station = RadioStation::new(&station_config.name, &station_config.modulation);
+            station.set_frequency(station_config.freq);
+            station.set_position(station_config.lat, station_config.lng, station_config.alt);
+            station.set_host(&station_config.host);
+            station.set_port(station_config.port);
+            if let Some(password) = &station_config.coalition_password {
+                station.set_coalition_password(password);
+            }
+            if let Some(addr) = station_config.local_address {
+                station.set_local_addr(addr);
+            }
+            stations.push((station, station_config.path, station_config.should_loop));
+        }
+    } else {
+        // Calling .unwrap() is safe here because "PATH" is required without a config
+        let path = matches.value_of("PATH").unwrap();
+        let should_loop = matches.is_present("loop");
+        let port = matches.value_of("port").unwrap();
+        let port = if let Ok(n) = u16::from_str(port) {
+            n
+        } else {
+            error!("The provided Port is not a valid number");
+            return Ok(());
+        };
+        let radio_name = matches.value_of("station_name").unwrap();
+        let radio_modulation = matches.value_of("radio_modulation").unwrap();
+        let freq = matches.value_of("frequency").unwrap();
+        let freq = match parse_frequency(freq) {
+            Ok(freq) => freq,
+            Err(err) => {
+                error!("The provided frequency is invalid: {}", err);
+                return Ok(());
+            }
+        };
+
+        let mut station = RadioStation::new(radio_name, radio_modulation);
+        station.set_frequency(freq);
+        station.set_position(0.0, 0.0, 8000.);
+        station.set_host(matches.value_of("host").unwrap());
+        station.set_port(port);
+        if let Some(password) = matches.value_of("coalition_password") {
+            station.set_coalition_password(password);
+        }
+        if let Some(addr) = matches.value_of("local_address") {
+            match IpAddr::from_str(addr.trim()) {
+                Ok(addr) => station.set_local_addr(addr),
+                Err(_) => {
+                    error!("The provided local address is not a valid IP address");
+                    return Ok(());
+                }
+            }
+        }
+        stations.push((station, path.to_string(), should_loop));
+    }
+
+    for (station, path, _) in &mut stations {
+        station.set_voice_ping_interval(voice_ping_interval);
+        station.set_position_update_interval(position_update_interval);
+        station.set_radio_update_interval(radio_update_interval);
+        station.set_hop_count(hop_count);
+
+        // fail early with a clear error instead of only after connecting to SRS
+        if let Err(err) = radio_station::read_audio_files(path.as_str()).await {
+            error!("{}: {}", station.name(), err);
+            return Ok(());
+        }
+    }
+
+    // disconnect all stations from SRS on the first Ctrl-C, so that they don't linger as ghost
+    // clients, and force exit on the second one
+    let shutdown_rxs = stations
+        .iter()
+        .map(|_| shutdown.subscribe())
+        .collect::<Vec<_>>();
+    if console {
+        tokio::spawn(async move {
+            if signal::ctrl_c().await.is_ok() {
+                info!("Shutting down ... (press Ctrl-C again to force exit)");
+                shutdown.trigger();
+            }
+            if signal::ctrl_c().await.is_ok() {
+                warn!("Forcing exit");
+                std::process::exit(1);
+            }
+        });
+    }
 
     info!("Start playing ...");
-    station.play(path, should_loop).await?;
+    let station_count = stations.len();
+    let results = future::join_all(stations.into_iter().zip(shutdown_rxs).map(
+        |((station, path, should_loop), shutdown_rx)| async move {
+            let name = station.name().to_string();
+            let result = station.play(path, should_loop, shutdown_rx).await;
+            if let Err(ref err) = result {
+                error!("{}: {:?}", name, err);
+            }
+            result
+        },
+    ))
+    .await;
+
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    if failed > 0 {
+        return Err(anyhow!("{} of {} station(s) failed", failed, station_count));
+    }
 
     Ok(())
 }
+
+/// Parses a frequency given either in Hz (e.g. `251000000`) or in MHz (e.g. `251.0`, `251` or
+/// `251MHz`) into Hz. Plain integers that would be below the SRS range as Hz are read as MHz.
+fn parse_frequency(value: &str) -> Result<u64, anyhow::Error> {
+    let value = value.trim();
+    let lower = value.to_lowercase();
+    let freq = if let Some(mhz) = lower.strip_suffix("mhz") {
+        parse_mhz(mhz.trim())?
+    } else if let Some(hz) = lower.strip_suffix("hz") {
+        u64::from_str(hz.trim()).map_err(|_| anyhow!("`{}` is not a valid frequency", value))?
+    } else if let Ok(n) = u64::from_str(&lower) {
+        if n < *SRS_FREQ_RANGE.start() {
+            n * 1_000_000
+        } else {
+            n
+        }
+    } else {
+        parse_mhz(&lower)?
+    };
+
+    if !SRS_FREQ_RANGE.contains(&freq) {
+        return Err(anyhow!(
+            "{} Hz is outside of the supported range of {}MHz to {}MHz",
+            freq,
+            SRS_FREQ_RANGE.start() / 1_000_000,
+            SRS_FREQ_RANGE.end() / 1_000_000
+        ));
+    }
+
+    Ok(freq)
+}
+
+/// Parses a frequency in MHz (e.g. `251.25`) into Hz.
+fn parse_mhz(value: &str) -> Result<u64, anyhow::Error> {
+    match f64::from_str(value) {
+        Ok(mhz) if mhz.is_finite() && mhz > 0.0 => Ok((mhz * 1_000_000.0).round() as u64),
+        _ => Err(anyhow!("`{}` is not a valid frequency", value)),
+    }
+}
+
+/// Maps the number of `-v` flags to the log level they enable, or `None` if no flag was given.
+fn verbosity_level(occurrences: u64) -> Option<LevelFilter> {
+    match occurrences {
+        0 => None,
+        1 => Some(LevelFilter::Debug),
+        _ => Some(LevelFilter::Trace),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_frequency, verbosity_level};
+    use log::LevelFilter;
+
+    #[test]
+    fn test_verbosity_level() {
+        assert_eq!(verbosity_level(0), None);
+        assert_eq!(verbosity_level(1), Some(LevelFilter::Debug));
+        assert_eq!(verbosity_level(2), Some(LevelFilter::Trace));
+        assert_eq!(verbosity_level(5), Some(LevelFilter::Trace));
+    }
+
+    #[test]
+    fn test_parse_frequency() {
+        assert_eq!(parse_frequency("251000000").unwrap(), 251_000_000);
+        assert_eq!(parse_frequency("251000000Hz").unwrap(), 251_000_000);
+        assert_eq!(parse_frequency("251.0").unwrap(), 251_000_000);
+        assert_eq!(parse_frequency("251").unwrap(), 251_000_000);
+        assert_eq!(parse_frequency("251MHz").unwrap(), 251_000_000);
+        assert_eq!(parse_frequency("251.0 MHz").unwrap(), 251_000_000);
+        assert_eq!(parse_frequency("251mhz").unwrap(), 251_000_000);
+        assert_eq!(parse_frequency("124.75").unwrap(), 124_750_000);
+        assert_eq!(parse_frequency("30.025MHz").unwrap(), 30_025_000);
+
+        assert!(parse_frequency("").is_err());
+        assert!(parse_frequency("abc").is_err());
+        assert!(parse_frequency("-251.0").is_err());
+        assert!(parse_frequency("0").is_err());
+        assert!(parse_frequency("5000MHz").is_err());
+        assert!(parse_frequency("500000Hz").is_err());
+    }
+}
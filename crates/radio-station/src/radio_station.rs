@@ -1,8 +1,9 @@
 use std::fs::File as SyncFile;
 use std::io::Cursor;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, SocketAddr};
 use std::time::{Duration, Instant};
 
+use anyhow::anyhow;
 use async_std::fs::File;
 use async_std::io::prelude::ReadExt;
 use async_std::path::{Path, PathBuf};
@@ -13,6 +14,7 @@ use ogg::reading::PacketReader;
 use ogg_metadata::{AudioMetadata, OggFormat};
 use srs::message::LatLngPosition;
 use srs::{Client, VoiceStream};
+use tokio::net::lookup_host;
 use tokio::sync::oneshot;
 use tokio::time::delay_for;
 
@@ -20,8 +22,15 @@ pub struct RadioStation {
     name: String,
     position: LatLngPosition,
     freq: u64,
+    host: String,
     port: u16,
     m: String,
+    coalition_password: Option<String>,
+    voice_ping_interval: Option<Duration>,
+    position_update_interval: Option<Duration>,
+    radio_update_interval: Option<Duration>,
+    local_addr: Option<IpAddr>,
+    hop_count: u8,
 }
 
 impl RadioStation {
@@ -30,15 +39,59 @@ impl RadioStation {
             name: name.to_string(),
             position: LatLngPosition::default(),
             freq: 251_000_000,
+            host: "127.0.0.1".to_string(),
             port: 5002,
             m: m.to_string(),
+            coalition_password: None,
+            voice_ping_interval: None,
+            position_update_interval: None,
+            radio_update_interval: None,
+            local_addr: None,
+            hop_count: 0,
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sets the host (a hostname, an IPv4 or an IPv6 address) of the SRS server.
+    pub fn set_host(&mut self, host: &str) {
+        self.host = host.to_string();
+    }
+
     pub fn set_port(&mut self, port: u16) {
         self.port = port;
     }
 
+    /// Sets the local address the connections to the SRS server are made from, to use a specific
+    /// network interface on multi-homed machines.
+    pub fn set_local_addr(&mut self, addr: IpAddr) {
+        self.local_addr = Some(addr);
+    }
+
+    pub fn set_coalition_password(&mut self, password: &str) {
+        self.coalition_password = Some(password.to_string());
+    }
+
+    pub fn set_voice_ping_interval(&mut self, interval: Duration) {
+        self.voice_ping_interval = Some(interval);
+    }
+
+    pub fn set_position_update_interval(&mut self, interval: Duration) {
+        self.position_update_interval = Some(interval);
+    }
+
+    pub fn set_radio_update_interval(&mut self, interval: Duration) {
+        self.radio_update_interval = Some(interval);
+    }
+
+    /// Sets the hop count of the sent voice packets, for setups that relay the transmissions
+    /// through retransmitters.
+    pub fn set_hop_count(&mut self, hop_count: u8) {
+        self.hop_count = hop_count;
+    }
+
     pub fn set_position(&mut self, lat: f64, lng: f64, alt: f64) {
         self.position = LatLngPosition { lat, lng, alt };
     }
@@ -46,21 +99,37 @@ impl RadioStation {
     pub fn set_frequency(&mut self, freq: u64) {
         self.freq = freq;
     }
-    
+
     pub async fn play<P: AsRef<Path>>(
         self,
         path: P,
         should_loop: bool,
+        shutdown_signal: oneshot::Receiver<()>,
     ) -> Result<(), anyhow::Error> {
         let mut client = Client::new(&self.name, self.freq, &self.m);
         client.set_position(self.position);
+        if let Some(password) = &self.coalition_password {
+            client.set_coalition_password(password);
+        }
+        if let Some(interval) = self.voice_ping_interval {
+            client.set_voice_ping_interval(interval)?;
+        }
+        if let Some(interval) = self.position_update_interval {
+            client.set_position_update_interval(interval)?;
+        }
+        if let Some(interval) = self.radio_update_interval {
+            client.set_radio_update_interval(interval)?;
+        }
+        if let Some(addr) = self.local_addr {
+            client.set_local_addr(addr);
+        }
+        client.set_hop_count(self.hop_count);
 
-        let (_tx, rx) = oneshot::channel();
-        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), self.port);
-        let (sink, stream) = client.start(addr, None, rx).await?.split();
+        let addr = resolve_addr(&self.host, self.port).await?;
+        let (sink, stream) = client.start(addr, None, shutdown_signal).await?.split();
 
         let rx = Box::pin(recv_voice_packets(stream));
-        let tx = Box::pin(radio_broadcast(sink, path, should_loop));
+        let tx = Box::pin(radio_broadcast(sink, &self.name, path, should_loop));
 
         match future::try_select(rx, tx).await {
             Err(Either::Left((err, _))) => Err(err.into()),
@@ -70,36 +139,52 @@ impl RadioStation {
     }
 }
 
+/// Resolves the host (which may also be a bracketed IPv6 address, e.g. `[::1]`) and port to the
+/// address of the SRS server.
+pub async fn resolve_addr(host: &str, port: u16) -> Result<SocketAddr, anyhow::Error> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    lookup_host((host, port))
+        .await
+        .map_err(|err| anyhow!("failed to resolve SRS host {}: {}", host, err))?
+        .next()
+        .ok_or_else(|| anyhow!("SRS host {} did not resolve to any address", host))
+}
+
 async fn recv_voice_packets(mut stream: SplitStream<VoiceStream>) -> Result<(), anyhow::Error> {
     while let Some(packet) = stream.next().await {
         packet?;
         // we are currently not interested in the received voice packets, so simply discard them
     }
 
+    // the stream only ends once the station has been shut down
+    debug!("Station disconnected from SRS");
     Ok(())
 }
 
-struct OpusFile {
+pub struct OpusFile {
     path: PathBuf,
     #[allow(unused)]
     duration: Duration,
 }
 
-async fn radio_broadcast<P: AsRef<Path>>(
-    mut sink: SplitSink<VoiceStream, Vec<u8>>,
-    path: P,
-    should_loop: bool,
-) -> Result<(), anyhow::Error> {
-    let mut file_paths: Vec<PathBuf> = Vec::new();
+/// Collects the OGG/OPUS files at the given path, which is either a single file or a directory.
+/// Files of a directory that aren't valid OGG/OPUS are skipped with a warning, whereas an invalid
+/// single file is an error. It is also an error if there is no playable file at all.
+pub async fn read_audio_files<P: AsRef<Path>>(path: P) -> Result<Vec<OpusFile>, anyhow::Error> {
+    let path = path.as_ref();
+    if !path.exists().await {
+        return Err(anyhow!("{} does not exist", path.to_string_lossy()));
+    }
 
-    if path.as_ref().is_dir().await {
-        let mut dir = path.as_ref().read_dir().await?;
+    if !path.is_dir().await {
+        let file = read_opus_file(path.to_path_buf())?;
+        return Ok(vec![file]);
+    }
 
-        while let Some(entry) = dir.next().await {
-            file_paths.push(entry?.path());
-        }
-    } else {
-        file_paths.push(path.as_ref().to_path_buf())
+    let mut file_paths: Vec<PathBuf> = Vec::new();
+    let mut dir = path.read_dir().await?;
+    while let Some(entry) = dir.next().await {
+        file_paths.push(entry?.path());
     }
 
     let mut audio_files = Vec::new();
@@ -109,46 +194,111 @@ async fn radio_broadcast<P: AsRef<Path>>(
             continue;
         }
 
-        // FIXME: find an async way of reading the metadata
-        let mut f = SyncFile::open(&path)?;
-        if let Some(OggFormat::Opus(meta)) = ogg_metadata::read_format(&mut f)?.into_iter().next() {
-            if let Some(duration) = meta.get_duration() {
-                audio_files.push(OpusFile { path, duration });
-            } else {
-                error!("Failed reading duration of {}", path.to_string_lossy());
-            }
+        match read_opus_file(path) {
+            Ok(file) => audio_files.push(file),
+            Err(err) => warn!("Skipping audio file: {}", err),
+        }
+    }
+
+    if audio_files.is_empty() {
+        return Err(anyhow!(
+            "{} does not contain any OGG/OPUS files",
+            path.to_string_lossy()
+        ));
+    }
+
+    Ok(audio_files)
+}
+
+fn read_opus_file(path: PathBuf) -> Result<OpusFile, anyhow::Error> {
+    // FIXME: find an async way of reading the metadata
+    let mut f = SyncFile::open(&path)
+        .map_err(|err| anyhow!("failed to open {}: {}", path.to_string_lossy(), err))?;
+    let format = ogg_metadata::read_format(&mut f).map_err(|err| {
+        anyhow!(
+            "{} is not a valid OGG file: {}",
+            path.to_string_lossy(),
+            err
+        )
+    })?;
+    if let Some(OggFormat::Opus(meta)) = format.into_iter().next() {
+        if let Some(duration) = meta.get_duration() {
+            Ok(OpusFile { path, duration })
         } else {
-            error!("{} is not opus encoded", path.to_string_lossy());
+            Err(anyhow!(
+                "failed reading duration of {}",
+                path.to_string_lossy()
+            ))
         }
+    } else {
+        Err(anyhow!(
+            "{} is not opus encoded (note that OGG/VORBIS is not supported)",
+            path.to_string_lossy()
+        ))
     }
+}
 
-    loop {
-        for OpusFile { ref path, .. } in &audio_files {
-            debug!("Playing {}", path.to_string_lossy());
+/// Reads all audio packets of the given OGG/OPUS file.
+async fn read_packets(path: &Path) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+    let mut file = File::open(path).await?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).await?;
 
-            let mut file = File::open(&path).await?;
-            let mut contents = Vec::new();
-            file.read_to_end(&mut contents).await?;
+    let mut audio = PacketReader::new(Cursor::new(contents));
+    let mut packets = Vec::new();
+    while let Some(pck) = audio.read_packet()? {
+        if !pck.data.is_empty() {
+            packets.push(pck.data);
+        }
+    }
 
-            let start = Instant::now();
-            let mut audio = PacketReader::new(Cursor::new(contents));
-            let mut frame_count = 0;
+    if packets.is_empty() {
+        return Err(anyhow!("no audio packets found"));
+    }
+
+    Ok(packets)
+}
+
+async fn radio_broadcast<P: AsRef<Path>>(
+    mut sink: SplitSink<VoiceStream, Vec<u8>>,
+    name: &str,
+    path: P,
+    should_loop: bool,
+) -> Result<(), anyhow::Error> {
+    let audio_files = read_audio_files(path).await?;
 
-            while let Some(pck) = audio.read_packet()? {
-                if pck.data.len() == 0 {
+    loop {
+        let mut played = 0;
+        for OpusFile { ref path, .. } in &audio_files {
+            // a file that became unreadable (e.g. because it was replaced while looping) is
+            // skipped instead of stopping the whole station
+            let packets = match read_packets(path).await {
+                Ok(packets) => packets,
+                Err(err) => {
+                    warn!("{}: Skipping {}: {}", name, path.to_string_lossy(), err);
                     continue;
                 }
+            };
+
+            debug!("{}: Playing {}", name, path.to_string_lossy());
+            let start = Instant::now();
 
-                sink.send(pck.data).await?;
-                frame_count += 1;
+            for (i, pck) in packets.into_iter().enumerate() {
+                sink.send(pck).await?;
 
                 // wait for the current ~playtime before sending the next package
-                let playtime = Duration::from_millis((frame_count as u64 + 1) * 20); // 20m per frame count
+                let frame_count = i as u64 + 1;
+                let playtime = Duration::from_millis((frame_count + 1) * 20); // 20m per frame count
                 let elapsed = start.elapsed();
                 if playtime > elapsed {
                     delay_for(playtime - elapsed).await;
                 }
             }
+            played += 1;
+        }
+
+        if played == 0 {
+            return Err(anyhow!("none of the audio files could be played"));
         }
 
         if !should_loop {
@@ -158,3 +308,74 @@ async fn radio_broadcast<P: AsRef<Path>>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use async_std::path::PathBuf;
+    use async_std::task;
+
+    use std::net::SocketAddr;
+
+    use super::{read_audio_files, read_packets, resolve_addr};
+
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("dcs-radio-station-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_resolve_addr() {
+        assert_eq!(
+            resolve_addr("127.0.0.1", 5002).await.unwrap(),
+            "127.0.0.1:5002".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            resolve_addr("::1", 5002).await.unwrap(),
+            "[::1]:5002".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            resolve_addr("[::1]", 5003).await.unwrap(),
+            "[::1]:5003".parse::<SocketAddr>().unwrap()
+        );
+        assert!(resolve_addr("localhost", 5002)
+            .await
+            .unwrap()
+            .ip()
+            .is_loopback());
+        assert!(resolve_addr("not a host", 5002).await.is_err());
+    }
+
+    #[test]
+    fn test_missing_audio_file() {
+        let dir = test_dir("missing");
+        let err = task::block_on(read_audio_files(PathBuf::from(dir.join("missing.ogg"))))
+            .err()
+            .unwrap();
+        assert!(err.to_string().ends_with("missing.ogg does not exist"));
+    }
+
+    #[test]
+    fn test_corrupt_audio_file() {
+        let dir = test_dir("corrupt");
+        let path = dir.join("corrupt.ogg");
+        fs::write(&path, b"definitely not an ogg file").unwrap();
+
+        let path = PathBuf::from(path);
+
+        // a single corrupt file is an error
+        assert!(task::block_on(read_audio_files(&path)).is_err());
+        // a corrupt file in a directory is skipped, leaving no playable file in this case
+        let err = task::block_on(read_audio_files(PathBuf::from(dir)))
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .ends_with("does not contain any OGG/OPUS files"));
+        // packets of a corrupt file cannot be read
+        assert!(task::block_on(read_packets(&path)).is_err());
+    }
+}
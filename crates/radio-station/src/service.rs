@@ -0,0 +1,179 @@
+//! Runs the stations as a Windows service, e.g. created with:
+//!
+//! ```text
+//! sc create DcsRadioStation binPath= "C:\...\dcs-radio-station.exe --service --config C:\...\stations.toml"
+//! ```
+
+use std::ffi::OsString;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::shutdown::Shutdown;
+use windows_service::service::{
+    ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{
+    self, ServiceControlHandlerResult, ServiceStatusHandle,
+};
+use windows_service::{define_windows_service, service_dispatcher};
+
+/// The name the service has to be created with, which is also used as the event log source.
+pub const SERVICE_NAME: &str = "DcsRadioStation";
+
+/// How long the service control manager is told to wait for the stations to disconnect from SRS
+/// once the service is stopping.
+const STOP_WAIT_HINT: Duration = Duration::from_secs(10);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Connects to the Windows service control manager and runs the stations until the service is
+/// stopped. Blocks until the service has stopped.
+pub fn run() -> Result<(), anyhow::Error> {
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)?;
+    Ok(())
+}
+
+/// Handles the events sent by the service control manager. Stopping the service (or shutting
+/// down Windows) takes the same graceful path as Ctrl-C in the console mode.
+fn handle_control(control: ServiceControl, shutdown: &Shutdown) -> ServiceControlHandlerResult {
+    match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            if shutdown.trigger() {
+                info!("Service is stopping ...");
+            }
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    }
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    // the arguments of the service's command line (not the ones the service is started with) are
+    // used to configure the stations
+    let matches = crate::app().get_matches();
+    let level = crate::verbosity_level(matches.occurrences_of("verbose"))
+        .and_then(|level| level.to_level())
+        .unwrap_or(log::Level::Info);
+    // the event source has to be registered once beforehand, which requires admin rights; if
+    // that hasn't been done, log to stderr instead of dropping all logs
+    if let Err(err) = eventlog::init(SERVICE_NAME, level) {
+        env_logger::Builder::new()
+            .filter_level(level.to_level_filter())
+            .init();
+        warn!(
+            "Logging to stderr, as the event log source {} is not available: {}",
+            SERVICE_NAME, err
+        );
+    }
+
+    let shutdown = Arc::new(Shutdown::new());
+    let status_handle = match service_control_handler::register(SERVICE_NAME, {
+        let shutdown = shutdown.clone();
+        move |control| handle_control(control, &shutdown)
+    }) {
+        Ok(status_handle) => status_handle,
+        Err(err) => {
+            error!("Failed to register the service control handler: {}", err);
+            return;
+        }
+    };
+
+    set_status(
+        status_handle,
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        ServiceExitCode::Win32(0),
+        Duration::default(),
+    );
+
+    let result = tokio::runtime::Runtime::new()
+        .map_err(anyhow::Error::from)
+        .and_then(|mut runtime| {
+            // report the service as stopping while the stations disconnect from SRS, so that the
+            // service control manager waits for them instead of considering the service hung
+            let stopping = shutdown.subscribe();
+            runtime.spawn(async move {
+                if stopping.await.is_ok() {
+                    set_status(
+                        status_handle,
+                        ServiceState::StopPending,
+                        ServiceControlAccept::empty(),
+                        ServiceExitCode::Win32(0),
+                        STOP_WAIT_HINT,
+                    );
+                }
+            });
+            runtime.block_on(crate::run(matches, shutdown, false))
+        });
+
+    // a non-zero exit code lets the service control manager's recovery actions restart the
+    // service
+    let exit_code = match result {
+        Ok(()) => ServiceExitCode::Win32(0),
+        Err(err) => {
+            error!("{}", err);
+            ServiceExitCode::ServiceSpecific(1)
+        }
+    };
+    set_status(
+        status_handle,
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        exit_code,
+        Duration::default(),
+    );
+}
+
+fn set_status(
+    status_handle: ServiceStatusHandle,
+    current_state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+    exit_code: ServiceExitCode,
+    wait_hint: Duration,
+) {
+    let status = ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted,
+        exit_code,
+        checkpoint: 0,
+        wait_hint,
+    };
+    if let Err(err) = status_handle.set_service_status(status) {
+        error!("Failed to update the service status: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::handle_control;
+    use crate::shutdown::Shutdown;
+    use windows_service::service::ServiceControl;
+    use windows_service::service_control_handler::ServiceControlHandlerResult;
+
+    #[test]
+    fn test_stop_triggers_shutdown() {
+        let shutdown = Shutdown::new();
+        let mut rx = shutdown.subscribe();
+
+        assert_eq!(
+            handle_control(ServiceControl::Interrogate, &shutdown),
+            ServiceControlHandlerResult::NoError
+        );
+        assert!(rx.try_recv().is_err());
+
+        assert_eq!(
+            handle_control(ServiceControl::Stop, &shutdown),
+            ServiceControlHandlerResult::NoError
+        );
+        assert!(rx.try_recv().is_ok());
+
+        // a shutdown of Windows after the stop doesn't trigger it again
+        assert_eq!(
+            handle_control(ServiceControl::Shutdown, &shutdown),
+            ServiceControlHandlerResult::NoError
+        );
+        assert!(!shutdown.trigger());
+    }
+}
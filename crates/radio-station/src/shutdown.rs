@@ -0,0 +1,70 @@
+use std::sync::Mutex;
+
+use tokio::sync::oneshot;
+
+/// Signals all stations to disconnect from SRS and stop playing, e.g. on Ctrl-C or when the
+/// Windows service is stopped.
+pub struct Shutdown {
+    txs: Mutex<Option<Vec<oneshot::Sender<()>>>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Shutdown {
+            txs: Mutex::new(Some(Vec::new())),
+        }
+    }
+
+    /// Returns a receiver that resolves once the shutdown is triggered (or immediately if it has
+    /// already been triggered).
+    pub fn subscribe(&self) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        match self.txs.lock().unwrap().as_mut() {
+            Some(txs) => txs.push(tx),
+            None => {
+                let _ = tx.send(());
+            }
+        }
+        rx
+    }
+
+    /// Triggers the shutdown. Returns `false` if it has already been triggered before.
+    pub fn trigger(&self) -> bool {
+        match self.txs.lock().unwrap().take() {
+            Some(txs) => {
+                for tx in txs {
+                    let _ = tx.send(());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Shutdown;
+
+    #[test]
+    fn test_trigger() {
+        let shutdown = Shutdown::new();
+        let mut a = shutdown.subscribe();
+        let mut b = shutdown.subscribe();
+        assert!(a.try_recv().is_err());
+
+        assert!(shutdown.trigger());
+        assert!(a.try_recv().is_ok());
+        assert!(b.try_recv().is_ok());
+
+        // subsequent triggers are no-ops, late subscribers are shut down right away
+        assert!(!shutdown.trigger());
+        assert!(shutdown.subscribe().try_recv().is_ok());
+    }
+}
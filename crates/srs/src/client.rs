@@ -1,11 +1,19 @@
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use crate::message::{create_sguid, GameMessage, LatLngPosition};
+use crate::message::{create_sguid, GameMessage, LatLngPosition, Radio};
 use crate::voice_stream::VoiceStream;
 use futures::channel::mpsc;
+use log::{Level, LevelFilter};
 use tokio::sync::oneshot::Receiver;
 
+/// The placeholders that can be used in the template of a client's name.
+const NAME_PLACEHOLDERS: [&str; 3] = ["{name}", "{freq}", "{modulation}"];
+/// The number of radios advertised to the server unless configured otherwise.
+pub const DEFAULT_RADIO_COUNT: usize = 10;
+
 #[derive(Debug, Clone)]
 pub struct UnitInfo {
     pub id: u32,
@@ -16,10 +24,27 @@ pub struct UnitInfo {
 pub struct Client {
     sguid: String,
     name: String,
-    freq: u64,
+    name_prefix: String,
+    name_suffix: String,
+    name_template: Option<String>,
+    /// Shared between the clones of the client, so that the frequency can be retuned while the
+    /// client is running (see [`crate::FrequencyControl`]).
+    freq: Arc<AtomicU64>,
     m: String,
     pos: Arc<RwLock<LatLngPosition>>,
     unit: Option<UnitInfo>,
+    radios: Vec<Radio>,
+    simultaneous_transmission: bool,
+    coalition_password: Option<String>,
+    radio_update_interval: Duration,
+    voice_ping_interval: Duration,
+    position_update_interval: Duration,
+    reconnect_on_blocked_udp: bool,
+    udp_send_buffer_size: Option<usize>,
+    udp_recv_buffer_size: Option<usize>,
+    local_addr: Option<IpAddr>,
+    hop_count: u8,
+    log_level: Option<LevelFilter>,
 }
 
 impl Client {
@@ -27,10 +52,27 @@ impl Client {
         Client {
             sguid: create_sguid(),
             name: name.to_string(),
-            freq,
+            name_prefix: String::new(),
+            name_suffix: String::new(),
+            name_template: None,
+            freq: Arc::new(AtomicU64::new(freq)),
             m: m.to_string(),
             pos: Arc::new(RwLock::new(LatLngPosition::default())),
             unit: None,
+            radios: std::iter::repeat_with(Radio::default)
+                .take(DEFAULT_RADIO_COUNT)
+                .collect(),
+            simultaneous_transmission: true,
+            coalition_password: None,
+            radio_update_interval: Duration::from_secs(30),
+            voice_ping_interval: Duration::from_secs(5),
+            position_update_interval: Duration::from_secs(60),
+            reconnect_on_blocked_udp: false,
+            udp_send_buffer_size: None,
+            udp_recv_buffer_size: None,
+            local_addr: None,
+            hop_count: 0,
+            log_level: None,
         }
     }
 
@@ -41,11 +83,32 @@ impl Client {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// The name shown in the SRS client list, formatted with the name template (if any), and
+    /// including the configured prefix and suffix.
+    pub fn display_name(&self) -> String {
+        let name = match &self.name_template {
+            Some(template) => template
+                .replace("{name}", &self.name)
+                .replace(
+                    "{freq}",
+                    &format!("{:.2}", self.freq() as f64 / 1_000_000.0),
+                )
+                .replace("{modulation}", &self.m),
+            None => self.name.clone(),
+        };
+        format!("{}{}{}", self.name_prefix, name, self.name_suffix)
+    }
+
     pub fn m(&self) -> &str {
         &self.m
     }
     pub fn freq(&self) -> u64 {
-        self.freq
+        self.freq.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn freq_handle(&self) -> Arc<AtomicU64> {
+        self.freq.clone()
     }
 
     pub fn position(&self) -> LatLngPosition {
@@ -61,11 +124,71 @@ impl Client {
         self.unit.as_ref()
     }
 
+    /// The radios advertised to the server in the client's radio info.
+    pub fn radios(&self) -> &[Radio] {
+        &self.radios
+    }
+
+    pub fn simultaneous_transmission(&self) -> bool {
+        self.simultaneous_transmission
+    }
+
+    pub fn coalition_password(&self) -> Option<&str> {
+        self.coalition_password.as_deref()
+    }
+
+    pub fn radio_update_interval(&self) -> Duration {
+        self.radio_update_interval
+    }
+
+    pub fn voice_ping_interval(&self) -> Duration {
+        self.voice_ping_interval
+    }
+
+    pub fn position_update_interval(&self) -> Duration {
+        self.position_update_interval
+    }
+
+    pub fn reconnect_on_blocked_udp(&self) -> bool {
+        self.reconnect_on_blocked_udp
+    }
+
+    pub fn udp_send_buffer_size(&self) -> Option<usize> {
+        self.udp_send_buffer_size
+    }
+
+    pub fn udp_recv_buffer_size(&self) -> Option<usize> {
+        self.udp_recv_buffer_size
+    }
+
+    pub fn local_addr(&self) -> Option<IpAddr> {
+        self.local_addr
+    }
+
+    pub fn hop_count(&self) -> u8 {
+        self.hop_count
+    }
+
+    /// Whether messages of the given level are logged for this client, considering its log level
+    /// override (the global log level applies additionally).
+    pub fn log_enabled(&self, level: Level) -> bool {
+        self.log_level.map(|max| level <= max).unwrap_or(true)
+    }
+
     pub fn set_position(&mut self, pos: LatLngPosition) {
         let mut p = self.pos.write().unwrap();
         *p = pos;
     }
 
+    /// Moves the advertised radios that are tuned to the old frequency to the new one.
+    pub(crate) fn retune_radios(&mut self, old: u64, new: u64) {
+        for radio in &mut self.radios {
+            if radio.freq as u64 == old {
+                radio.freq = new as f64;
+            }
+        }
+    }
+
     pub fn set_unit(&mut self, id: u32, name: &str) {
         self.unit = Some(UnitInfo {
             id,
@@ -73,6 +196,109 @@ impl Client {
         });
     }
 
+    /// Sets the radios advertised to the server in the client's radio info, for servers that
+    /// expect a specific radio layout. Defaults to `DEFAULT_RADIO_COUNT` disabled radios.
+    pub fn set_radios(&mut self, radios: Vec<Radio>) -> Result<(), anyhow::Error> {
+        if radios.is_empty() {
+            return Err(anyhow!("at least one radio is required"));
+        }
+        self.radios = radios;
+        Ok(())
+    }
+
+    /// Sets whether the client advertises to transmit on multiple radios simultaneously. Enabled
+    /// by default; some servers expect single-radio stations to advertise it as disabled.
+    pub fn set_simultaneous_transmission(&mut self, enabled: bool) {
+        self.simultaneous_transmission = enabled;
+    }
+
+    /// Sets a prefix and a suffix that are added to the name shown in the SRS client list (e.g.
+    /// `[Training] `), to tell the clients of multiple DATIS instances on the same server apart.
+    pub fn set_name_affixes(&mut self, prefix: &str, suffix: &str) {
+        self.name_prefix = prefix.to_string();
+        self.name_suffix = suffix.to_string();
+    }
+
+    /// Sets a template for the name shown in the SRS client list (e.g. `{name} ({freq} {modulation})`
+    /// for `ATIS Batumi (251.00 AM)`). The placeholders are replaced with the client's name, its
+    /// current frequency (in MHz) and its modulation. The name used for everything else (like
+    /// logging) is not affected.
+    pub fn set_name_template(&mut self, template: &str) -> Result<(), anyhow::Error> {
+        validate_name_template(template)?;
+        self.name_template = Some(template.to_string());
+        Ok(())
+    }
+
+    /// Sets the password used to join a coalition on servers that protect their coalitions with
+    /// a password (SRS' external AWACS mode).
+    pub fn set_coalition_password(&mut self, password: &str) {
+        self.coalition_password = Some(password.to_string());
+    }
+
+    /// Sets the interval in which the radio info is re-sent to the server, even if nothing has
+    /// changed. Some servers remove clients from their client list that haven't refreshed their
+    /// radio info for a while.
+    pub fn set_radio_update_interval(&mut self, interval: Duration) -> Result<(), anyhow::Error> {
+        self.radio_update_interval = validate_interval("radio update", interval)?;
+        Ok(())
+    }
+
+    /// Sets the interval in which pings are sent through the voice (UDP) connection.
+    pub fn set_voice_ping_interval(&mut self, interval: Duration) -> Result<(), anyhow::Error> {
+        self.voice_ping_interval = validate_interval("voice ping", interval)?;
+        Ok(())
+    }
+
+    /// If enabled, voice pings are sent even if the client doesn't receive voice, and the voice
+    /// stream fails once the UDP voice connection seems to be blocked (no responses to the voice
+    /// pings), so that the caller can reconnect. Disabled by default.
+    pub fn set_reconnect_on_blocked_udp(&mut self, reconnect: bool) {
+        self.reconnect_on_blocked_udp = reconnect;
+    }
+
+    /// Sets the interval in which the station's position is checked for changes (and sent to the
+    /// server if it has changed).
+    pub fn set_position_update_interval(
+        &mut self,
+        interval: Duration,
+    ) -> Result<(), anyhow::Error> {
+        self.position_update_interval = validate_interval("position update", interval)?;
+        Ok(())
+    }
+
+    /// Sets the send buffer size (in bytes) of the voice (UDP) socket. Uses the OS default if not
+    /// set. The OS might adjust the size (e.g. Linux doubles it and caps it at `wmem_max`).
+    pub fn set_udp_send_buffer_size(&mut self, size: usize) -> Result<(), anyhow::Error> {
+        self.udp_send_buffer_size = Some(validate_buffer_size(size)?);
+        Ok(())
+    }
+
+    /// Sets the receive buffer size (in bytes) of the voice (UDP) socket. Uses the OS default if
+    /// not set. The OS might adjust the size (e.g. Linux doubles it and caps it at `rmem_max`).
+    pub fn set_udp_recv_buffer_size(&mut self, size: usize) -> Result<(), anyhow::Error> {
+        self.udp_recv_buffer_size = Some(validate_buffer_size(size)?);
+        Ok(())
+    }
+
+    /// Sets the local address the connections to the server are made from, to use a specific
+    /// network interface on multi-homed machines. Must be of the same address family as the
+    /// server's address. Defaults to any interface.
+    pub fn set_local_addr(&mut self, addr: IpAddr) {
+        self.local_addr = Some(addr);
+    }
+
+    /// Sets the hop count of the sent voice packets, for setups that relay the transmissions
+    /// through retransmitters. Defaults to 0.
+    pub fn set_hop_count(&mut self, hop_count: u8) {
+        self.hop_count = hop_count;
+    }
+
+    /// Overrides the log level of the client's messages, e.g. to quiet a noisy client without
+    /// lowering the global log level.
+    pub fn set_log_level(&mut self, level: LevelFilter) {
+        self.log_level = Some(level);
+    }
+
     /**
       Start sending updates to the specified server. If `game_source` is None,
       the client will act as a stationary transmitter using the position and
@@ -92,3 +318,48 @@ impl Client {
         Ok(stream)
     }
 }
+
+/// Checks that the interval is greater than zero. The name (e.g. `voice ping`) is used in the
+/// error message.
+pub fn validate_interval(name: &str, interval: Duration) -> Result<Duration, anyhow::Error> {
+    if interval == Duration::from_secs(0) {
+        Err(anyhow!("the {} interval must be greater than zero", name))
+    } else {
+        Ok(interval)
+    }
+}
+
+/// Checks that the name template isn't empty, and that it only contains known placeholders.
+pub fn validate_name_template(template: &str) -> Result<(), anyhow::Error> {
+    if template.trim().is_empty() {
+        return Err(anyhow!("name template must not be empty"));
+    }
+
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end + 1)
+            .ok_or_else(|| anyhow!("unclosed placeholder in name template `{}`", template))?;
+        let placeholder = &rest[start..end];
+        if !NAME_PLACEHOLDERS.contains(&placeholder) {
+            return Err(anyhow!(
+                "unknown placeholder `{}` in name template `{}` (expected {})",
+                placeholder,
+                template,
+                NAME_PLACEHOLDERS.join(", ")
+            ));
+        }
+        rest = &rest[end..];
+    }
+
+    Ok(())
+}
+
+fn validate_buffer_size(size: usize) -> Result<usize, anyhow::Error> {
+    if size == 0 {
+        Err(anyhow!("buffer size must be greater than zero"))
+    } else {
+        Ok(size)
+    }
+}
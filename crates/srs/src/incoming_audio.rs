@@ -0,0 +1,237 @@
+use std::collections::hash_map::{Entry, HashMap};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use crate::jitter_buffer::JitterBuffer;
+use crate::voice_codec::{Frequency, VoicePacket};
+use audiopus::coder::Decoder;
+use audiopus::{Channels, SampleRate};
+use futures::future::FutureExt;
+use futures::stream::{Stream, StreamExt};
+use tokio::time::{self, Delay};
+
+/// The maximum opus frame duration is 120ms, which are 1920 samples at 16kHz mono.
+const MAX_FRAME_SIZE: usize = 1920;
+
+/// Decoded audio of a voice packet received from another SRS client.
+#[derive(Debug, Clone)]
+pub struct IncomingAudio {
+    pub client_sguid: String,
+    pub frequencies: Vec<Frequency>,
+    pub packet_id: u64,
+    /// 16kHz mono PCM samples.
+    pub pcm: Vec<i16>,
+}
+
+/// Decodes the opus encoded audio of the voice packets of the wrapped stream (usually a
+/// `VoiceStream`). Each sending client gets its own decoder, since opus decoding is stateful.
+pub struct AudioDecoder<S> {
+    inner: S,
+    decoders: HashMap<[u8; 22], Decoder>,
+    jitter_buffer: Option<JitterBuffer>,
+    /// Packets released by the jitter buffer that haven't been decoded yet.
+    ready: VecDeque<VoicePacket>,
+    /// Fires once the next packet held back by the jitter buffer has to be released.
+    release_timer: Option<Delay>,
+    inner_done: bool,
+}
+
+impl<S> AudioDecoder<S> {
+    pub fn new(inner: S) -> Self {
+        AudioDecoder {
+            inner,
+            decoders: HashMap::new(),
+            jitter_buffer: None,
+            ready: VecDeque::new(),
+            release_timer: None,
+            inner_done: false,
+        }
+    }
+
+    /// Creates a decoder that reorders the received packets (and drops duplicates) before
+    /// decoding them. Packets are delayed by up to `delay` to wait for late packets. Must be polled
+    /// within a tokio runtime (with the timer enabled).
+    pub fn with_jitter_buffer(inner: S, delay: Duration) -> Self {
+        AudioDecoder {
+            jitter_buffer: Some(JitterBuffer::new(delay)),
+            ..AudioDecoder::new(inner)
+        }
+    }
+
+    fn decode(&mut self, packet: VoicePacket) -> Result<IncomingAudio, anyhow::Error> {
+        let decoder = match self.decoders.entry(packet.client_sguid) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(Decoder::new(SampleRate::Hz16000, Channels::Mono)?)
+            }
+        };
+
+        let mut pcm = vec![0; MAX_FRAME_SIZE];
+        let len = decoder.decode(Some(&packet.audio_part[..]), &mut pcm, false)?;
+        pcm.truncate(len);
+
+        Ok(IncomingAudio {
+            client_sguid: String::from_utf8_lossy(&packet.client_sguid).to_string(),
+            frequencies: packet.frequencies,
+            packet_id: packet.packet_id,
+            pcm,
+        })
+    }
+}
+
+impl<S> Stream for AudioDecoder<S>
+where
+    S: Stream<Item = Result<VoicePacket, anyhow::Error>> + Unpin,
+{
+    type Item = Result<IncomingAudio, anyhow::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let s = self.get_mut();
+        loop {
+            if let Some(packet) = s.ready.pop_front() {
+                return Poll::Ready(Some(s.decode(packet)));
+            }
+            if s.inner_done {
+                return Poll::Ready(None);
+            }
+
+            match s.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(packet))) => match &mut s.jitter_buffer {
+                    Some(jitter_buffer) => {
+                        s.ready.extend(jitter_buffer.push(packet, Instant::now()))
+                    }
+                    None => return Poll::Ready(Some(s.decode(packet))),
+                },
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => {
+                    // release all packets still held back by the jitter buffer
+                    if let Some(jitter_buffer) = &mut s.jitter_buffer {
+                        s.ready.extend(jitter_buffer.flush());
+                    }
+                    s.inner_done = true;
+                }
+                Poll::Pending => {
+                    // release held back packets once they have been delayed long enough, even if
+                    // no further packets are received (e.g. at the end of a transmission)
+                    let deadline = match s.jitter_buffer.as_ref().and_then(|j| j.next_deadline()) {
+                        Some(deadline) => deadline,
+                        None => return Poll::Pending,
+                    };
+                    let timer = s
+                        .release_timer
+                        .get_or_insert_with(|| time::delay_until(deadline.into()));
+                    if timer.deadline() != deadline.into() {
+                        timer.reset(deadline.into());
+                    }
+                    if timer.poll_unpin(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    if let Some(jitter_buffer) = &mut s.jitter_buffer {
+                        s.ready.extend(jitter_buffer.release_expired(deadline));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::voice_codec::{Encryption, Modulation};
+    use audiopus::coder::Encoder;
+    use audiopus::Application;
+    use futures::stream;
+
+    fn voice_packet(sguid: &[u8; 22], packet_id: u64, audio_part: Vec<u8>) -> VoicePacket {
+        VoicePacket {
+            audio_part,
+            frequencies: vec![Frequency {
+                freq: 251_000_000.0,
+                modulation: Modulation::AM,
+                encryption: Encryption::None,
+            }],
+            unit_id: 0,
+            packet_id,
+            hop_count: 0,
+            transmission_sguid: *sguid,
+            client_sguid: *sguid,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_incoming_audio() {
+        let enc = Encoder::new(SampleRate::Hz16000, Channels::Mono, Application::Voip).unwrap();
+        let mut output = [0; 256];
+        let frame = (0..320)
+            .map(|i| ((i as f64 / 16.0).sin() * 8000.0) as i16)
+            .collect::<Vec<_>>();
+        let len = enc.encode(&frame, &mut output).unwrap();
+
+        let sguid = *b"aaaaaaaaaaaaaaaaaaaaaa";
+        let packets = vec![
+            Ok(voice_packet(&sguid, 1, output[..len].to_vec())),
+            Ok(voice_packet(&sguid, 2, output[..len].to_vec())),
+        ];
+
+        let audio = AudioDecoder::new(stream::iter(packets))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(audio.len(), 2);
+        for (i, audio) in audio.into_iter().enumerate() {
+            let audio = audio.unwrap();
+            assert_eq!(audio.client_sguid, "aaaaaaaaaaaaaaaaaaaaaa");
+            assert_eq!(audio.packet_id, i as u64 + 1);
+            assert_eq!(audio.frequencies[0].freq, 251_000_000.0);
+            assert_eq!(audio.pcm.len(), 320);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_with_jitter_buffer() {
+        let enc = Encoder::new(SampleRate::Hz16000, Channels::Mono, Application::Voip).unwrap();
+        let mut output = [0; 256];
+        let len = enc.encode(&[0; 320], &mut output).unwrap();
+
+        let sguid = *b"aaaaaaaaaaaaaaaaaaaaaa";
+        let packets = [2, 1, 1, 3]
+            .iter()
+            .map(|id| Ok(voice_packet(&sguid, *id, output[..len].to_vec())))
+            .collect::<Vec<_>>();
+
+        let ids =
+            AudioDecoder::with_jitter_buffer(stream::iter(packets), Duration::from_millis(60))
+                .map(|audio| audio.unwrap().packet_id)
+                .collect::<Vec<_>>()
+                .await;
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_release_tail_of_stalled_stream() {
+        let enc = Encoder::new(SampleRate::Hz16000, Channels::Mono, Application::Voip).unwrap();
+        let mut output = [0; 256];
+        let len = enc.encode(&[0; 320], &mut output).unwrap();
+
+        // the stream doesn't end after the last packets of a transmission (with 2 being lost)
+        let sguid = *b"aaaaaaaaaaaaaaaaaaaaaa";
+        let packets = [1, 3]
+            .iter()
+            .map(|id| Ok(voice_packet(&sguid, *id, output[..len].to_vec())))
+            .collect::<Vec<_>>();
+        let stalled = stream::iter(packets).chain(stream::pending());
+
+        let ids = time::timeout(
+            Duration::from_secs(1),
+            AudioDecoder::with_jitter_buffer(stalled, Duration::from_millis(60))
+                .map(|audio| audio.unwrap().packet_id)
+                .take(2)
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .expect("held back packets weren't released");
+        assert_eq!(ids, vec![1, 3]);
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::voice_codec::VoicePacket;
+
+/// The duration of the audio of a single voice packet.
+const PACKET_DURATION: Duration = Duration::from_millis(20);
+/// Packets that are more than this many packets (1s of audio) behind the already emitted ones are
+/// not late, but the start of a new sequence (e.g. because the sending client reconnected).
+const MAX_LATENESS: u64 = 50;
+/// The state of a sending client is reset after it hasn't sent any packet for this long, as its
+/// next packet might belong to a new sequence.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Reorders received voice packets by their `packet_id` (separately for each sending client) and
+/// drops duplicates. Packets are held back until either all previous packets have been received,
+/// until more packets than fit into the configured delay window are buffered, or until they have
+/// been held back for the configured delay (the missing packets are considered lost then).
+pub struct JitterBuffer {
+    delay: Duration,
+    window: usize,
+    clients: HashMap<[u8; 22], ClientBuffer>,
+}
+
+struct ClientBuffer {
+    /// The id of the next packet to be emitted, `None` if no packet has been emitted yet.
+    next_id: Option<u64>,
+    /// Buffered packets (and when they have been received), ordered by their `packet_id`.
+    packets: Vec<(Instant, VoicePacket)>,
+    last_received: Instant,
+}
+
+impl ClientBuffer {
+    fn new(now: Instant) -> Self {
+        ClientBuffer {
+            next_id: None,
+            packets: Vec::new(),
+            last_received: now,
+        }
+    }
+
+    /// Emits the buffered packets that are ready to be played, in order.
+    fn release(
+        &mut self,
+        window: usize,
+        delay: Duration,
+        now: Instant,
+        ready: &mut Vec<VoicePacket>,
+    ) {
+        while let Some((received, first)) = self.packets.first() {
+            let is_next = self.next_id == Some(first.packet_id);
+            let is_expired = now.duration_since(*received) >= delay;
+            if !is_next && !is_expired && self.packets.len() <= window {
+                break;
+            }
+
+            let (_, packet) = self.packets.remove(0);
+            self.next_id = Some(packet.packet_id.wrapping_add(1));
+            ready.push(packet);
+        }
+    }
+}
+
+impl JitterBuffer {
+    pub fn new(delay: Duration) -> Self {
+        let window =
+            (delay.as_millis() + PACKET_DURATION.as_millis() - 1) / PACKET_DURATION.as_millis();
+        JitterBuffer {
+            delay,
+            window: (window as usize).max(1),
+            clients: HashMap::new(),
+        }
+    }
+
+    /// Adds the packet (received at `now`) to the buffer and returns all packets that are ready
+    /// to be played, in order.
+    pub fn push(&mut self, packet: VoicePacket, now: Instant) -> Vec<VoicePacket> {
+        let (window, delay) = (self.window, self.delay);
+        let buffer = self
+            .clients
+            .entry(packet.client_sguid)
+            .or_insert_with(|| ClientBuffer::new(now));
+        let mut ready = Vec::new();
+
+        // start over if the client has been idle, or if it restarted its sequence, after
+        // emitting what is left of the previous sequence
+        let is_idle = now.duration_since(buffer.last_received) > IDLE_TIMEOUT;
+        let is_restart = match buffer.next_id {
+            Some(next_id) => {
+                is_before(packet.packet_id, next_id)
+                    && next_id.wrapping_sub(packet.packet_id) > MAX_LATENESS
+            }
+            None => false,
+        };
+        if is_idle || is_restart {
+            ready.extend(buffer.packets.drain(..).map(|(_, p)| p));
+            buffer.next_id = None;
+        }
+        buffer.last_received = now;
+
+        // drop late packets (the packet or a later one has already been emitted) ...
+        if let Some(next_id) = buffer.next_id {
+            if is_before(packet.packet_id, next_id) {
+                return ready;
+            }
+        }
+
+        // ... and duplicates
+        match buffer
+            .packets
+            .iter()
+            .position(|(_, p)| !is_before(p.packet_id, packet.packet_id))
+        {
+            Some(ix) if buffer.packets[ix].1.packet_id == packet.packet_id => return ready,
+            Some(ix) => buffer.packets.insert(ix, (now, packet)),
+            None => buffer.packets.push((now, packet)),
+        }
+
+        buffer.release(window, delay, now, &mut ready);
+        ready
+    }
+
+    /// Returns the packets of all clients that have been held back for the configured delay at
+    /// `now` (and the packets that follow them), so that the end of a transmission is played even
+    /// if no further packets are received.
+    pub fn release_expired(&mut self, now: Instant) -> Vec<VoicePacket> {
+        let (window, delay) = (self.window, self.delay);
+        let mut ready = Vec::new();
+        for buffer in self.clients.values_mut() {
+            buffer.release(window, delay, now, &mut ready);
+        }
+        // forget clients that stopped sending
+        self.clients.retain(|_, buffer| {
+            !buffer.packets.is_empty() || now.duration_since(buffer.last_received) <= IDLE_TIMEOUT
+        });
+        ready
+    }
+
+    /// The time at which the next packet has been held back for the configured delay, if any
+    /// packets are buffered.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.clients
+            .values()
+            .filter_map(|buffer| buffer.packets.first())
+            .map(|(received, _)| *received + self.delay)
+            .min()
+    }
+
+    /// Returns all buffered packets of all clients, e.g. once the voice stream has ended.
+    pub fn flush(&mut self) -> Vec<VoicePacket> {
+        self.clients
+            .drain()
+            .flat_map(|(_, buffer)| buffer.packets.into_iter().map(|(_, p)| p))
+            .collect()
+    }
+}
+
+/// Whether the packet id `a` is before `b`, taking a wrap-around of the ids into account.
+fn is_before(a: u64, b: u64) -> bool {
+    (a.wrapping_sub(b) as i64) < 0
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::JitterBuffer;
+    use crate::voice_codec::VoicePacket;
+
+    fn packet(sguid: u8, packet_id: u64) -> VoicePacket {
+        VoicePacket {
+            audio_part: Vec::new(),
+            frequencies: Vec::new(),
+            unit_id: 0,
+            packet_id,
+            hop_count: 0,
+            transmission_sguid: [sguid; 22],
+            client_sguid: [sguid; 22],
+        }
+    }
+
+    fn push_all(buffer: &mut JitterBuffer, packets: Vec<VoicePacket>) -> Vec<(u8, u64)> {
+        let now = Instant::now();
+        let mut ids = Vec::new();
+        for p in packets {
+            ids.extend(
+                buffer
+                    .push(p, now)
+                    .into_iter()
+                    .map(|p| (p.client_sguid[0], p.packet_id)),
+            );
+        }
+        ids.extend(
+            buffer
+                .flush()
+                .into_iter()
+                .map(|p| (p.client_sguid[0], p.packet_id)),
+        );
+        ids
+    }
+
+    #[test]
+    fn test_reorder_shuffled_packets() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        let ids = push_all(
+            &mut buffer,
+            [3, 1, 2, 5, 4, 6, 8, 7]
+                .iter()
+                .map(|id| packet(1, *id))
+                .collect(),
+        );
+        assert_eq!(
+            ids,
+            (1..=8).map(|id| (1, id)).collect::<Vec<_>>(),
+            "packets aren't in order"
+        );
+    }
+
+    #[test]
+    fn test_drop_duplicates() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        let ids = push_all(
+            &mut buffer,
+            [1, 2, 2, 1, 4, 3, 3, 5, 4]
+                .iter()
+                .map(|id| packet(1, *id))
+                .collect(),
+        );
+        assert_eq!(ids, (1..=5).map(|id| (1, id)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_lost_packets() {
+        let now = Instant::now();
+        let mut buffer = JitterBuffer::new(Duration::from_millis(40));
+        // packet 2 is lost, and 3 is emitted once the window is exceeded
+        let mut ids = Vec::new();
+        for id in &[1, 3, 4] {
+            ids.extend(
+                buffer
+                    .push(packet(1, *id), now)
+                    .into_iter()
+                    .map(|p| p.packet_id),
+            );
+        }
+        assert_eq!(ids, vec![1]);
+        ids.extend(
+            buffer
+                .push(packet(1, 5), now)
+                .into_iter()
+                .map(|p| p.packet_id),
+        );
+        assert_eq!(ids, vec![1, 3, 4, 5]);
+
+        // the lost packet arriving late is dropped
+        assert!(buffer.push(packet(1, 2), now).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_around() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        let ids = push_all(
+            &mut buffer,
+            [u64::MAX - 1, u64::MAX, 1, 0, 2]
+                .iter()
+                .map(|id| packet(1, *id))
+                .collect(),
+        );
+        assert_eq!(
+            ids,
+            vec![(1, u64::MAX - 1), (1, u64::MAX), (1, 0), (1, 1), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn test_separate_clients() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        let ids = push_all(
+            &mut buffer,
+            vec![packet(1, 2), packet(2, 10), packet(1, 1), packet(2, 11)],
+        );
+        let client1 = ids.iter().filter(|(c, _)| *c == 1).collect::<Vec<_>>();
+        let client2 = ids.iter().filter(|(c, _)| *c == 2).collect::<Vec<_>>();
+        assert_eq!(client1, vec![&(1, 1), &(1, 2)]);
+        assert_eq!(client2, vec![&(2, 10), &(2, 11)]);
+    }
+
+    #[test]
+    fn test_release_tail_after_delay() {
+        let start = Instant::now();
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        // the last packets of a transmission (with 2 being lost) are held back ...
+        assert!(buffer.push(packet(1, 1), start).is_empty());
+        assert!(buffer
+            .push(packet(1, 3), start + Duration::from_millis(20))
+            .is_empty());
+        assert_eq!(
+            buffer.next_deadline(),
+            Some(start + Duration::from_millis(60))
+        );
+        assert!(buffer
+            .release_expired(start + Duration::from_millis(40))
+            .is_empty());
+
+        // ... only until they have been delayed for the configured delay
+        let ids = buffer
+            .release_expired(start + Duration::from_millis(60))
+            .into_iter()
+            .map(|p| p.packet_id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1]);
+        let ids = buffer
+            .release_expired(start + Duration::from_millis(80))
+            .into_iter()
+            .map(|p| p.packet_id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![3]);
+        assert_eq!(buffer.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_sequence_restart() {
+        let start = Instant::now();
+        let mut buffer = JitterBuffer::new(Duration::from_millis(40));
+        let mut ids = Vec::new();
+        let mut push = |buffer: &mut JitterBuffer, id: u64, at: Duration| {
+            ids.extend(
+                buffer
+                    .push(packet(1, id), start + at)
+                    .into_iter()
+                    .map(|p| p.packet_id),
+            )
+        };
+
+        // the client restarts its sequence (e.g. after reconnecting) ...
+        for id in 100..=104 {
+            push(&mut buffer, id, Duration::from_millis(0));
+        }
+        for id in 1..=3 {
+            push(&mut buffer, id, Duration::from_millis(20));
+        }
+        // ... or starts over after being idle for a while
+        for id in 1..=3 {
+            push(&mut buffer, id, Duration::from_secs(5));
+        }
+        ids.extend(buffer.flush().into_iter().map(|p| p.packet_id));
+
+        assert_eq!(
+            ids,
+            vec![100, 101, 102, 103, 104, 1, 2, 3, 1, 2, 3],
+            "restarted sequences are dropped as late packets"
+        );
+    }
+}
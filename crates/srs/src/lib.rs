@@ -1,14 +1,26 @@
-#![recursion_limit = "512"]
+#![recursion_limit = "1024"]
 
 #[macro_use]
 extern crate anyhow;
 
+/// Logs a message of a client, unless the client's log level override filters it.
+macro_rules! client_log {
+    ($client:expr, $lvl:expr, $($arg:tt)+) => {
+        if $client.log_enabled($lvl) {
+            log::log!($lvl, $($arg)+);
+        }
+    };
+}
+
 mod client;
+mod incoming_audio;
+mod jitter_buffer;
 pub mod message;
 mod messages_codec;
 mod voice_codec;
 mod voice_stream;
 
-pub use client::Client;
+pub use client::{validate_interval, validate_name_template, Client, DEFAULT_RADIO_COUNT};
+pub use incoming_audio::{AudioDecoder, IncomingAudio};
 pub use voice_codec::{Encryption, Frequency, Modulation, VoicePacket};
-pub use voice_stream::VoiceStream;
+pub use voice_stream::{FrequencyControl, TransmissionControl, VoiceStream};
@@ -14,6 +14,7 @@ pub enum MsgType {
     ServerSettings,
     ClientDisconnect,
     VersionMismatch,
+    ExternalAwacsModePassword,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -202,6 +203,7 @@ impl Default for RadioSwitchControls {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct Client {
+    #[serde(default)]
     pub client_guid: String,
     pub name: Option<String>,
     pub radio_info: Option<RadioInfo>,
@@ -222,9 +224,13 @@ pub struct Message {
     pub client: Option<Client>,
     pub msg_type: MsgType,
     pub server_settings: Option<HashMap<String, String>>,
+    #[serde(
+        rename = "ExternalAWACSModePassword",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub external_awacs_mode_password: Option<String>,
     // Clients
-    // ServerSettings
-    // ExternalAWACSModePassword
     pub version: String,
 }
 
@@ -296,6 +302,7 @@ impl ::serde::Serialize for MsgType {
             MsgType::ServerSettings => 4,
             MsgType::ClientDisconnect => 5,
             MsgType::VersionMismatch => 6,
+            MsgType::ExternalAwacsModePassword => 7,
         })
     }
 }
@@ -328,6 +335,7 @@ impl<'de> ::serde::Deserialize<'de> for MsgType {
                     4 => Ok(MsgType::ServerSettings),
                     5 => Ok(MsgType::ClientDisconnect),
                     6 => Ok(MsgType::VersionMismatch),
+                    7 => Ok(MsgType::ExternalAwacsModePassword),
                     _ => Err(E::custom(format!(
                         "unknown {} value: {}",
                         stringify!(MsgType),
@@ -1,6 +1,6 @@
 use std::io::{self, Cursor, Read, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 use bytes::{BufMut, BytesMut};
 use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
 
@@ -71,13 +71,24 @@ impl Decoder for VoiceCodec {
     // a `None` from a stream means the stream is closed. This is planned to be fixed in tokio
     // 0.2.0. Until then, we are using an option item here instead, so the stream would return
     // `Some(None)` instead.
-    type Item = Option<VoicePacket>;
+    type Item = Option<Packet>;
     type Error = io::Error;
 
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // discard ping messages
+        // responses to our own ping messages
         if self.is_head && buf.len() == 22 {
-            return Ok(Some(None));
+            let mut sguid = [0; 22];
+            sguid.copy_from_slice(&buf.split_to(22));
+            return Ok(Some(Some(Packet::Ping(sguid))));
+        }
+
+        // each datagram contains a whole packet, so a datagram shorter than its length prefix is
+        // truncated (and must not leave the inner codec waiting for the rest of the packet)
+        if buf.len() < 2 || usize::from(LittleEndian::read_u16(&buf[..2])) > buf.len() {
+            return Err(invalid_data(format!(
+                "truncated voice packet of {} bytes",
+                buf.len()
+            )));
         }
 
         if let Some(bytes) = self.inner.decode(buf)? {
@@ -89,10 +100,13 @@ impl Decoder for VoiceCodec {
             let len_audio_part = rd.read_u16::<LittleEndian>()? as u64;
             let len_frequencies = rd.read_u16::<LittleEndian>()? as u64;
 
-            assert_eq!(
-                len,
-                4 + len_audio_part as u64 + len_frequencies + 4 + 8 + 22
-            );
+            let expected_len = 4 + len_audio_part + len_frequencies + 4 + 8 + 1 + 22 + 22;
+            if len != expected_len {
+                return Err(invalid_data(format!(
+                    "voice packet of {} bytes, expected {} bytes",
+                    len, expected_len
+                )));
+            }
 
             let mut audio_part = vec![0u8; len_audio_part as usize];
             rd.read_exact(&mut audio_part)?;
@@ -132,9 +146,15 @@ impl Decoder for VoiceCodec {
             let mut client_sguid = [0; 22];
             rd.read_exact(&mut client_sguid)?;
 
-            assert_eq!(rd.position(), len);
+            if rd.position() != len {
+                return Err(invalid_data(format!(
+                    "voice packet of {} bytes, but read {} bytes",
+                    len,
+                    rd.position()
+                )));
+            }
 
-            Ok(Some(Some(VoicePacket {
+            Ok(Some(Some(Packet::Voice(VoicePacket {
                 audio_part,
                 frequencies,
                 unit_id,
@@ -142,7 +162,7 @@ impl Decoder for VoiceCodec {
                 hop_count,
                 transmission_sguid,
                 client_sguid,
-            })))
+            }))))
         } else {
             self.is_head = false;
             Ok(Some(None))
@@ -221,8 +241,88 @@ impl Encoder<Packet> for VoiceCodec {
     }
 }
 
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
 impl From<VoicePacket> for Packet {
     fn from(p: VoicePacket) -> Self {
         Packet::Voice(p)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::{Encryption, Frequency, Modulation, Packet, VoiceCodec, VoicePacket};
+
+    fn encoded_packet() -> BytesMut {
+        let mut buf = BytesMut::new();
+        VoiceCodec::new()
+            .encode(
+                Packet::Voice(VoicePacket {
+                    audio_part: vec![1, 2, 3, 4],
+                    frequencies: vec![Frequency {
+                        freq: 251_000_000.0,
+                        modulation: Modulation::AM,
+                        encryption: Encryption::None,
+                    }],
+                    unit_id: 42,
+                    packet_id: 7,
+                    hop_count: 1,
+                    transmission_sguid: [b't'; 22],
+                    client_sguid: [b'c'; 22],
+                }),
+                &mut buf,
+            )
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_decode_voice_packet() {
+        let mut buf = encoded_packet();
+        match VoiceCodec::new().decode(&mut buf).unwrap() {
+            Some(Some(Packet::Voice(packet))) => {
+                assert_eq!(packet.audio_part, vec![1, 2, 3, 4]);
+                assert_eq!(packet.frequencies.len(), 1);
+                assert_eq!(packet.unit_id, 42);
+                assert_eq!(packet.packet_id, 7);
+                assert_eq!(packet.hop_count, 1);
+                assert_eq!(packet.transmission_sguid, [b't'; 22]);
+                assert_eq!(packet.client_sguid, [b'c'; 22]);
+            }
+            other => panic!("expected a voice packet, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_malformed_packets() {
+        let mut codec = VoiceCodec::new();
+
+        // truncated
+        let packet = encoded_packet();
+        let mut buf = BytesMut::from(&packet[..packet.len() - 5]);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // oversized: the length prefix covers more bytes than the packet's segments
+        let mut buf = encoded_packet();
+        let len = buf.len() as u16 + 3;
+        buf[..2].copy_from_slice(&len.to_le_bytes());
+        buf.extend_from_slice(&[0, 0, 0]);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // the codec still decodes valid packets afterwards
+        let mut buf = encoded_packet();
+        assert!(matches!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Some(Packet::Voice(_)))
+        ));
+    }
+}
@@ -1,16 +1,16 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::io;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::client::Client;
 use crate::message::{
-    Client as MsgClient, Coalition, GameMessage, Message, MsgType, Radio, RadioInfo,
-    RadioSwitchControls,
+    Client as MsgClient, Coalition, GameMessage, Message, MsgType, RadioInfo, RadioSwitchControls,
 };
 use crate::messages_codec::MessagesCodec;
 use crate::voice_codec::*;
@@ -19,21 +19,168 @@ use futures::future::FutureExt;
 use futures::select;
 use futures::sink::{Sink, SinkExt};
 use futures::stream::{SplitStream, Stream, StreamExt};
+use log::Level;
+use socket2::{Domain, Protocol, Socket, Type};
 use tokio::io::AsyncWriteExt;
 use tokio::net::{TcpStream, UdpSocket};
 use tokio::sync::oneshot::Receiver;
+use tokio::task;
 use tokio::time;
 use tokio_util::codec::{FramedRead, FramedWrite};
 use tokio_util::udp::UdpFramed;
 
 const SRS_VERSION: &str = "1.9.0.0";
+/// The UDP voice connection is considered blocked if no ping got a response for this many ping
+/// intervals.
+const MISSING_PONGS_THRESHOLD: u32 = 3;
+/// The number of outgoing voice packets that are queued until they are sent to the SRS server.
+/// Once the queue is full, the sink isn't ready to accept further packets until the queue is
+/// drained again.
+const VOICE_QUEUE_CAPACITY: usize = 32;
+/// How long to wait for the TCP connection to the SRS server to be established.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct VoiceStream {
     voice_sink: mpsc::Sender<Packet>,
     voice_stream: SplitStream<UdpFramed<VoiceCodec>>,
     heartbeat: Pin<Box<dyn Send + Future<Output = Result<(), anyhow::Error>>>>,
     client: Client,
-    packet_id: u64,
+    transmission: TransmissionControl,
+    frequency: FrequencyControl,
+    udp_health: Arc<Mutex<UdpHealth>>,
+    /// Whether the queue of outgoing voice packets is currently full.
+    saturated: bool,
+    /// How often the queue of outgoing voice packets got full.
+    saturations: u64,
+}
+
+/// Controls the `packet_id` sequence and the transmission sguid of a voice stream's outgoing
+/// packets. SRS treats voice packets with a continuous `packet_id` sequence as one transmission, so
+/// the sequence continues across everything sent to the stream (e.g. multiple audio files played
+/// back to back) until the transmission is deliberately ended. Can be retrieved before the stream
+/// is split, to control the transmissions of the split sink.
+#[derive(Debug, Clone)]
+pub struct TransmissionControl(Arc<TransmissionState>);
+
+#[derive(Debug)]
+struct TransmissionState {
+    packet_id: AtomicU64,
+    /// The sguid of the current transmission, if it differs from the client's sguid.
+    sguid: Mutex<Option<[u8; 22]>>,
+}
+
+impl TransmissionControl {
+    fn new() -> Self {
+        TransmissionControl(Arc::new(TransmissionState {
+            packet_id: AtomicU64::new(1),
+            sguid: Mutex::new(None),
+        }))
+    }
+
+    /// The `packet_id` of the next outgoing voice packet.
+    pub fn next_packet_id(&self) -> u64 {
+        self.0.packet_id.load(Ordering::Relaxed)
+    }
+
+    /// Sets the sguid of the current transmission (e.g. the sguid of the original sender when
+    /// relaying a transmission), which is otherwise the client's sguid. The client's sguid stays
+    /// the same. The sguid is reset to the client's one once the transmission is ended.
+    pub fn set_transmission_sguid(&self, sguid: &str) -> Result<(), anyhow::Error> {
+        let sguid = validate_sguid(sguid)?;
+        *self.0.sguid.lock().unwrap() = Some(sguid);
+        Ok(())
+    }
+
+    /// Ends the current transmission, so that the next voice packet starts a new one (the
+    /// `packet_id` sequence restarts at 1, and the transmission sguid is reset to the client's
+    /// sguid).
+    pub fn end_transmission(&self) {
+        self.0.packet_id.store(1, Ordering::Relaxed);
+        *self.0.sguid.lock().unwrap() = None;
+    }
+
+    fn take_packet_id(&self) -> u64 {
+        self.0.packet_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn transmission_sguid(&self) -> Option<[u8; 22]> {
+        *self.0.sguid.lock().unwrap()
+    }
+}
+
+/// Retunes the frequency a voice stream transmits on while it is running, e.g. to share one SRS
+/// connection between multiple stations. Subsequent voice packets are sent on the new frequency,
+/// and the client's radio info is updated accordingly. Can be retrieved before the stream is split.
+#[derive(Debug, Clone)]
+pub struct FrequencyControl {
+    freq: Arc<AtomicU64>,
+    retuned: mpsc::UnboundedSender<u64>,
+}
+
+impl FrequencyControl {
+    /// The frequency the next outgoing voice packet is sent on.
+    pub fn freq(&self) -> u64 {
+        self.freq.load(Ordering::Relaxed)
+    }
+
+    /// Retunes the stream to the given frequency. Should not be called in the middle of a
+    /// transmission.
+    pub fn retune(&self, freq: u64) {
+        if self.freq.swap(freq, Ordering::Relaxed) != freq {
+            // the heartbeat is gone once the stream is closed, which is noticed by the stream's
+            // user anyway
+            let _ = self.retuned.unbounded_send(freq);
+        }
+    }
+}
+
+/// SRS sguids are 22 characters long (a base64 encoded UUID).
+fn validate_sguid(sguid: &str) -> Result<[u8; 22], anyhow::Error> {
+    if sguid.len() != 22 || !sguid.is_ascii() {
+        return Err(anyhow!(
+            "sguid `{}` must consist of exactly 22 ASCII characters",
+            sguid
+        ));
+    }
+    let mut bytes = [0; 22];
+    bytes.clone_from_slice(sguid.as_bytes());
+    Ok(bytes)
+}
+
+/// Tracks the server's responses to voice pings to detect whether the UDP voice connection is
+/// blocked (while the TCP connection is still up).
+#[derive(Debug)]
+struct UdpHealth {
+    last_pong: Instant,
+    healthy: bool,
+}
+
+impl UdpHealth {
+    fn new(now: Instant) -> Self {
+        UdpHealth {
+            last_pong: now,
+            healthy: true,
+        }
+    }
+
+    /// Returns `true` if the connection has been unhealthy before, and is thus recovered now.
+    fn pong_received(&mut self, now: Instant) -> bool {
+        self.last_pong = now;
+        let recovered = !self.healthy;
+        self.healthy = true;
+        recovered
+    }
+
+    /// Returns `true` if the connection has just become unhealthy, because no pong has been
+    /// received within the given `timeout`.
+    fn check(&mut self, now: Instant, timeout: Duration) -> bool {
+        if self.healthy && now.duration_since(self.last_pong) > timeout {
+            self.healthy = false;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -51,9 +198,7 @@ impl VoiceStream {
         game_source: Option<mpsc::UnboundedReceiver<GameMessage>>,
         shutdown_signal: Receiver<()>,
     ) -> Result<Self, io::Error> {
-        let recv_voice = game_source.is_some();
-
-        let tcp = TcpStream::connect(addr).await?;
+        let tcp = connect_tcp(addr, &client).await?;
         let (stream, sink) = tcp.into_split();
         let mut messages_sink = FramedWrite::new(sink, MessagesCodec::new());
         let messages_stream = FramedRead::new(stream, MessagesCodec::new());
@@ -63,28 +208,58 @@ impl VoiceStream {
             distance_enabled: AtomicBool::new(false),
         }));
 
-        let local_addr: SocketAddr = "0.0.0.0:0".parse().unwrap();
-        let udp = UdpSocket::bind(local_addr).await?;
+        let udp = UdpSocket::from_std(bind_udp_socket(&addr, &client)?)?;
         udp.connect(addr).await?;
         let (mut voice_sink, voice_stream) = UdpFramed::new(udp, VoiceCodec::new()).split();
-        let (mut tx, mut rx) = mpsc::channel(32);
+        let (mut tx, mut rx) = mpsc::channel(VOICE_QUEUE_CAPACITY);
         let tx2 = tx.clone();
 
+        let recv_voice = game_source.is_some();
+        let check_udp = client.reconnect_on_blocked_udp();
+        let udp_health = Arc::new(Mutex::new(UdpHealth::new(Instant::now())));
+        let udp_health2 = udp_health.clone();
+
+        let (retuned_tx, mut retuned) = mpsc::unbounded();
+        let frequency = FrequencyControl {
+            freq: client.freq_handle(),
+            retuned: retuned_tx,
+        };
+
         let client2 = client.clone();
         let heartbeat = async move {
+            let mut client = client;
+            let mut advertised_freq = client.freq();
             let mut messages_stream = messages_stream.fuse();
 
+            // the coalition is changed once the server accepted the coalition password
+            let mut coalition = Coalition::Blue;
+
             // send sync message to receive server settings
-            messages_sink.send(create_sync_message(&client)).await?;
+            messages_sink
+                .send(create_sync_message(&client, coalition))
+                .await?;
+
+            // authenticate for a coalition if a coalition password is set
+            if let Some(password) = client.coalition_password() {
+                messages_sink
+                    .send(create_coalition_password_message(&client, password))
+                    .await?;
+            }
 
             // send initial Update message
             messages_sink
-                .send(create_radio_update_message(&client))
+                .send(create_radio_update_message(&client, coalition))
                 .await?;
 
             let mut old_pos = client.position();
-            let mut position_update_interval = time::interval(Duration::from_secs(60)).fuse();
-            let mut voice_ping_interval = time::interval(Duration::from_secs(5)).fuse();
+            let mut position_update_interval =
+                time::interval(client.position_update_interval()).fuse();
+            let mut radio_update_interval = time::interval_at(
+                time::Instant::now() + client.radio_update_interval(),
+                client.radio_update_interval(),
+            )
+            .fuse();
+            let mut voice_ping_interval = time::interval(client.voice_ping_interval()).fuse();
             let mut game_source_interval = time::interval(Duration::from_secs(5)).fuse();
             let mut shutdown_signal = shutdown_signal.fuse();
             let mut last_game_msg = None;
@@ -105,11 +280,11 @@ impl VoiceStream {
                             // update server settings
                             if let Some(settings) = msg.server_settings {
                                 server_settings.0.los_enabled.store(
-                                    settings.get("LOS_ENABLED").map(|s| s.as_str()) == Some("True"),
+                                    is_setting_enabled(&settings, "LOS_ENABLED"),
                                     Ordering::Relaxed,
                                 );
                                 server_settings.0.distance_enabled.store(
-                                    settings.get("DISTANCE_ENABLED").map(|s| s.as_str()) == Some("true"),
+                                    is_setting_enabled(&settings, "DISTANCE_ENABLED"),
                                     Ordering::Relaxed,
                                 );
                             }
@@ -123,13 +298,42 @@ impl VoiceStream {
                                         msg.version
                                     ));
                                 }
+                                MsgType::ExternalAwacsModePassword => {
+                                    // the server responds with the spectator coalition if the
+                                    // password has been rejected
+                                    match msg.client.map(|c| c.coalition) {
+                                        Some(Coalition::Spectator) | None => {
+                                            return Err(anyhow!(
+                                                "The SRS server rejected the coalition password of {}",
+                                                client.name()
+                                            ));
+                                        }
+                                        Some(c) => {
+                                            client_log!(
+                                                client,
+                                                Level::Debug,
+                                                "{} joined coalition {:?}",
+                                                client.name(),
+                                                c
+                                            );
+                                            coalition = c;
+                                            messages_sink
+                                                .send(create_radio_update_message(&client, coalition))
+                                                .await?;
+                                        }
+                                    }
+                                }
                                 _ => {
                                     // discard other messages for now
                                 }
                             }
                         } else {
-                            log::debug!("Messages stream was closed, closing voice stream");
-                            break;
+                            client_log!(
+                                client,
+                                Level::Debug,
+                                "Messages stream was closed, closing voice stream"
+                            );
+                            return Err(anyhow!("TCP connection was closed unexpectedly"));
                         }
                     }
 
@@ -146,15 +350,54 @@ impl VoiceStream {
                         let los_enabled = server_settings.0.los_enabled.load(Ordering::Relaxed);
                         let distance_enabled = server_settings.0.distance_enabled.load(Ordering::Relaxed);
                         if (los_enabled || distance_enabled) && new_pos != old_pos {
-                            log::debug!(
+                            client_log!(
+                                client,
+                                Level::Debug,
                                 "Position of {} changed, sending a new update message",
                                 client.name()
                             );
-                            messages_sink.send(create_update_message(&client)).await?;
+                            messages_sink.send(create_update_message(&client, coalition)).await?;
                             old_pos = new_pos;
                         }
                     }
 
+                    // Periodically re-send the radio info, even if nothing has changed, to not get
+                    // removed from the server's client list. If `game_source` is set, this is
+                    // already done with each received `GameMessage`.
+                    _ = radio_update_interval.next() => {
+                        if send_client_position_updates {
+                            messages_sink.send(create_radio_update_message(&client, coalition)).await?;
+                        }
+                    }
+
+                    // Advertise the frequency the stream has been retuned to. Retunes that happened
+                    // in quick succession are coalesced into a single radio update, to not flood
+                    // the server with updates when the frequency is switched frequently.
+                    freq = retuned.next() => {
+                        if let Some(mut freq) = freq {
+                            while let Ok(Some(next)) = retuned.try_next() {
+                                freq = next;
+                            }
+                            if freq == advertised_freq {
+                                continue;
+                            }
+
+                            client_log!(
+                                client,
+                                Level::Debug,
+                                "{} retuned from {} to {}",
+                                client.name(),
+                                advertised_freq,
+                                freq
+                            );
+                            client.retune_radios(advertised_freq, freq);
+                            advertised_freq = freq;
+                            if send_client_position_updates {
+                                messages_sink.send(create_radio_update_message(&client, coalition)).await?;
+                            }
+                        }
+                    }
+
                     msg = game_source.next() => {
                         if let Some(msg) = msg {
                             last_game_msg = Some(msg);
@@ -163,13 +406,44 @@ impl VoiceStream {
 
                     _ = game_source_interval.next() => {
                         if let Some(msg) = &last_game_msg {
-                            messages_sink.send(radio_message_from_game(&client, msg)).await?;
+                            messages_sink.send(radio_message_from_game(&client, msg, coalition)).await?;
                         }
                     }
 
+                    // The pings register the client for receiving voice. If enabled, they are also
+                    // used to check whether the UDP voice connection works, as the server responds
+                    // to them.
                     _ = voice_ping_interval.next() => {
-                        if recv_voice {
-                            tx.send(Packet::Ping(sguid.clone())).await?;
+                        if recv_voice || check_udp {
+                            // Don't wait for free capacity here, as the queue is drained by this
+                            // very loop. Skipping a ping is fine, since the server keeps the client
+                            // registered by the queued voice packets anyway.
+                            if let Err(err) = tx.try_send(Packet::Ping(sguid.clone())) {
+                                if err.is_disconnected() {
+                                    return Err(err.into_send_error().into());
+                                }
+                                client_log!(
+                                    client,
+                                    Level::Debug,
+                                    "{} skipped a voice ping, the voice queue is full",
+                                    client.name()
+                                );
+                            }
+                        }
+
+                        if check_udp {
+                            let timeout = client.voice_ping_interval() * MISSING_PONGS_THRESHOLD;
+                            let blocked = udp_health.lock().unwrap().check(Instant::now(), timeout);
+                            if blocked {
+                                client_log!(
+                                    client,
+                                    Level::Warn,
+                                    "{} didn't receive any response to its voice pings for {}s, the UDP voice connection to the SRS server seems to be blocked",
+                                    client.name(),
+                                    timeout.as_secs()
+                                );
+                                return Err(anyhow!("UDP voice connection seems to be blocked"));
+                            }
                         }
                     }
 
@@ -180,7 +454,14 @@ impl VoiceStream {
                     }
 
                     _ = shutdown_signal => {
-                        messages_sink.into_inner().shutdown();
+                        // send voice packets that are already queued before disconnecting
+                        while let Ok(Some(p)) = rx.try_next() {
+                            voice_sink.send((p, addr)).await?;
+                        }
+
+                        client_log!(client, Level::Debug, "Shutting down {}", client.name());
+                        messages_sink.flush().await?;
+                        messages_sink.into_inner().shutdown().await?;
                         break;
                     }
                 }
@@ -194,9 +475,23 @@ impl VoiceStream {
             voice_sink: tx2,
             heartbeat: Box::pin(heartbeat),
             client: client2,
-            packet_id: 1,
+            transmission: TransmissionControl::new(),
+            frequency,
+            udp_health: udp_health2,
+            saturated: false,
+            saturations: 0,
         })
     }
+
+    /// Returns the handle to control the transmissions of the stream's outgoing voice packets.
+    pub fn transmission_control(&self) -> TransmissionControl {
+        self.transmission.clone()
+    }
+
+    /// Returns the handle to retune the frequency of the stream's outgoing voice packets.
+    pub fn frequency_control(&self) -> FrequencyControl {
+        self.frequency.clone()
+    }
 }
 
 impl Stream for VoiceStream {
@@ -205,26 +500,48 @@ impl Stream for VoiceStream {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         let s = self.get_mut();
 
-        match s.voice_stream.poll_next_unpin(cx) {
-            Poll::Pending => {}
-            Poll::Ready(None) => {
-                return Poll::Ready(Some(Err(anyhow!("voice stream was closed unexpectedly"))))
-            }
-            Poll::Ready(Some(Ok((None, _)))) => {
-                // not enough data for the codec to create a new item
-            }
-            Poll::Ready(Some(Ok((Some(p), _)))) => {
-                return Poll::Ready(Some(Ok(p)));
+        loop {
+            match s.voice_stream.poll_next_unpin(cx) {
+                Poll::Pending => break,
+                Poll::Ready(None) => {
+                    return Poll::Ready(Some(Err(anyhow!("voice stream was closed unexpectedly"))))
+                }
+                Poll::Ready(Some(Ok((None, _)))) => {
+                    // not enough data for the codec to create a new item
+                }
+                Poll::Ready(Some(Ok((Some(Packet::Ping(_)), _)))) => {
+                    let recovered = s.udp_health.lock().unwrap().pong_received(Instant::now());
+                    if recovered {
+                        client_log!(
+                            s.client,
+                            Level::Info,
+                            "{} received a response to its voice pings again, the UDP voice connection recovered",
+                            s.client.name()
+                        );
+                    }
+                }
+                Poll::Ready(Some(Ok((Some(Packet::Voice(p)), _)))) => {
+                    return Poll::Ready(Some(Ok(p)));
+                }
+                Poll::Ready(Some(Err(err))) if err.kind() == io::ErrorKind::InvalidData => {
+                    // drop malformed packets instead of failing the whole voice stream
+                    client_log!(
+                        s.client,
+                        Level::Debug,
+                        "{} dropped a malformed voice packet: {}",
+                        s.client.name(),
+                        err
+                    );
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
             }
-            Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
         }
 
         match s.heartbeat.poll_unpin(cx) {
             Poll::Pending => {}
             Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
-            Poll::Ready(Ok(_)) => {
-                return Poll::Ready(Some(Err(anyhow!("TCP connection was closed unexpectedly"))));
-            }
+            // the heartbeat only completes successfully after a shutdown signal
+            Poll::Ready(Ok(_)) => return Poll::Ready(None),
         }
 
         Poll::Pending
@@ -234,17 +551,45 @@ impl Stream for VoiceStream {
 impl Sink<Vec<u8>> for VoiceStream {
     type Error = mpsc::SendError;
 
+    /// Is pending while the queue of outgoing voice packets is full (e.g. due to a slow network),
+    /// so that senders wait for the queue to be drained instead of packets being dropped.
     fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
         let s = self.get_mut();
-        Pin::new(&mut s.voice_sink).poll_ready(cx)
+        match Pin::new(&mut s.voice_sink).poll_ready(cx) {
+            Poll::Pending => {
+                if !s.saturated {
+                    s.saturated = true;
+                    s.saturations += 1;
+                    client_log!(
+                        s.client,
+                        Level::Warn,
+                        "{} voice queue is full, waiting for it to be sent to the SRS server (saturated {} times)",
+                        s.client.name(),
+                        s.saturations
+                    );
+                }
+                Poll::Pending
+            }
+            ready => {
+                if s.saturated {
+                    s.saturated = false;
+                    client_log!(
+                        s.client,
+                        Level::Info,
+                        "{} voice queue is drained again",
+                        s.client.name()
+                    );
+                }
+                ready
+            }
+        }
     }
 
     fn start_send(self: Pin<&mut Self>, item: Vec<u8>) -> Result<(), Self::Error> {
         let mut sguid = [0; 22];
         sguid.clone_from_slice(self.client.sguid().as_bytes());
         let mut m = Modulation::AM;
-        if self.client.m() == "FM"
-        {
+        if self.client.m() == "FM" {
             m = Modulation::FM;
         }
         let packet = VoicePacket {
@@ -255,15 +600,13 @@ impl Sink<Vec<u8>> for VoiceStream {
                 encryption: Encryption::None,
             }],
             unit_id: self.client.unit().map(|u| u.id).unwrap_or(0),
-            packet_id: self.packet_id,
-            hop_count: 0,
-            transmission_sguid: sguid,
+            packet_id: self.transmission.take_packet_id(),
+            hop_count: self.client.hop_count(),
+            transmission_sguid: self.transmission.transmission_sguid().unwrap_or(sguid),
             client_sguid: sguid,
         };
 
         let s = self.get_mut();
-        s.packet_id = s.packet_id.wrapping_add(1);
-
         Pin::new(&mut s.voice_sink).start_send(packet.into())
     }
 
@@ -278,75 +621,200 @@ impl Sink<Vec<u8>> for VoiceStream {
     }
 }
 
-fn create_radio_update_message(client: &Client) -> Message {
+/// Returns the local address the sockets are bound to, which must be of the same address family as
+/// the SRS server's address. Unless a local address is configured, the unspecified address (any
+/// interface) is used.
+fn local_bind_addr(
+    server_addr: &SocketAddr,
+    local_ip: Option<IpAddr>,
+) -> Result<SocketAddr, io::Error> {
+    let ip = match (local_ip, server_addr) {
+        (Some(ip), _) if ip.is_ipv4() == server_addr.is_ipv4() => ip,
+        (Some(ip), _) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the local address {} is not of the same address family as the SRS server address {}",
+                    ip, server_addr
+                ),
+            ))
+        }
+        (None, SocketAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        (None, SocketAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    Ok(SocketAddr::new(ip, 0))
+}
+
+fn domain(addr: &SocketAddr) -> Domain {
+    match addr {
+        SocketAddr::V4(_) => Domain::ipv4(),
+        SocketAddr::V6(_) => Domain::ipv6(),
+    }
+}
+
+fn bind(socket: &Socket, addr: SocketAddr) -> Result<(), io::Error> {
+    socket.bind(&addr.into()).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("failed to bind to local address {}: {}", addr.ip(), err),
+        )
+    })
+}
+
+/// Connects to the SRS server, from the client's local address if one is configured. Gives up
+/// after `CONNECT_TIMEOUT`.
+async fn connect_tcp(server_addr: SocketAddr, client: &Client) -> Result<TcpStream, io::Error> {
+    let local_ip = match client.local_addr() {
+        Some(ip) => ip,
+        None => {
+            return time::timeout(CONNECT_TIMEOUT, TcpStream::connect(server_addr))
+                .await
+                .map_err(|_| {
+                    io::Error::new(io::ErrorKind::TimedOut, "connecting to SRS timed out")
+                })?
+        }
+    };
+
+    let socket = Socket::new(domain(&server_addr), Type::stream(), Some(Protocol::tcp()))?;
+    bind(&socket, local_bind_addr(&server_addr, Some(local_ip))?)?;
+    // tokio cannot connect an already bound socket, so connect it on the blocking thread pool
+    let tcp = task::spawn_blocking(move || {
+        socket.connect_timeout(&server_addr.into(), CONNECT_TIMEOUT)?;
+        Ok::<_, io::Error>(socket.into_tcp_stream())
+    })
+    .await
+    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))??;
+    tcp.set_nonblocking(true)?;
+    TcpStream::from_std(tcp)
+}
+
+/// Creates the UDP voice socket, bound to the client's local address (or any local address of the
+/// SRS server's address family), and applies the client's send and receive buffer sizes.
+fn bind_udp_socket(
+    server_addr: &SocketAddr,
+    client: &Client,
+) -> Result<std::net::UdpSocket, io::Error> {
+    let socket = Socket::new(domain(server_addr), Type::dgram(), Some(Protocol::udp()))?;
+    if let Some(size) = client.udp_send_buffer_size() {
+        socket.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = client.udp_recv_buffer_size() {
+        socket.set_recv_buffer_size(size)?;
+    }
+    bind(&socket, local_bind_addr(server_addr, client.local_addr())?)?;
+    // tokio expects the socket to be non-blocking
+    socket.set_nonblocking(true)?;
+
+    // the OS might have adjusted the configured sizes, so log the effective ones
+    let send_buffer_size = socket.send_buffer_size()?;
+    let recv_buffer_size = socket.recv_buffer_size()?;
+    if client.udp_send_buffer_size().is_some() || client.udp_recv_buffer_size().is_some() {
+        client_log!(
+            client,
+            Level::Info,
+            "{} UDP buffer sizes: send {} bytes, receive {} bytes",
+            client.name(),
+            send_buffer_size,
+            recv_buffer_size
+        );
+    } else {
+        client_log!(
+            client,
+            Level::Debug,
+            "{} UDP buffer sizes (OS defaults): send {} bytes, receive {} bytes",
+            client.name(),
+            send_buffer_size,
+            recv_buffer_size
+        );
+    }
+
+    Ok(socket.into_udp_socket())
+}
+
+/// Server settings are booleans serialized by C#, thus `True` or `False`.
+fn is_setting_enabled(settings: &HashMap<String, String>, key: &str) -> bool {
+    settings
+        .get(key)
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+fn create_radio_update_message(client: &Client, coalition: Coalition) -> Message {
     let pos = client.position();
     Message {
         client: Some(MsgClient {
             client_guid: client.sguid().to_string(),
-            name: Some(client.name().to_string()),
-            coalition: Coalition::Blue,
+            name: Some(client.display_name()),
+            coalition,
             radio_info: Some(RadioInfo {
                 name: "DATIS Radios".to_string(),
                 ptt: false,
                 // TODO: enable one of the radios to receive voice
-                radios: std::iter::repeat_with(Radio::default).take(10).collect(),
+                radios: client.radios().to_vec(),
                 control: crate::message::RadioSwitchControls::Hotas,
                 selected: 0,
                 unit: client
                     .unit()
                     .map(|u| u.name.clone())
-                    .unwrap_or_else(|| client.name().to_string()),
+                    .unwrap_or_else(|| client.display_name()),
                 unit_id: client.unit().as_ref().map(|u| u.id).unwrap_or(0),
-                simultaneous_transmission: true,
+                simultaneous_transmission: client.simultaneous_transmission(),
             }),
             lat_lng_position: Some(pos.clone()),
         }),
         msg_type: MsgType::RadioUpdate,
         server_settings: None,
+        external_awacs_mode_password: None,
         version: SRS_VERSION.to_string(),
     }
 }
 
-fn create_update_message(client: &Client) -> Message {
+fn create_update_message(client: &Client, coalition: Coalition) -> Message {
     let pos = client.position();
     Message {
         client: Some(MsgClient {
             client_guid: client.sguid().to_string(),
-            name: Some(client.name().to_string()),
-            coalition: Coalition::Blue,
+            name: Some(client.display_name()),
+            coalition,
             radio_info: None,
             lat_lng_position: Some(pos.clone()),
         }),
         msg_type: MsgType::Update,
         server_settings: None,
+        external_awacs_mode_password: None,
         version: SRS_VERSION.to_string(),
     }
 }
 
-fn create_sync_message(client: &Client) -> Message {
+fn create_sync_message(client: &Client, coalition: Coalition) -> Message {
     let pos = client.position();
     Message {
         client: Some(MsgClient {
             client_guid: client.sguid().to_string(),
-            name: Some(client.name().to_string()),
-            coalition: Coalition::Blue,
+            name: Some(client.display_name()),
+            coalition,
             radio_info: None,
             lat_lng_position: Some(pos.clone()),
         }),
         msg_type: MsgType::Sync,
         server_settings: None,
+        external_awacs_mode_password: None,
         version: SRS_VERSION.to_string(),
     }
 }
 
-fn radio_message_from_game(client: &Client, game_message: &GameMessage) -> Message {
+fn radio_message_from_game(
+    client: &Client,
+    game_message: &GameMessage,
+    coalition: Coalition,
+) -> Message {
     let pos = game_message.lat_lng_position.clone();
 
     Message {
         client: Some(MsgClient {
             client_guid: client.sguid().to_string(),
             name: Some(game_message.name.clone()),
-            coalition: Coalition::Blue,
+            coalition,
             radio_info: Some(RadioInfo {
                 name: game_message.name.clone(),
                 ptt: game_message.ptt,
@@ -355,12 +823,628 @@ fn radio_message_from_game(client: &Client, game_message: &GameMessage) -> Messa
                 selected: game_message.selected,
                 unit: game_message.unit.clone(),
                 unit_id: game_message.unit_id,
-                simultaneous_transmission: true,
+                simultaneous_transmission: client.simultaneous_transmission(),
             }),
             lat_lng_position: Some(pos.clone()),
         }),
         msg_type: MsgType::RadioUpdate,
         server_settings: None,
+        external_awacs_mode_password: None,
+        version: SRS_VERSION.to_string(),
+    }
+}
+
+fn create_coalition_password_message(client: &Client, password: &str) -> Message {
+    let pos = client.position();
+    Message {
+        client: Some(MsgClient {
+            client_guid: client.sguid().to_string(),
+            name: Some(client.display_name()),
+            coalition: Coalition::Spectator,
+            radio_info: None,
+            lat_lng_position: Some(pos.clone()),
+        }),
+        msg_type: MsgType::ExternalAwacsModePassword,
+        server_settings: None,
+        external_awacs_mode_password: Some(password.to_string()),
         version: SRS_VERSION.to_string(),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message;
+    use tokio::net::{TcpListener, UdpSocket};
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn test_coalition_password_message() {
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        client.set_coalition_password("secret");
+
+        let msg = create_coalition_password_message(&client, client.coalition_password().unwrap());
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["MsgType"], 7);
+        assert_eq!(json["ExternalAWACSModePassword"], "secret");
+        assert_eq!(json["Client"]["ClientGuid"], client.sguid());
+    }
+
+    #[test]
+    fn test_name_affixes() {
+        let mut client = Client::new("ATIS Batumi", 251_000_000, "AM");
+        client.set_name_affixes("[Training] ", "");
+
+        let msg = create_radio_update_message(&client, Coalition::Blue);
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["Client"]["Name"], "[Training] ATIS Batumi");
+        assert_eq!(
+            json["Client"]["RadioInfo"]["unit"],
+            "[Training] ATIS Batumi"
+        );
+        // the name used for everything else (like logging) is left untouched
+        assert_eq!(client.name(), "ATIS Batumi");
+
+        client.set_name_affixes("", " (2)");
+        assert_eq!(client.display_name(), "ATIS Batumi (2)");
+    }
+
+    #[test]
+    fn test_name_template() {
+        let mut client = Client::new("ATIS Batumi", 251_000_000, "AM");
+        client
+            .set_name_template("{name} ({freq} {modulation})")
+            .unwrap();
+
+        let msg = create_radio_update_message(&client, Coalition::Blue);
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["Client"]["Name"], "ATIS Batumi (251.00 AM)");
+        // the name used for everything else (like logging) is left untouched
+        assert_eq!(client.name(), "ATIS Batumi");
+
+        // the affixes are added around the formatted name
+        client.set_name_affixes("[Training] ", "");
+        assert_eq!(client.display_name(), "[Training] ATIS Batumi (251.00 AM)");
+
+        assert!(client.set_name_template("").is_err());
+        assert!(client.set_name_template("{name} {callsign}").is_err());
+        assert!(client.set_name_template("{name").is_err());
+        assert_eq!(client.display_name(), "[Training] ATIS Batumi (251.00 AM)");
+    }
+
+    #[test]
+    fn test_radio_layout() {
+        let mut client = Client::new("ATIS Batumi", 251_000_000, "AM");
+        let json =
+            serde_json::to_value(&create_radio_update_message(&client, Coalition::Blue)).unwrap();
+        let radios = json["Client"]["RadioInfo"]["radios"].as_array().unwrap();
+        assert_eq!(radios.len(), 10);
+
+        client
+            .set_radios(vec![
+                message::Radio::default(),
+                message::Radio {
+                    freq: 251_000_000.0,
+                    modulation: message::Modulation::AM,
+                    name: "ATIS".to_string(),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        let json =
+            serde_json::to_value(&create_radio_update_message(&client, Coalition::Blue)).unwrap();
+        let radios = json["Client"]["RadioInfo"]["radios"].as_array().unwrap();
+        assert_eq!(radios.len(), 2);
+        assert_eq!(radios[1]["freq"], 251_000_000.0);
+        assert_eq!(radios[1]["modulation"], 0);
+        assert_eq!(radios[1]["name"], "ATIS");
+
+        assert!(client.set_radios(Vec::new()).is_err());
+        assert_eq!(client.radios().len(), 2);
+    }
+
+    #[test]
+    fn test_frequency_control() {
+        let mut client = Client::new("ATIS Batumi", 251_000_000, "AM");
+        client
+            .set_radios(vec![
+                message::Radio::default(),
+                message::Radio {
+                    freq: 251_000_000.0,
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded();
+        let frequency = FrequencyControl {
+            freq: client.freq_handle(),
+            retuned: tx,
+        };
+        frequency.retune(131_500_000);
+        assert_eq!(frequency.freq(), 131_500_000);
+        assert_eq!(client.freq(), 131_500_000);
+        assert_eq!(rx.try_next().unwrap(), Some(131_500_000));
+
+        // retuning to the current frequency doesn't cause a radio update
+        frequency.retune(131_500_000);
+        assert!(rx.try_next().is_err());
+
+        client.retune_radios(251_000_000, 131_500_000);
+        assert_eq!(client.radios()[0].freq, message::Radio::default().freq);
+        assert_eq!(client.radios()[1].freq, 131_500_000.0);
+    }
+
+    #[test]
+    fn test_simultaneous_transmission() {
+        let mut client = Client::new("ATIS Batumi", 251_000_000, "AM");
+        let game_message = GameMessage {
+            control: 0,
+            name: "Player".to_string(),
+            lat_lng_position: message::LatLngPosition::default(),
+            ptt: false,
+            radios: Vec::new(),
+            selected: 0,
+            unit: "F-16C".to_string(),
+            unit_id: 1,
+        };
+        let flags = |client: &Client| {
+            let radio_update = create_radio_update_message(client, Coalition::Blue);
+            let from_game = radio_message_from_game(client, &game_message, Coalition::Blue);
+            let flag = |msg: &Message| {
+                serde_json::to_value(msg).unwrap()["Client"]["RadioInfo"]
+                    ["simultaneousTransmission"]
+                    .as_bool()
+            };
+            (flag(&radio_update), flag(&from_game))
+        };
+
+        assert_eq!(flags(&client), (Some(true), Some(true)));
+        client.set_simultaneous_transmission(false);
+        assert_eq!(flags(&client), (Some(false), Some(false)));
+    }
+
+    #[test]
+    fn test_server_settings() {
+        let mut settings = HashMap::new();
+        settings.insert("LOS_ENABLED".to_string(), "True".to_string());
+        settings.insert("DISTANCE_ENABLED".to_string(), "false".to_string());
+        assert!(is_setting_enabled(&settings, "LOS_ENABLED"));
+        assert!(!is_setting_enabled(&settings, "DISTANCE_ENABLED"));
+        assert!(!is_setting_enabled(&settings, "IRL_RADIO_TX"));
+    }
+
+    #[test]
+    fn test_no_password_in_other_messages() {
+        let client = Client::new("Test", 251_000_000, "AM");
+        let json = serde_json::to_value(&create_sync_message(&client, Coalition::Blue)).unwrap();
+        assert!(json.get("ExternalAWACSModePassword").is_none());
+        assert_eq!(json["Client"]["Coalition"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_radio_info_is_re_sent() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        client
+            .set_radio_update_interval(Duration::from_millis(50))
+            .unwrap();
+        let (_tx, rx) = oneshot::channel();
+        let stream = VoiceStream::new(client, addr, None, rx).await.unwrap();
+        tokio::spawn(stream.for_each(|_| async {}));
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut messages = FramedRead::new(socket, MessagesCodec::new());
+
+        // the initial radio update, and at least one re-sent one
+        let mut radio_updates = 0;
+        while radio_updates < 2 {
+            let msg = time::timeout(Duration::from_secs(1), messages.next())
+                .await
+                .expect("radio update not re-sent")
+                .unwrap()
+                .unwrap();
+            if msg.msg_type == MsgType::RadioUpdate {
+                radio_updates += 1;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_voice_ping_interval() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut udp = UdpSocket::bind(addr).await.unwrap();
+
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        client
+            .set_voice_ping_interval(Duration::from_millis(50))
+            .unwrap();
+        let (_tx, rx) = oneshot::channel();
+        // pings are only sent if voice is received, which is the case if a game source is set
+        let (_game_tx, game_source) = mpsc::unbounded();
+        let stream = VoiceStream::new(client, addr, Some(game_source), rx)
+            .await
+            .unwrap();
+        tokio::spawn(stream.for_each(|_| async {}));
+        let _socket = listener.accept().await.unwrap();
+
+        // the default interval of 5s would exceed the timeout
+        let mut buf = [0; 64];
+        for _ in 0..3 {
+            let len = time::timeout(Duration::from_secs(1), udp.recv(&mut buf))
+                .await
+                .expect("no ping received")
+                .unwrap();
+            assert_eq!(len, 22);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_position_update_interval() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        client
+            .set_position_update_interval(Duration::from_millis(50))
+            .unwrap();
+        let position = client.position_handle();
+        let (_tx, rx) = oneshot::channel();
+        let stream = VoiceStream::new(client, addr, None, rx).await.unwrap();
+        tokio::spawn(stream.for_each(|_| async {}));
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let (read, write) = socket.into_split();
+        let mut messages = FramedRead::new(read, MessagesCodec::new());
+        let mut sink = FramedWrite::new(write, MessagesCodec::new());
+
+        // position updates are only sent if LOS is enabled
+        let mut settings = HashMap::new();
+        settings.insert("LOS_ENABLED".to_string(), "True".to_string());
+        sink.send(Message {
+            client: None,
+            msg_type: MsgType::ServerSettings,
+            server_settings: Some(settings),
+            external_awacs_mode_password: None,
+            version: SRS_VERSION.to_string(),
+        })
+        .await
+        .unwrap();
+        {
+            let mut pos = position.write().unwrap();
+            pos.lat = 42.0;
+        }
+
+        // the default interval of 60s would exceed the timeout
+        loop {
+            let msg = time::timeout(Duration::from_secs(1), messages.next())
+                .await
+                .expect("position update not sent")
+                .unwrap()
+                .unwrap();
+            if msg.msg_type == MsgType::Update {
+                assert_eq!(msg.client.unwrap().lat_lng_position.unwrap().lat, 42.0);
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_continuous_packet_ids() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let udp = UdpSocket::bind(addr).await.unwrap();
+
+        let client = Client::new("Test", 251_000_000, "AM");
+        let (_tx, rx) = oneshot::channel();
+        let stream = VoiceStream::new(client, addr, None, rx).await.unwrap();
+        let transmission = stream.transmission_control();
+        let (mut sink, stream) = stream.split();
+        tokio::spawn(stream.for_each(|_| async {}));
+        let _socket = listener.accept().await.unwrap();
+        let mut voice = UdpFramed::new(udp, VoiceCodec::new());
+
+        // two files played back to back are a single transmission
+        let files = vec![vec![vec![1], vec![2]], vec![vec![3], vec![4], vec![5]]];
+        for file in files {
+            for frame in file {
+                sink.send(frame).await.unwrap();
+            }
+        }
+        assert_eq!(transmission.next_packet_id(), 6);
+
+        // a deliberately ended transmission restarts the sequence
+        transmission.end_transmission();
+        sink.send(vec![6]).await.unwrap();
+
+        let mut packet_ids = Vec::new();
+        while packet_ids.len() < 6 {
+            let (packet, _) = time::timeout(Duration::from_secs(1), voice.next())
+                .await
+                .expect("voice packet not received")
+                .unwrap()
+                .unwrap();
+            if let Some(Packet::Voice(packet)) = packet {
+                packet_ids.push(packet.packet_id);
+            }
+        }
+        assert_eq!(packet_ids, vec![1, 2, 3, 4, 5, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_hop_count() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let udp = UdpSocket::bind(addr).await.unwrap();
+
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        client.set_hop_count(2);
+        let (_tx, rx) = oneshot::channel();
+        let stream = VoiceStream::new(client, addr, None, rx).await.unwrap();
+        let (mut sink, stream) = stream.split();
+        tokio::spawn(stream.for_each(|_| async {}));
+        let _socket = listener.accept().await.unwrap();
+        let mut voice = UdpFramed::new(udp, VoiceCodec::new());
+
+        sink.send(vec![1]).await.unwrap();
+        loop {
+            let (packet, _) = time::timeout(Duration::from_secs(1), voice.next())
+                .await
+                .expect("voice packet not received")
+                .unwrap()
+                .unwrap();
+            if let Some(Packet::Voice(packet)) = packet {
+                assert_eq!(packet.hop_count, 2);
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transmission_sguid() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let udp = UdpSocket::bind(addr).await.unwrap();
+
+        let client = Client::new("Test", 251_000_000, "AM");
+        let client_sguid = client.sguid().to_string();
+        let (_tx, rx) = oneshot::channel();
+        let stream = VoiceStream::new(client, addr, None, rx).await.unwrap();
+        let transmission = stream.transmission_control();
+        let (mut sink, stream) = stream.split();
+        tokio::spawn(stream.for_each(|_| async {}));
+        let _socket = listener.accept().await.unwrap();
+        let mut voice = UdpFramed::new(udp, VoiceCodec::new());
+
+        assert!(transmission.set_transmission_sguid("too short").is_err());
+        let relayed_sguid = message::create_sguid();
+        transmission.set_transmission_sguid(&relayed_sguid).unwrap();
+        sink.send(vec![1]).await.unwrap();
+        // both sguids are the client's one again after the transmission
+        transmission.end_transmission();
+        sink.send(vec![2]).await.unwrap();
+
+        let mut sguids = Vec::new();
+        while sguids.len() < 2 {
+            let (packet, _) = time::timeout(Duration::from_secs(1), voice.next())
+                .await
+                .expect("voice packet not received")
+                .unwrap()
+                .unwrap();
+            if let Some(Packet::Voice(packet)) = packet {
+                sguids.push((
+                    String::from_utf8(packet.transmission_sguid.to_vec()).unwrap(),
+                    String::from_utf8(packet.client_sguid.to_vec()).unwrap(),
+                ));
+            }
+        }
+        assert_eq!(
+            sguids,
+            vec![
+                (relayed_sguid, client_sguid.clone()),
+                (client_sguid.clone(), client_sguid),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_bind_addr() {
+        let addr = local_bind_addr(&"127.0.0.1:5002".parse().unwrap(), None).unwrap();
+        assert_eq!(addr, "0.0.0.0:0".parse().unwrap());
+
+        let addr = local_bind_addr(&"[2001:db8::1]:5002".parse().unwrap(), None).unwrap();
+        assert!(addr.is_ipv6());
+        assert_eq!(addr, "[::]:0".parse().unwrap());
+
+        let local_ip = "192.168.1.10".parse().unwrap();
+        let addr = local_bind_addr(&"127.0.0.1:5002".parse().unwrap(), Some(local_ip)).unwrap();
+        assert_eq!(addr, "192.168.1.10:0".parse().unwrap());
+        assert!(local_bind_addr(&"[2001:db8::1]:5002".parse().unwrap(), Some(local_ip)).is_err());
+    }
+
+    #[test]
+    fn test_configured_local_addr() {
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        client.set_local_addr("127.0.0.1".parse().unwrap());
+        let udp = bind_udp_socket(&"127.0.0.1:5002".parse().unwrap(), &client).unwrap();
+        assert_eq!(udp.local_addr().unwrap().ip(), client.local_addr().unwrap());
+
+        // binding to an address of none of the machine's interfaces fails
+        client.set_local_addr("192.0.2.1".parse().unwrap());
+        let err = bind_udp_socket(&"127.0.0.1:5002".parse().unwrap(), &client).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("failed to bind to local address 192.0.2.1"));
+    }
+
+    #[test]
+    fn test_udp_buffer_sizes() {
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        client.set_udp_send_buffer_size(64 * 1024).unwrap();
+        client.set_udp_recv_buffer_size(96 * 1024).unwrap();
+        assert!(client.set_udp_recv_buffer_size(0).is_err());
+
+        let addr = "127.0.0.1:5002".parse().unwrap();
+        let socket = Socket::from(bind_udp_socket(&addr, &client).unwrap());
+
+        // the OS might round the sizes up (e.g. Linux doubles them), but caps them at its maximum
+        // (e.g. Linux at `wmem_max`/`rmem_max`), which is what the largest possible sizes end up as
+        let mut unlimited = Client::new("Test", 251_000_000, "AM");
+        unlimited
+            .set_udp_send_buffer_size(i32::MAX as usize)
+            .unwrap();
+        unlimited
+            .set_udp_recv_buffer_size(i32::MAX as usize)
+            .unwrap();
+        let max = Socket::from(bind_udp_socket(&addr, &unlimited).unwrap());
+
+        assert!(
+            socket.send_buffer_size().unwrap() >= (64 * 1024).min(max.send_buffer_size().unwrap())
+        );
+        assert!(
+            socket.recv_buffer_size().unwrap() >= (96 * 1024).min(max.recv_buffer_size().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_connection() {
+        // skip if IPv6 isn't available on the machine running the tests
+        let mut listener = match TcpListener::bind("[::1]:0").await {
+            Ok(listener) => listener,
+            Err(_) => return,
+        };
+        let addr = listener.local_addr().unwrap();
+        let mut udp = UdpSocket::bind(addr).await.unwrap();
+
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        client
+            .set_voice_ping_interval(Duration::from_millis(50))
+            .unwrap();
+        let (_tx, rx) = oneshot::channel();
+        let (_game_tx, game_source) = mpsc::unbounded();
+        let stream = VoiceStream::new(client, addr, Some(game_source), rx)
+            .await
+            .unwrap();
+        tokio::spawn(stream.for_each(|_| async {}));
+        let _socket = listener.accept().await.unwrap();
+
+        let mut buf = [0; 64];
+        let len = time::timeout(Duration::from_secs(1), udp.recv(&mut buf))
+            .await
+            .expect("no ping received over IPv6")
+            .unwrap();
+        assert_eq!(len, 22);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_signal() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = Client::new("Test", 251_000_000, "AM");
+        let (tx, rx) = oneshot::channel();
+        let mut stream = VoiceStream::new(client, addr, None, rx).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut messages = FramedRead::new(socket, MessagesCodec::new());
+
+        tx.send(()).unwrap();
+
+        // the voice stream ends instead of yielding an error
+        let next = time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .expect("voice stream did not end");
+        assert!(next.is_none());
+
+        // the TCP connection to the server is closed
+        loop {
+            let msg = time::timeout(Duration::from_secs(1), messages.next())
+                .await
+                .expect("TCP connection not closed");
+            if msg.is_none() {
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_voice_queue_is_pending() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut udp = UdpSocket::bind(addr).await.unwrap();
+
+        let client = Client::new("Test", 251_000_000, "AM");
+        let (_tx, rx) = oneshot::channel();
+        let mut stream = VoiceStream::new(client, addr, None, rx).await.unwrap();
+        let _socket = listener.accept().await.unwrap();
+
+        // the queue isn't drained as long as the stream isn't polled
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut queued = 0;
+        while let Poll::Ready(result) = Pin::new(&mut stream).poll_ready(&mut cx) {
+            result.unwrap();
+            Pin::new(&mut stream).start_send(vec![0; 16]).unwrap();
+            queued += 1;
+            assert!(
+                queued <= VOICE_QUEUE_CAPACITY + 1,
+                "voice queue never got full"
+            );
+        }
+        assert_eq!(queued, VOICE_QUEUE_CAPACITY + 1);
+        assert!(stream.saturated);
+        assert_eq!(stream.saturations, 1);
+
+        // polling the stream drains the queue, and all queued packets are sent
+        let _ = time::timeout(Duration::from_millis(100), stream.next()).await;
+        let mut received = 0;
+        let mut buf = [0; 128];
+        while received < queued {
+            let len = time::timeout(Duration::from_secs(1), udp.recv(&mut buf))
+                .await
+                .expect("queued voice packet was dropped")
+                .unwrap();
+            // skip voice pings
+            if len > 22 {
+                received += 1;
+            }
+        }
+
+        assert!(Pin::new(&mut stream).poll_ready(&mut cx).is_ready());
+        assert!(!stream.saturated);
+    }
+
+    #[test]
+    fn test_zero_interval() {
+        let mut client = Client::new("Test", 251_000_000, "AM");
+        assert!(client
+            .set_voice_ping_interval(Duration::from_secs(0))
+            .is_err());
+        assert_eq!(client.voice_ping_interval(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_udp_health() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(15);
+        let mut health = UdpHealth::new(start);
+
+        // pongs are received in time
+        assert!(!health.pong_received(start + Duration::from_secs(5)));
+        assert!(!health.check(start + Duration::from_secs(10), timeout));
+        assert!(health.healthy);
+
+        // pongs are missing
+        assert!(!health.check(start + Duration::from_secs(15), timeout));
+        assert!(health.check(start + Duration::from_secs(25), timeout));
+        assert!(!health.healthy);
+        // only flips once
+        assert!(!health.check(start + Duration::from_secs(30), timeout));
+
+        // recovers once a pong is received again
+        assert!(health.pong_received(start + Duration::from_secs(35)));
+        assert!(health.healthy);
+    }
+}
@@ -2,11 +2,41 @@ cdata = {
   DCSATIS = _("DCS ATIS USING AMAZON POLLY AND GOOGLE TEXT TO SPEECH"),
   DATIS_DEFAULT_VOICE = _("Default Voice:"),
   DATIS_CLOUD_ACCESS_KEY = _("Google Cloud Access Key:"),
+  DATIS_CLOUD_REGION = _("Google Cloud Region (optional):"),
   DATIS_AWS_ACCESS_KEY = _("Amazon Access Key:"),
   DATIS_AWS_PRIVATE_KEY = _("Amazon Private Key:"),
   DATIS_AWS_REGION = _("Amazon Region:"),
   DATIS_AWS_REGION_TEXT = ("See available regions at https://docs.aws.amazon.com/general/latest/gr/rande.html#endpoint-tables."),
+  DATIS_PROXY = _("HTTP Proxy (optional):"),
+  DATIS_TTS_TIMEOUT = _("TTS Timeout (10s):"),
   DATIS_SRS_SERVER_PORT = _("SRS Server Port (5002):"),
+  DATIS_SRS_COALITION_PASSWORD = _("SRS Coalition Password:"),
+  DATIS_SRS_NAME_PREFIX = _("SRS Name Prefix (optional):"),
+  DATIS_SRS_NAME_SUFFIX = _("SRS Name Suffix (optional):"),
+  DATIS_SRS_VOICE_PING_INTERVAL = _("SRS Voice Ping Interval (5s):"),
+  DATIS_SRS_POSITION_UPDATE_INTERVAL = _("SRS Position Update Interval (60s):"),
+  DATIS_SRS_RADIO_UPDATE_INTERVAL = _("SRS Radio Update Interval (30s):"),
+  DATIS_SRS_RECONNECT_ON_BLOCKED_UDP = _("Reconnect to SRS if the UDP Voice Connection seems blocked"),
+  DATIS_SRS_UDP_SEND_BUFFER_SIZE = _("SRS UDP Send Buffer in KiB (optional):"),
+  DATIS_SRS_UDP_RECV_BUFFER_SIZE = _("SRS UDP Receive Buffer in KiB (optional):"),
+  DATIS_SRS_LOCAL_ADDRESS = _("SRS Local Address (optional):"),
+  DATIS_RANDOM_SEED = _("Random Seed (optional):"),
+  DATIS_VOICE_BALANCING = _("Voice Balancing (optional):"),
+  DATIS_METAR_URL = _("METAR URL (optional):"),
+  DATIS_RADIO_EFFECTS = _("Radio Effects (0-100%):"),
+  DATIS_LEAD_IN_TONE = _("Lead-in Tone Hz/ms (optional):"),
+  DATIS_AUTO_ATIS = _("Auto ATIS Base Frequency (optional):"),
+  DATIS_SAMPLE_RATE = _("Sample Rate in Hz (optional):"),
+  DATIS_WATCHDOG_TIMEOUT = _("Station Watchdog Timeout (600s):"),
+  DATIS_WIND_ROUNDING = _("Wind Direction Rounding (10/5/exact):"),
+  DATIS_PRESSURE_ROUNDING = _("Pressure Rounding (US/ICAO):"),
+  DATIS_CARRIER_CASE1_MINIMUMS = _("Carrier Case I Minimums (3000/5):"),
+  DATIS_CARRIER_CASE2_MINIMUMS = _("Carrier Case II Minimums (1000/5):"),
+  DATIS_MAX_REPORT_LENGTH = _("Max. Report Length (5000 chars):"),
+  DATIS_MULTIPLEX_STATIONS = _("Multiplex Airfield Stations over one SRS Connection"),
+  DATIS_SRS_NAME_TEMPLATE = _("SRS Client Name Template (optional):"),
+  DATIS_SRS_HOP_COUNT = _("SRS Hop Count (0):"),
   DATIS_DEBUG_LOGGING = _("Enable Debug Logging"),
-  DATIS_DEBUG_LOGGING_HELP = _("Add additional log output Saved Games\\Logs\\DATIS.log.")
+  DATIS_DEBUG_LOGGING_HELP = _("Add additional log output Saved Games\\Logs\\DATIS.log."),
+  DATIS_DEBUG_INFO = _("Write everything extracted from the mission to Saved Games\\DATIS\\debug-info.json")
 }
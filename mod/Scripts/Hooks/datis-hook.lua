@@ -68,6 +68,27 @@ function datis_next()
   end
 end
 
+function datis_getWeather(params)
+  local position = {
+    x = params.x,
+    y = params.alt,
+    z = params.y,
+  }
+  local wind = Weather.getGroundWindAtPoint({
+    position = position
+  })
+  local temp, pressure = Weather.getTemperatureAndPressureAtPoint({
+    position = position
+  })
+
+  return {
+    windSpeed = wind.v,
+    windDir = wind.a,
+    temp = temp,
+    pressure = pressure,
+  }
+end
+
 function datis_handleRequest(method, params)
   -- log.write("[DATIS]", log.INFO, "RECV " .. method .. " " .. params)
 
@@ -76,25 +97,18 @@ function datis_handleRequest(method, params)
   end
 
   if method == "get_weather" then
-    local position = {
-      x = params.x,
-      y = params.alt,
-      z = params.y,
+    return {
+      result = net.lua2json(datis_getWeather(params))
     }
-    local wind = Weather.getGroundWindAtPoint({
-      position = position
-    })
-    local temp, pressure = Weather.getTemperatureAndPressureAtPoint({
-      position = position
-    })
+
+  elseif method == "get_weather_batch" then
+    local results = {}
+    for i, position in ipairs(params.positions) do
+      results[i] = datis_getWeather(position)
+    end
 
     return {
-      result = net.lua2json({
-        windSpeed = wind.v,
-        windDir = wind.a,
-        temp = temp,
-        pressure = pressure,
-      })
+      result = net.lua2json(results)
     }
 
   elseif method == "get_unit_position" then